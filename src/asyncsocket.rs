@@ -0,0 +1,631 @@
+use crate::attr::*;
+use crate::channels::ChannelSpec;
+use crate::cmd::Nl80211Cmd;
+use crate::decode::{decode_interface, decode_wiphy};
+use crate::error::Nl80211Error;
+use crate::events::{McastGroup, Nl80211Event, Nl80211Events};
+use crate::interface::{Interface, InterfaceId, InterfaceOptions, MonitorFlags};
+use crate::ntsocket::{
+    channel_spec_request, create_interface_request, delete_interface_request, frequency_request,
+    genl_request, new_interface_reply, powersave_off_request, regdomain_reply, regdomain_request,
+    regdomains_reply, scan_group, scan_outcome, scan_request, scan_results_reply,
+    scan_results_request, set_interface_type_request, set_regdomain_request, station_reply,
+    station_request, stations_reply, survey_reply, survey_request, NtSocket,
+};
+use crate::phy::{read_driver, WirelessPhy};
+use crate::reg::RegDomain;
+use crate::request::Nl80211Request;
+use crate::rtsocket::{
+    generate_valid_mac, getlink_request, parse_operstate, set_mac_request, set_up_request,
+    LinkEvent, LinkInfo, LinkMonitor, RtSocket,
+};
+use crate::scan::{Bss, ScanRequest};
+use crate::station::StationInfo;
+use crate::survey::SurveyInfo;
+use crate::transport::Transport;
+use futures_core::Stream;
+use neli::consts::{nl::NlmF, nl::Nlmsg, rtnl::Rtm};
+use neli::genl::Genlmsghdr;
+use neli::nl::Nlmsghdr;
+use neli::rtnl::Ifinfomsg;
+use tokio::io::unix::AsyncFd;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+/// Register a non-blocking socket with the reactor of the current tokio runtime.
+fn register<T: AsRawFd>(sock: T) -> Result<AsyncFd<T>, Nl80211Error> {
+    // SAFETY: the socket owns its fd and only closes it when the AsyncFd drops it.
+    unsafe { AsyncFd::register(sock) }
+        .map_err(|err| Nl80211Error::io("connect", io::Error::from(err)))
+}
+
+/// `NtSocket` driven by the tokio reactor, replies are awaited instead of polled.
+pub struct AsyncNtSocket {
+    fd: AsyncFd<NtSocket>,
+}
+
+impl fmt::Debug for AsyncNtSocket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncNtSocket")
+            .field("sock", self.fd.get_ref())
+            .finish()
+    }
+}
+
+impl AsyncNtSocket {
+    /// Must be called from within a tokio runtime.
+    pub fn connect() -> Result<Self, Nl80211Error> {
+        AsyncNtSocket::from_socket(NtSocket::connect()?)
+    }
+
+    pub fn from_socket(sock: NtSocket) -> Result<Self, Nl80211Error> {
+        sock.transport
+            .sock
+            .nonblock()
+            .map_err(|err| Nl80211Error::io("connect", err))?;
+        let fd = register(sock)?;
+        Ok(AsyncNtSocket { fd })
+    }
+
+    /// Like `NtSocket::execute_ack`.
+    pub async fn execute_ack(&mut self, request: Nl80211Request) -> Result<(), Nl80211Error> {
+        self.execute(request, &[NlmF::Request, NlmF::Ack]).await?;
+        Ok(())
+    }
+
+    /// Like `NtSocket::execute_get`.
+    pub async fn execute_get(
+        &mut self,
+        request: Nl80211Request,
+    ) -> Result<Option<Genlmsghdr<Nl80211Cmd, Nl80211Attr>>, Nl80211Error> {
+        Ok(self
+            .execute(request, &[NlmF::Request, NlmF::Ack])
+            .await?
+            .into_iter()
+            .next())
+    }
+
+    /// Like `NtSocket::execute_dump`.
+    pub async fn execute_dump(
+        &mut self,
+        request: Nl80211Request,
+    ) -> Result<Vec<Genlmsghdr<Nl80211Cmd, Nl80211Attr>>, Nl80211Error> {
+        self.execute(request, &[NlmF::Request, NlmF::Dump]).await
+    }
+
+    async fn execute(
+        &mut self,
+        request: Nl80211Request,
+        flags: &[NlmF],
+    ) -> Result<Vec<Genlmsghdr<Nl80211Cmd, Nl80211Attr>>, Nl80211Error> {
+        let command = request.name;
+        let sock = self.fd.get_mut();
+        let request = genl_request(sock.family_id, request, flags)?;
+        let mut transaction = sock.transactor.start(
+            command,
+            &mut sock.transport,
+            request,
+            Nl80211Error::kernel::<Nlmsg>,
+        )?;
+        loop {
+            let mut guard = self
+                .fd
+                .readable_mut()
+                .await
+                .map_err(|err| Nl80211Error::io(command, err))?;
+            let sock = guard.get_inner_mut();
+            if sock
+                .transactor
+                .read(&mut sock.transport, &mut transaction)?
+            {
+                return Ok(transaction.into_payloads());
+            }
+            guard.clear_ready();
+        }
+    }
+
+    pub async fn cmd_get_interfaces(
+        &mut self,
+    ) -> Result<HashMap<InterfaceId, Interface>, Nl80211Error> {
+        let replies = self
+            .execute_dump(Nl80211Request::new(
+                "CMD_GET_INTERFACE",
+                Nl80211Cmd::CmdGetInterface,
+            ))
+            .await?;
+
+        let mut interfaces = HashMap::new();
+        for reply in replies {
+            if reply.cmd != Nl80211Cmd::CmdNewInterface {
+                continue;
+            }
+            let interface = decode_interface(&reply)?;
+            if let Some(id) = interface.id() {
+                interfaces.insert(id, interface);
+            }
+        }
+        Ok(interfaces)
+    }
+
+    pub async fn cmd_get_interface(
+        &mut self,
+        interface_index: u32,
+    ) -> Result<Interface, Nl80211Error> {
+        let reply = self
+            .execute_get(
+                Nl80211Request::new("CMD_GET_INTERFACE", Nl80211Cmd::CmdGetInterface)
+                    .attr(Nl80211Attr::AttrIfindex, interface_index),
+            )
+            .await?;
+
+        match reply {
+            Some(reply) if reply.cmd == Nl80211Cmd::CmdNewInterface => decode_interface(&reply),
+            _ => Err(Nl80211Error::NotFound("Interface".to_string())),
+        }
+    }
+
+    pub async fn cmd_get_wiphy(&mut self, phy: u32) -> Result<WirelessPhy, Nl80211Error> {
+        let replies = self
+            .execute_dump(
+                Nl80211Request::new("CMD_GET_WIPHY", Nl80211Cmd::CmdGetWiphy)
+                    .attr(Nl80211Attr::AttrWiphy, phy)
+                    .flag(Nl80211Attr::AttrSplitWiphyDump),
+            )
+            .await?;
+
+        let mut wiphy = decode_wiphy(&replies)?;
+        wiphy.driver = wiphy.phy_name.as_deref().and_then(read_driver);
+        Ok(wiphy)
+    }
+
+    /// Like `NtSocket::cmd_get_all_wiphy`, lists the phys and then fetches each one on its own.
+    pub async fn cmd_get_all_wiphy(&mut self) -> Result<HashMap<u32, WirelessPhy>, Nl80211Error> {
+        let replies = self
+            .execute_dump(Nl80211Request::new(
+                "CMD_GET_WIPHY",
+                Nl80211Cmd::CmdGetWiphy,
+            ))
+            .await?;
+
+        let mut phys_available: Vec<u32> = Vec::new();
+        for reply in replies {
+            if reply.cmd != Nl80211Cmd::CmdNewWiphy {
+                continue;
+            }
+            let wiphy: u32 = reply
+                .get_attr_handle()
+                .get_attr_payload_as(Nl80211Attr::AttrWiphy)?;
+            if !phys_available.contains(&wiphy) {
+                phys_available.push(wiphy);
+            }
+        }
+
+        let mut phys = HashMap::new();
+        for phy in phys_available {
+            if let Ok(phy_data) = self.cmd_get_wiphy(phy).await {
+                phys.insert(phy, phy_data);
+            }
+        }
+        Ok(phys)
+    }
+
+    pub async fn set_type_vec(
+        &mut self,
+        interface_index: u32,
+        iftype: Nl80211Iftype,
+        active: bool,
+    ) -> Result<(), Nl80211Error> {
+        let flags = if iftype == Nl80211Iftype::IftypeMonitor && active {
+            Some(MonitorFlags::active())
+        } else {
+            None
+        };
+        self.set_interface_type(interface_index, iftype, flags)
+            .await
+    }
+
+    pub async fn set_interface_type(
+        &mut self,
+        interface_index: u32,
+        iftype: Nl80211Iftype,
+        monitor_flags: Option<MonitorFlags>,
+    ) -> Result<(), Nl80211Error> {
+        self.execute_ack(set_interface_type_request(
+            interface_index,
+            iftype,
+            monitor_flags,
+        ))
+        .await
+    }
+
+    pub async fn set_powersave_off(&mut self, interface_index: u32) -> Result<(), Nl80211Error> {
+        self.execute_ack(powersave_off_request(interface_index))
+            .await
+    }
+
+    pub async fn set_frequency(
+        &mut self,
+        interface_index: u32,
+        frequency: u32,
+        chan_width: Nl80211ChanWidth,
+        chan_type: Nl80211ChannelType,
+    ) -> Result<(), Nl80211Error> {
+        self.execute_ack(frequency_request(
+            interface_index,
+            frequency,
+            chan_width,
+            chan_type,
+        ))
+        .await
+    }
+
+    pub async fn set_channel_spec(
+        &mut self,
+        interface_index: u32,
+        spec: &ChannelSpec,
+    ) -> Result<(), Nl80211Error> {
+        self.execute_ack(channel_spec_request(interface_index, spec))
+            .await
+    }
+
+    /// Like `NtSocket::create_interface`.
+    pub async fn create_interface(
+        &mut self,
+        wiphy: u32,
+        name: &str,
+        iftype: Nl80211Iftype,
+        options: &InterfaceOptions,
+    ) -> Result<u32, Nl80211Error> {
+        let reply = self
+            .execute_get(create_interface_request(wiphy, name, iftype, options))
+            .await?;
+        new_interface_reply(reply)
+    }
+
+    /// Like `NtSocket::delete_interface`.
+    pub async fn delete_interface(&mut self, interface_index: u32) -> Result<(), Nl80211Error> {
+        self.execute_ack(delete_interface_request(interface_index))
+            .await
+    }
+
+    /// Like `NtSocket::trigger_scan`, but waits for the scan to finish without a deadline, wrap
+    /// it in a tokio timeout to bound it. Dropping the future before it completes leaves the
+    /// socket subscribed to the scan group until the next scan on it finishes.
+    pub async fn trigger_scan(
+        &mut self,
+        interface_index: u32,
+        request: ScanRequest,
+    ) -> Result<(), Nl80211Error> {
+        let sock = self.fd.get_mut();
+        let group = scan_group(&sock.mcast_groups)?;
+        // Join the scan group before triggering so the completion notification can't be missed.
+        sock.transport
+            .join_group(group)
+            .map_err(|err| Nl80211Error::io("CMD_TRIGGER_SCAN", err))?;
+        sock.transactor.keep_notifications(true);
+        let result = match self
+            .execute_ack(scan_request(interface_index, &request))
+            .await
+        {
+            Ok(()) => self.wait_for_scan(interface_index).await,
+            Err(err) => Err(err),
+        };
+        let sock = self.fd.get_mut();
+        sock.transactor.keep_notifications(false);
+        // Leftover scan notifications are dropped by later requests anyway
+        let _ = sock.transport.leave_group(group);
+        result
+    }
+
+    /// Wait on the scan group for this interface's scan to finish.
+    async fn wait_for_scan(&mut self, interface_index: u32) -> Result<(), Nl80211Error> {
+        loop {
+            let mut guard = self
+                .fd
+                .readable_mut()
+                .await
+                .map_err(|err| Nl80211Error::io("CMD_TRIGGER_SCAN", err))?;
+            let sock = guard.get_inner_mut();
+            match sock
+                .transactor
+                .try_notification("CMD_TRIGGER_SCAN", &mut sock.transport)?
+            {
+                Some(msg) => {
+                    if let Some(result) = scan_outcome(sock.family_id, interface_index, &msg) {
+                        return result;
+                    }
+                }
+                None => guard.clear_ready(),
+            }
+        }
+    }
+
+    /// Like `NtSocket::get_scan_results`.
+    pub async fn get_scan_results(
+        &mut self,
+        interface_index: u32,
+    ) -> Result<Vec<Bss>, Nl80211Error> {
+        let replies = self
+            .execute_dump(scan_results_request(interface_index))
+            .await?;
+        scan_results_reply(replies)
+    }
+
+    /// Like `NtSocket::get_station`.
+    pub async fn get_station(
+        &mut self,
+        interface_index: u32,
+        mac: &[u8; 6],
+    ) -> Result<StationInfo, Nl80211Error> {
+        let reply = self
+            .execute_get(station_request(interface_index, Some(mac)))
+            .await?;
+        station_reply(reply)
+    }
+
+    /// Like `NtSocket::dump_stations`.
+    pub async fn dump_stations(
+        &mut self,
+        interface_index: u32,
+    ) -> Result<Vec<StationInfo>, Nl80211Error> {
+        let replies = self
+            .execute_dump(station_request(interface_index, None))
+            .await?;
+        stations_reply(replies)
+    }
+
+    /// Like `NtSocket::get_survey`.
+    pub async fn get_survey(
+        &mut self,
+        interface_index: u32,
+    ) -> Result<Vec<SurveyInfo>, Nl80211Error> {
+        let replies = self.execute_dump(survey_request(interface_index)).await?;
+        survey_reply(replies)
+    }
+
+    /// Like `NtSocket::get_regdomain`.
+    pub async fn get_regdomain(&mut self, wiphy: Option<u32>) -> Result<RegDomain, Nl80211Error> {
+        let reply = self.execute_get(regdomain_request(wiphy)).await?;
+        regdomain_reply(reply)
+    }
+
+    /// Like `NtSocket::dump_regdomains`.
+    pub async fn dump_regdomains(&mut self) -> Result<Vec<RegDomain>, Nl80211Error> {
+        let replies = self.execute_dump(regdomain_request(None)).await?;
+        regdomains_reply(replies)
+    }
+
+    /// Like `NtSocket::request_regdomain`.
+    pub async fn request_regdomain(&mut self, alpha2: &str) -> Result<(), Nl80211Error> {
+        self.execute_ack(set_regdomain_request(alpha2)?).await
+    }
+}
+
+/// `RtSocket` driven by the tokio reactor.
+pub struct AsyncRtSocket {
+    fd: AsyncFd<RtSocket>,
+}
+
+impl fmt::Debug for AsyncRtSocket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncRtSocket")
+            .field("sock", self.fd.get_ref())
+            .finish()
+    }
+}
+
+impl AsyncRtSocket {
+    /// Must be called from within a tokio runtime.
+    pub fn connect() -> Result<Self, Nl80211Error> {
+        let fd = register(RtSocket::connect()?)?;
+        Ok(AsyncRtSocket { fd })
+    }
+
+    /// Send a request and collect the replies until the ack.
+    async fn transact(
+        &mut self,
+        command: &'static str,
+        request: Nlmsghdr<Rtm, Ifinfomsg>,
+    ) -> Result<Vec<Ifinfomsg>, Nl80211Error> {
+        let sock = self.fd.get_mut();
+        let mut transaction = sock.transactor.start(
+            command,
+            &mut sock.transport,
+            request,
+            Nl80211Error::nlmsgerr::<Rtm, Ifinfomsg>,
+        )?;
+        loop {
+            let mut guard = self
+                .fd
+                .readable_mut()
+                .await
+                .map_err(|err| Nl80211Error::io(command, err))?;
+            let sock = guard.get_inner_mut();
+            if sock
+                .transactor
+                .read(&mut sock.transport, &mut transaction)?
+            {
+                return Ok(transaction.into_payloads());
+            }
+            guard.clear_ready();
+        }
+    }
+
+    pub async fn get_interface_status(
+        &mut self,
+        interface: u32,
+    ) -> Result<Operstate, Nl80211Error> {
+        let replies = self
+            .transact("RTM_GETLINK", getlink_request(interface))
+            .await?;
+        match replies.first() {
+            Some(reply) => parse_operstate(reply),
+            None => Ok(Operstate::Unknown),
+        }
+    }
+
+    pub async fn set_interface_up(&mut self, interface_index: u32) -> Result<(), Nl80211Error> {
+        self.transact("RTM_NEWLINK", set_up_request(interface_index, true))
+            .await?;
+        Ok(())
+    }
+
+    pub async fn set_interface_down(&mut self, interface_index: u32) -> Result<(), Nl80211Error> {
+        self.transact("RTM_NEWLINK", set_up_request(interface_index, false))
+            .await?;
+        Ok(())
+    }
+
+    pub async fn set_interface_mac(
+        &mut self,
+        interface_index: u32,
+        mac: &[u8; 6],
+    ) -> Result<(), Nl80211Error> {
+        self.transact("RTM_NEWLINK", set_mac_request(interface_index, mac))
+            .await?;
+        Ok(())
+    }
+
+    pub async fn set_interface_mac_random(
+        &mut self,
+        interface_index: u32,
+    ) -> Result<(), Nl80211Error> {
+        let mac = generate_valid_mac();
+        self.set_interface_mac(interface_index, &mac).await
+    }
+}
+
+/// `Nl80211Events` as a `Stream`, waiting on the reactor for notifications.
+pub struct AsyncNl80211Events {
+    fd: AsyncFd<Nl80211Events>,
+}
+
+impl fmt::Debug for AsyncNl80211Events {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncNl80211Events")
+            .field("events", self.fd.get_ref())
+            .finish()
+    }
+}
+
+impl AsyncNl80211Events {
+    /// Must be called from within a tokio runtime.
+    pub fn subscribe(groups: &[McastGroup]) -> Result<Self, Nl80211Error> {
+        AsyncNl80211Events::new(Nl80211Events::subscribe(groups)?)
+    }
+
+    pub fn subscribe_all() -> Result<Self, Nl80211Error> {
+        AsyncNl80211Events::new(Nl80211Events::subscribe_all()?)
+    }
+
+    pub fn new(events: Nl80211Events) -> Result<Self, Nl80211Error> {
+        events.set_nonblocking(true)?;
+        let fd = register(events)?;
+        Ok(AsyncNl80211Events { fd })
+    }
+
+    pub fn join(&mut self, group: McastGroup) -> Result<(), Nl80211Error> {
+        self.fd.get_mut().join(group)
+    }
+
+    pub async fn next_event(&mut self) -> Result<Nl80211Event, Nl80211Error> {
+        loop {
+            let mut guard = self
+                .fd
+                .readable_mut()
+                .await
+                .map_err(|err| Nl80211Error::io("recv", err))?;
+            match guard.get_inner_mut().next_event()? {
+                Some(event) => return Ok(event),
+                None => guard.clear_ready(),
+            }
+        }
+    }
+}
+
+impl Stream for AsyncNl80211Events {
+    type Item = Result<Nl80211Event, Nl80211Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            let mut guard = match ready!(this.fd.poll_read_ready_mut(cx)) {
+                Ok(guard) => guard,
+                Err(err) => return Poll::Ready(Some(Err(Nl80211Error::io("recv", err)))),
+            };
+            match guard.get_inner_mut().next_event() {
+                Ok(Some(event)) => return Poll::Ready(Some(Ok(event))),
+                Ok(None) => guard.clear_ready(),
+                Err(err) => return Poll::Ready(Some(Err(err))),
+            }
+        }
+    }
+}
+
+/// `LinkMonitor` as a `Stream`, waiting on the reactor for link notifications.
+pub struct AsyncLinkMonitor {
+    fd: AsyncFd<LinkMonitor>,
+}
+
+impl fmt::Debug for AsyncLinkMonitor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncLinkMonitor")
+            .field("monitor", self.fd.get_ref())
+            .finish()
+    }
+}
+
+impl AsyncLinkMonitor {
+    /// Must be called from within a tokio runtime.
+    pub fn connect(wireless_only: bool) -> Result<Self, Nl80211Error> {
+        AsyncLinkMonitor::new(LinkMonitor::connect(wireless_only)?)
+    }
+
+    pub fn new(monitor: LinkMonitor) -> Result<Self, Nl80211Error> {
+        monitor.set_nonblocking(true)?;
+        let fd = register(monitor)?;
+        Ok(AsyncLinkMonitor { fd })
+    }
+
+    pub fn links(&self) -> &HashMap<u32, LinkInfo> {
+        self.fd.get_ref().links()
+    }
+
+    pub async fn next_event(&mut self) -> Result<LinkEvent, Nl80211Error> {
+        loop {
+            let mut guard = self
+                .fd
+                .readable_mut()
+                .await
+                .map_err(|err| Nl80211Error::io("recv", err))?;
+            match guard.get_inner_mut().next_event()? {
+                Some(event) => return Ok(event),
+                None => guard.clear_ready(),
+            }
+        }
+    }
+}
+
+impl Stream for AsyncLinkMonitor {
+    type Item = Result<LinkEvent, Nl80211Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            let mut guard = match ready!(this.fd.poll_read_ready_mut(cx)) {
+                Ok(guard) => guard,
+                Err(err) => return Poll::Ready(Some(Err(Nl80211Error::io("recv", err)))),
+            };
+            match guard.get_inner_mut().next_event() {
+                Ok(Some(event)) => return Poll::Ready(Some(Ok(event))),
+                Ok(None) => guard.clear_ready(),
+                Err(err) => return Poll::Ready(Some(Err(err))),
+            }
+        }
+    }
+}
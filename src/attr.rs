@@ -442,6 +442,16 @@ pub enum Nl80211RateInfo {
     RateInfo160MhzWidth = 10,
     RateInfo10MhzWidth = 11,
     RateInfo5MhzWidth = 12,
+    RateInfoHeMcs = 13,
+    RateInfoHeNss = 14,
+    RateInfoHeGi = 15,
+    RateInfoHeDcm = 16,
+    RateInfoHeRuAlloc = 17,
+    RateInfo320MhzWidth = 18,
+    RateInfoEhtMcs = 19,
+    RateInfoEhtNss = 20,
+    RateInfoEhtGi = 21,
+    RateInfoEhtRuAlloc = 22,
 }
 
 impl NlAttrType for Nl80211RateInfo {}
@@ -564,10 +574,36 @@ pub enum Nl80211BandAttr {
     BandAttrHtAmpduDensity = 6,
     BandAttrVhtMcsSet = 7,
     BandAttrVhtCapa = 8,
+    BandAttrIftypeData = 9,
+    BandAttrEdmgChannels = 10,
+    BandAttrEdmgBwConfig = 11,
+    BandAttrS1gMcsNssSet = 12,
+    BandAttrS1gCapa = 13,
 }
 
 impl NlAttrType for Nl80211BandAttr {}
 
+/// nl80211BandIftypeAttr
+///
+/// Enumeration from nl80211/nl80211.h
+#[neli_enum(serialized_type = "u16")]
+pub enum Nl80211BandIftypeAttr {
+    BandIftypeAttrInvalid = 0,
+    BandIftypeAttrIftypes = 1,
+    BandIftypeAttrHeCapMac = 2,
+    BandIftypeAttrHeCapPhy = 3,
+    BandIftypeAttrHeCapMcsSet = 4,
+    BandIftypeAttrHeCapPpe = 5,
+    BandIftypeAttrHe6ghzCapa = 6,
+    BandIftypeAttrVendorElems = 7,
+    BandIftypeAttrEhtCapMac = 8,
+    BandIftypeAttrEhtCapPhy = 9,
+    BandIftypeAttrEhtCapMcsSet = 10,
+    BandIftypeAttrEhtCapPpe = 11,
+}
+
+impl NlAttrType for Nl80211BandIftypeAttr {}
+
 /// nl80211FrequencyAttr
 ///
 /// Enumeration from nl80211/nl80211.h:2833
@@ -1217,6 +1253,7 @@ pub enum Nl80211IfCombinationAttrs {
     IfaceCombNumChannels = 4,
     IfaceCombRadarDetectWidths = 5,
     IfaceCombRadarDetectRegions = 6,
+    IfaceCombBiMinGcd = 7,
 }
 
 impl NlAttrType for Nl80211IfCombinationAttrs {}
@@ -1316,7 +1353,7 @@ impl NlAttrType for Nl80211TdlsOperation {}
 /// nl80211FeatureFlags
 ///
 /// Enumeration from nl80211/nl80211.h:4526
-#[neli_enum(serialized_type = "u16")]
+#[neli_enum(serialized_type = "u32")]
 pub enum Nl80211FeatureFlags {
     FeatureSkTxStatus = 1 << 0,
     FeatureHtIbss = 1 << 1,
@@ -1333,25 +1370,23 @@ pub enum Nl80211FeatureFlags {
     FeatureP2pGoOppps = 1 << 12,
     FeatureAdvertiseChanLimits = 1 << 14,
     FeatureFullApClientState = 1 << 15,
-    // FeatureUserspaceMpm          =1 << 16,
-    // FeatureActiveMonitor         =1 << 17,
-    // FeatureApModeChanWidthChange =1 << 18,
-    // FeatureDsParamSetIeInProbes  =1 << 19,
-    // FeatureWfaTpcIeInProbes      =1 << 20,
-    // FeatureQuiet                 =1 << 21,
-    // FeatureTxPowerInsertion      =1 << 22,
-    // FeatureAcktoEstimation       =1 << 23,
-    // FeatureStaticSmps            =1 << 24,
-    // FeatureDynamicSmps           =1 << 25,
-    // FeatureSupportsWmmAdmission  =1 << 26,
-    // FeatureMacOnCreate           =1 << 27,
-    // FeatureTdlsChannelSwitch     =1 << 28,
-    // FeatureScanRandomMacAddr     =1 << 29,
-    // FeatureSchedScanRandomMacAddr=1 << 30,
-    // FeatureNdRandomMacAddr       =1 << 31
-}
-
-impl NlAttrType for Nl80211FeatureFlags {}
+    FeatureUserspaceMpm = 1 << 16,
+    FeatureActiveMonitor = 1 << 17,
+    FeatureApModeChanWidthChange = 1 << 18,
+    FeatureDsParamSetIeInProbes = 1 << 19,
+    FeatureWfaTpcIeInProbes = 1 << 20,
+    FeatureQuiet = 1 << 21,
+    FeatureTxPowerInsertion = 1 << 22,
+    FeatureAcktoEstimation = 1 << 23,
+    FeatureStaticSmps = 1 << 24,
+    FeatureDynamicSmps = 1 << 25,
+    FeatureSupportsWmmAdmission = 1 << 26,
+    FeatureMacOnCreate = 1 << 27,
+    FeatureTdlsChannelSwitch = 1 << 28,
+    FeatureScanRandomMacAddr = 1 << 29,
+    FeatureSchedScanRandomMacAddr = 1 << 30,
+    FeatureNdRandomMacAddr = 1 << 31,
+}
 
 /// nl80211ExtFeatureIndex
 ///
@@ -1367,6 +1402,66 @@ pub enum Nl80211ExtFeatureIndex {
     ExtFeatureBeaconRateLegacy = 6,
     ExtFeatureBeaconRateHt = 7,
     ExtFeatureBeaconRateVht = 8,
+    ExtFeatureFilsSta = 9,
+    ExtFeatureMgmtTxRandomTa = 10,
+    ExtFeatureMgmtTxRandomTaConnected = 11,
+    ExtFeatureSchedScanRelativeRssi = 12,
+    ExtFeatureCqmRssiList = 13,
+    ExtFeatureFilsSkOffload = 14,
+    ExtFeature4wayHandshakeStaPsk = 15,
+    ExtFeature4wayHandshakeSta1x = 16,
+    ExtFeatureFilsMaxChannelTime = 17,
+    ExtFeatureAcceptBcastProbeResp = 18,
+    ExtFeatureOceProbeReqHighTxRate = 19,
+    ExtFeatureOceProbeReqDeferralSuppression = 20,
+    ExtFeatureMfpOptional = 21,
+    ExtFeatureLowSpanScan = 22,
+    ExtFeatureLowPowerScan = 23,
+    ExtFeatureHighAccuracyScan = 24,
+    ExtFeatureDfsOffload = 25,
+    ExtFeatureControlPortOverNl80211 = 26,
+    ExtFeatureAckSignalSupport = 27,
+    ExtFeatureTxqs = 28,
+    ExtFeatureScanRandomSn = 29,
+    ExtFeatureScanMinPreqContent = 30,
+    ExtFeatureCanReplacePtk0 = 31,
+    ExtFeatureEnableFtmResponder = 32,
+    ExtFeatureAirtimeFairness = 33,
+    ExtFeatureApPmksaCaching = 34,
+    ExtFeatureSchedScanBandSpecificRssiThold = 35,
+    ExtFeatureExtKeyId = 36,
+    ExtFeatureStaTxPwr = 37,
+    ExtFeatureSaeOffload = 38,
+    ExtFeatureVlanOffload = 39,
+    ExtFeatureAql = 40,
+    ExtFeatureBeaconProtection = 41,
+    ExtFeatureControlPortNoPreauth = 42,
+    ExtFeatureProtectedTwt = 43,
+    ExtFeatureDelIbssSta = 44,
+    ExtFeatureMulticastRegistrations = 45,
+    ExtFeatureBeaconProtectionClient = 46,
+    ExtFeatureScanFreqKhz = 47,
+    ExtFeatureControlPortOverNl80211TxStatus = 48,
+    ExtFeatureOperatingChannelValidation = 49,
+    ExtFeature4wayHandshakeApPsk = 50,
+    ExtFeatureSaeOffloadAp = 51,
+    ExtFeatureFilsDiscovery = 52,
+    ExtFeatureUnsolBcastProbeResp = 53,
+    ExtFeatureBeaconRateHe = 54,
+    ExtFeatureSecureLtf = 55,
+    ExtFeatureSecureRtt = 56,
+    ExtFeatureProtRangeNegoAndMeasure = 57,
+    ExtFeatureBssColor = 58,
+    ExtFeatureFilsCryptoOffload = 59,
+    ExtFeatureRadarBackground = 60,
+    ExtFeaturePoweredAddrChange = 61,
+    ExtFeaturePunct = 62,
+    ExtFeatureSecureNan = 63,
+    ExtFeatureAuthAndDeauthRandomTa = 64,
+    ExtFeatureOweOffload = 65,
+    ExtFeatureOweOffloadAp = 66,
+    ExtFeatureDfsConcurrent = 67,
+    ExtFeatureSppAmsduSupport = 68,
 }
 
 impl NlAttrType for Nl80211ExtFeatureIndex {}
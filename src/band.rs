@@ -0,0 +1,755 @@
+use crate::attr::Nl80211Iftype;
+
+/// A legacy rate of a band, from `BandAttrRates`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bitrate {
+    pub rate: u32,            // BitrateAttrRate (100 kbps)
+    pub short_preamble: bool, // BitrateAttr2ghzShortpreamble
+}
+
+impl Bitrate {
+    /// Bitrate in Mbps
+    pub fn mbps(&self) -> f32 {
+        self.rate as f32 / 10.0
+    }
+}
+
+// HT capability info field (802.11-2020 9.4.2.55.2)
+const HT_CAP_LDPC: u16 = 1 << 0;
+const HT_CAP_SUP_WIDTH_20_40: u16 = 1 << 1;
+const HT_CAP_GREENFIELD: u16 = 1 << 4;
+const HT_CAP_SGI_20: u16 = 1 << 5;
+const HT_CAP_SGI_40: u16 = 1 << 6;
+const HT_CAP_TX_STBC: u16 = 1 << 7;
+const HT_CAP_RX_STBC_SHIFT: u16 = 8;
+const HT_CAP_MAX_AMSDU: u16 = 1 << 11;
+
+// Data rate of HT MCS 0-7 for one spatial stream with the long guard interval (Mbps)
+const HT_RATES_20MHZ: [f32; 8] = [6.5, 13.0, 19.5, 26.0, 39.0, 52.0, 58.5, 65.0];
+const HT_RATES_40MHZ: [f32; 8] = [13.5, 27.0, 40.5, 54.0, 81.0, 108.0, 121.5, 135.0];
+
+/// HT (802.11n) capabilities of a band, or of a station from its HT Capabilities element
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct HtCapabilities {
+    pub capa: u16,                  // BandAttrHtCapa, the raw capability info
+    pub ldpc: bool,                 // LDPC coding
+    pub ht40: bool,                 // 20/40 MHz channels
+    pub greenfield: bool,           // HT greenfield format
+    pub sgi20: bool,                // short guard interval at 20 MHz
+    pub sgi40: bool,                // short guard interval at 40 MHz
+    pub tx_stbc: bool,              // STBC transmission
+    pub rx_stbc: u8,                // STBC reception, number of spatial streams
+    pub max_amsdu_len: u16,         // 3839 or 7935 bytes
+    pub ampdu_factor: u8,           // BandAttrHtAmpduFactor
+    pub ampdu_density: u8,          // BandAttrHtAmpduDensity
+    pub rx_mcs_mask: [u8; 10],      // BandAttrHtMcsSet, bit n set if MCS n can be received
+    pub rx_highest: u16,            // BandAttrHtMcsSet, highest receive rate (Mbps), 0 if unset
+    pub tx_params: u8,              // BandAttrHtMcsSet, transmit MCS set parameters
+    pub extended_capabilities: u16, // element only, HT extended capabilities
+    pub txbf_capabilities: u32,     // element only, transmit beamforming capabilities
+    pub asel_capabilities: u8,      // element only, antenna selection capabilities
+}
+
+impl HtCapabilities {
+    /// Decode the body of a HT Capabilities element (802.11-2020 9.4.2.55).
+    pub fn parse(data: &[u8]) -> Option<HtCapabilities> {
+        if data.len() < 26 {
+            return None;
+        }
+        let mut ht = HtCapabilities {
+            ampdu_factor: data[2] & 0x3,
+            ampdu_density: (data[2] >> 2) & 0x7,
+            extended_capabilities: u16::from_le_bytes([data[19], data[20]]),
+            txbf_capabilities: u32::from_le_bytes([data[21], data[22], data[23], data[24]]),
+            asel_capabilities: data[25],
+            ..Default::default()
+        };
+        ht.set_capa(u16::from_le_bytes([data[0], data[1]]));
+        ht.set_mcs_set(&data[3..19]);
+        Some(ht)
+    }
+
+    pub(crate) fn set_capa(&mut self, capa: u16) {
+        self.capa = capa;
+        self.ldpc = capa & HT_CAP_LDPC != 0;
+        self.ht40 = capa & HT_CAP_SUP_WIDTH_20_40 != 0;
+        self.greenfield = capa & HT_CAP_GREENFIELD != 0;
+        self.sgi20 = capa & HT_CAP_SGI_20 != 0;
+        self.sgi40 = capa & HT_CAP_SGI_40 != 0;
+        self.tx_stbc = capa & HT_CAP_TX_STBC != 0;
+        self.rx_stbc = ((capa >> HT_CAP_RX_STBC_SHIFT) & 0x3) as u8;
+        self.max_amsdu_len = if capa & HT_CAP_MAX_AMSDU != 0 {
+            7935
+        } else {
+            3839
+        };
+    }
+
+    /// Set from the 16 byte supported MCS set, shorter payloads are ignored.
+    pub(crate) fn set_mcs_set(&mut self, mcs_set: &[u8]) {
+        if mcs_set.len() < 13 {
+            return;
+        }
+        self.rx_mcs_mask.copy_from_slice(&mcs_set[..10]);
+        self.rx_highest = u16::from_le_bytes([mcs_set[10], mcs_set[11]]) & 0x3ff;
+        self.tx_params = mcs_set[12];
+    }
+
+    /// The MCS indexes that can be received.
+    pub fn mcs_indexes(&self) -> Vec<u8> {
+        (0..77u8)
+            .filter(|mcs| self.rx_mcs_mask[*mcs as usize / 8] & (1 << (mcs % 8)) != 0)
+            .collect()
+    }
+
+    /// Maximum A-MPDU length in bytes
+    pub fn max_ampdu_len(&self) -> u32 {
+        (1 << (13 + self.ampdu_factor.min(3))) - 1
+    }
+
+    /// Minimum time between the start of two MPDUs in an A-MPDU (ns)
+    pub fn min_mpdu_spacing_ns(&self) -> u32 {
+        match self.ampdu_density {
+            0 => 0,
+            density => 125 << density.min(7),
+        }
+    }
+
+    /// Highest receive rate in Mbps, the advertised one or the rate of the highest equal
+    /// modulation MCS (0-31) at the widest width.
+    pub fn max_rate_mbps(&self) -> Option<f32> {
+        if self.rx_highest != 0 {
+            return Some(self.rx_highest as f32);
+        }
+        let mcs = self
+            .mcs_indexes()
+            .into_iter()
+            .filter(|mcs| *mcs < 32)
+            .max()?;
+        let (rates, sgi) = if self.ht40 {
+            (&HT_RATES_40MHZ, self.sgi40)
+        } else {
+            (&HT_RATES_20MHZ, self.sgi20)
+        };
+        let rate = rates[mcs as usize % 8] * (mcs / 8 + 1) as f32;
+        Some(if sgi { rate * 10.0 / 9.0 } else { rate })
+    }
+
+    pub fn summary(&self) -> String {
+        let mut parts: Vec<String> = Vec::new();
+        for (supported, name) in [
+            (self.ldpc, "LDPC"),
+            (self.ht40, "HT40"),
+            (self.greenfield, "Greenfield"),
+            (self.sgi20, "SGI20"),
+            (self.sgi40, "SGI40"),
+            (self.tx_stbc, "TX-STBC"),
+        ] {
+            if supported {
+                parts.push(name.to_string());
+            }
+        }
+        if self.rx_stbc > 0 {
+            parts.push(format!("RX-STBC{}", self.rx_stbc));
+        }
+        parts.push(format!("A-MSDU {}", self.max_amsdu_len));
+        parts.push(format!("A-MPDU {}", self.max_ampdu_len()));
+        if let Some(mcs) = self.mcs_indexes().into_iter().filter(|mcs| *mcs < 32).max() {
+            parts.push(format!("MCS 0-{}", mcs));
+        }
+        if let Some(rate) = self.max_rate_mbps() {
+            parts.push(format!("{:.1} Mbps", rate));
+        }
+        parts.join(" ")
+    }
+}
+
+// VHT capabilities info field (802.11-2020 9.4.2.157.2)
+const VHT_CAP_MAX_MPDU_MASK: u32 = 0x3;
+const VHT_CAP_SUPP_CHAN_WIDTH_SHIFT: u32 = 2;
+const VHT_CAP_RXLDPC: u32 = 1 << 4;
+const VHT_CAP_SGI_80: u32 = 1 << 5;
+const VHT_CAP_SGI_160: u32 = 1 << 6;
+const VHT_CAP_TXSTBC: u32 = 1 << 7;
+const VHT_CAP_RXSTBC_SHIFT: u32 = 8;
+const VHT_CAP_SU_BEAMFORMER: u32 = 1 << 11;
+const VHT_CAP_SU_BEAMFORMEE: u32 = 1 << 12;
+const VHT_CAP_BEAMFORMEE_STS_SHIFT: u32 = 13;
+const VHT_CAP_SOUNDING_DIMENSIONS_SHIFT: u32 = 16;
+const VHT_CAP_MU_BEAMFORMER: u32 = 1 << 19;
+const VHT_CAP_MU_BEAMFORMEE: u32 = 1 << 20;
+const VHT_CAP_MAX_AMPDU_EXP_SHIFT: u32 = 23;
+
+// Data rate of VHT MCS 0-9 for one spatial stream at 80 MHz with the long guard interval (Mbps)
+const VHT_RATES_80MHZ: [f32; 10] = [
+    29.3, 58.5, 87.8, 117.0, 175.5, 234.0, 263.3, 292.5, 351.0, 390.0,
+];
+
+/// VHT (802.11ac) capabilities of a band, or of a station from its VHT Capabilities element
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct VhtCapabilities {
+    pub capa: u32,         // BandAttrVhtCapa, the raw capability info
+    pub max_mpdu_len: u16, // 3895, 7991 or 11454 bytes
+    pub vht160: bool,      // 160 MHz channels
+    pub vht80p80: bool,    // 80+80 MHz channels
+    pub rx_ldpc: bool,     // LDPC coding
+    pub sgi80: bool,       // short guard interval at 80 MHz
+    pub sgi160: bool,      // short guard interval at 160 and 80+80 MHz
+    pub tx_stbc: bool,     // STBC transmission
+    pub rx_stbc: u8,       // STBC reception, number of spatial streams
+    pub su_beamformer: bool,
+    pub su_beamformee: bool,
+    pub mu_beamformer: bool,
+    pub mu_beamformee: bool,
+    pub beamformee_sts: u8,      // space-time streams as beamformee
+    pub sounding_dimensions: u8, // sounding dimensions as beamformer
+    pub max_ampdu_exp: u8,       // maximum A-MPDU length exponent
+    pub rx_mcs: [Option<u8>; 8], // BandAttrVhtMcsSet, highest receive MCS per number of streams
+    pub tx_mcs: [Option<u8>; 8], // BandAttrVhtMcsSet, highest transmit MCS per number of streams
+    pub rx_highest: u16, // BandAttrVhtMcsSet, highest long GI receive rate (Mbps), 0 if unset
+    pub tx_highest: u16, // BandAttrVhtMcsSet, highest long GI transmit rate (Mbps), 0 if unset
+}
+
+impl VhtCapabilities {
+    /// Decode the body of a VHT Capabilities element (802.11-2020 9.4.2.157).
+    pub fn parse(data: &[u8]) -> Option<VhtCapabilities> {
+        let capa = data.get(0..4)?;
+        let mut vht = VhtCapabilities::default();
+        vht.set_capa(u32::from_le_bytes([capa[0], capa[1], capa[2], capa[3]]));
+        vht.set_mcs_set(data.get(4..12)?);
+        Some(vht)
+    }
+
+    pub(crate) fn set_capa(&mut self, capa: u32) {
+        self.capa = capa;
+        self.max_mpdu_len = match capa & VHT_CAP_MAX_MPDU_MASK {
+            0 => 3895,
+            1 => 7991,
+            _ => 11454,
+        };
+        let widths = (capa >> VHT_CAP_SUPP_CHAN_WIDTH_SHIFT) & 0x3;
+        self.vht160 = widths == 1 || widths == 2;
+        self.vht80p80 = widths == 2;
+        self.rx_ldpc = capa & VHT_CAP_RXLDPC != 0;
+        self.sgi80 = capa & VHT_CAP_SGI_80 != 0;
+        self.sgi160 = capa & VHT_CAP_SGI_160 != 0;
+        self.tx_stbc = capa & VHT_CAP_TXSTBC != 0;
+        self.rx_stbc = ((capa >> VHT_CAP_RXSTBC_SHIFT) & 0x7) as u8;
+        self.su_beamformer = capa & VHT_CAP_SU_BEAMFORMER != 0;
+        self.su_beamformee = capa & VHT_CAP_SU_BEAMFORMEE != 0;
+        self.mu_beamformer = capa & VHT_CAP_MU_BEAMFORMER != 0;
+        self.mu_beamformee = capa & VHT_CAP_MU_BEAMFORMEE != 0;
+        // Both are stored minus one
+        self.beamformee_sts = ((capa >> VHT_CAP_BEAMFORMEE_STS_SHIFT) & 0x7) as u8 + 1;
+        self.sounding_dimensions = ((capa >> VHT_CAP_SOUNDING_DIMENSIONS_SHIFT) & 0x7) as u8 + 1;
+        self.max_ampdu_exp = ((capa >> VHT_CAP_MAX_AMPDU_EXP_SHIFT) & 0x7) as u8;
+    }
+
+    /// Set from the 8 byte supported VHT-MCS and NSS set, shorter payloads are ignored.
+    pub(crate) fn set_mcs_set(&mut self, mcs_set: &[u8]) {
+        if mcs_set.len() < 8 {
+            return;
+        }
+        let word = |i: usize| u16::from_le_bytes([mcs_set[i], mcs_set[i + 1]]);
+        self.rx_mcs = vht_mcs_map(word(0));
+        self.rx_highest = word(2) & 0x1fff;
+        self.tx_mcs = vht_mcs_map(word(4));
+        self.tx_highest = word(6) & 0x1fff;
+    }
+
+    /// Maximum A-MPDU length in bytes
+    pub fn max_ampdu_len(&self) -> u32 {
+        (1 << (13 + self.max_ampdu_exp)) - 1
+    }
+
+    /// Number of spatial streams that can be received
+    pub fn rx_streams(&self) -> u8 {
+        self.rx_mcs.iter().filter(|mcs| mcs.is_some()).count() as u8
+    }
+
+    /// Highest receive rate in Mbps, the advertised one or the rate of the highest MCS and stream
+    /// count at the widest width.
+    pub fn max_rate_mbps(&self) -> Option<f32> {
+        if self.rx_highest != 0 {
+            return Some(self.rx_highest as f32);
+        }
+        let (factor, sgi) = if self.vht160 {
+            (2.0, self.sgi160)
+        } else {
+            (1.0, self.sgi80)
+        };
+        self.rx_mcs
+            .iter()
+            .enumerate()
+            .filter_map(|(nss, mcs)| {
+                mcs.map(|mcs| VHT_RATES_80MHZ[mcs as usize] * factor * (nss + 1) as f32)
+            })
+            .reduce(f32::max)
+            .map(|rate| if sgi { rate * 10.0 / 9.0 } else { rate })
+    }
+
+    pub fn summary(&self) -> String {
+        let mut parts: Vec<String> = Vec::new();
+        for (supported, name) in [
+            (self.vht160, "VHT160"),
+            (self.vht80p80, "VHT80+80"),
+            (self.rx_ldpc, "RX-LDPC"),
+            (self.sgi80, "SGI80"),
+            (self.sgi160, "SGI160"),
+            (self.tx_stbc, "TX-STBC"),
+            (self.su_beamformer, "SU-Beamformer"),
+            (self.su_beamformee, "SU-Beamformee"),
+            (self.mu_beamformer, "MU-Beamformer"),
+            (self.mu_beamformee, "MU-Beamformee"),
+        ] {
+            if supported {
+                parts.push(name.to_string());
+            }
+        }
+        if self.rx_stbc > 0 {
+            parts.push(format!("RX-STBC{}", self.rx_stbc));
+        }
+        parts.push(format!("MPDU {}", self.max_mpdu_len));
+        parts.push(format!("A-MPDU {}", self.max_ampdu_len()));
+        let streams: Vec<String> = self
+            .rx_mcs
+            .iter()
+            .enumerate()
+            .filter_map(|(nss, mcs)| mcs.map(|mcs| format!("{}ss MCS 0-{}", nss + 1, mcs)))
+            .collect();
+        if !streams.is_empty() {
+            parts.push(streams.join(", "));
+        }
+        if let Some(rate) = self.max_rate_mbps() {
+            parts.push(format!("{:.1} Mbps", rate));
+        }
+        parts.join(" ")
+    }
+}
+
+/// Highest MCS per number of spatial streams of a VHT-MCS map, two bits per stream count.
+fn vht_mcs_map(map: u16) -> [Option<u8>; 8] {
+    let mut mcs = [None; 8];
+    for (nss, entry) in mcs.iter_mut().enumerate() {
+        *entry = match (map >> (nss * 2)) & 0x3 {
+            0 => Some(7),
+            1 => Some(8),
+            2 => Some(9),
+            _ => None,
+        };
+    }
+    mcs
+}
+
+/// HE and EHT capabilities the band offers to a set of interface types, from `BandAttrIftypeData`
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct IftypeCapabilities {
+    pub iftypes: Vec<Nl80211Iftype>,         // BandIftypeAttrIftypes
+    pub he: Option<HeCapabilities>,          // BandIftypeAttrHeCap*
+    pub he_6ghz: Option<He6GhzCapabilities>, // BandIftypeAttrHe6ghzCapa
+    pub eht: Option<EhtCapabilities>,        // BandIftypeAttrEhtCap*
+}
+
+// HE MAC capabilities (802.11ax-2021 9.4.2.248.2)
+const HE_MAC_CAP0_TWT_REQ: u8 = 1 << 1;
+const HE_MAC_CAP0_TWT_RES: u8 = 1 << 2;
+
+// HE PHY capabilities (802.11ax-2021 9.4.2.248.3)
+const HE_PHY_CAP0_40MHZ_IN_2G: u8 = 1 << 1;
+const HE_PHY_CAP0_40MHZ_80MHZ_IN_5G: u8 = 1 << 2;
+const HE_PHY_CAP0_160MHZ_IN_5G: u8 = 1 << 3;
+const HE_PHY_CAP0_80PLUS80_MHZ_IN_5G: u8 = 1 << 4;
+const HE_PHY_CAP1_LDPC_CODING_IN_PAYLOAD: u8 = 1 << 5;
+const HE_PHY_CAP3_SU_BEAMFORMER: u8 = 1 << 7;
+const HE_PHY_CAP4_SU_BEAMFORMEE: u8 = 1 << 0;
+const HE_PHY_CAP4_MU_BEAMFORMER: u8 = 1 << 1;
+const HE_PHY_CAP6_PPE_THRESHOLD_PRESENT: u8 = 1 << 7;
+
+// Data rate of HE MCS 0-11 for one spatial stream at 80 MHz with the 0.8us guard interval (Mbps)
+const HE_RATES_80MHZ: [f32; 12] = [
+    36.0, 72.1, 108.1, 144.1, 216.2, 288.2, 324.3, 360.3, 432.4, 480.4, 540.4, 600.5,
+];
+
+/// Highest HE-MCS per number of spatial streams for one channel width
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct HeMcsNss {
+    pub rx: [Option<u8>; 8],
+    pub tx: [Option<u8>; 8],
+}
+
+impl HeMcsNss {
+    fn from_maps(rx: u16, tx: u16) -> Self {
+        HeMcsNss {
+            rx: he_mcs_map(rx),
+            tx: he_mcs_map(tx),
+        }
+    }
+
+    /// Number of spatial streams that can be received
+    pub fn rx_streams(&self) -> u8 {
+        self.rx.iter().filter(|mcs| mcs.is_some()).count() as u8
+    }
+}
+
+/// HE (802.11ax) capabilities of a band for some interface types, or of a station from its HE
+/// Capabilities element
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct HeCapabilities {
+    pub mac_cap: [u8; 6],   // BandIftypeAttrHeCapMac, the raw MAC capabilities
+    pub phy_cap: [u8; 11],  // BandIftypeAttrHeCapPhy, the raw PHY capabilities
+    pub he40_2ghz: bool,    // 40 MHz channels in 2.4 GHz
+    pub he40_80_5ghz: bool, // 40 and 80 MHz channels in 5 and 6 GHz
+    pub he160: bool,        // 160 MHz channels in 5 and 6 GHz
+    pub he80p80: bool,      // 80+80 MHz channels in 5 and 6 GHz
+    pub ldpc: bool,         // LDPC coding in payload
+    pub su_beamformer: bool,
+    pub su_beamformee: bool,
+    pub mu_beamformer: bool,
+    pub twt_requester: bool,
+    pub twt_responder: bool,
+    pub mcs_80: HeMcsNss,            // BandIftypeAttrHeCapMcsSet, up to 80 MHz
+    pub mcs_160: Option<HeMcsNss>,   // BandIftypeAttrHeCapMcsSet, 160 MHz
+    pub mcs_80p80: Option<HeMcsNss>, // BandIftypeAttrHeCapMcsSet, 80+80 MHz
+    pub ppe_thresholds: Vec<u8>,     // BandIftypeAttrHeCapPpe, empty if not present
+}
+
+impl HeCapabilities {
+    /// Decode the body of a HE Capabilities element (802.11ax-2021 9.4.2.248), without the
+    /// Element ID Extension.
+    pub fn parse(data: &[u8]) -> Option<HeCapabilities> {
+        let (mac_cap, rest) = data.split_at_checked(6)?;
+        let (phy_cap, rest) = rest.split_at_checked(11)?;
+        // The MCS and NSS set grows with the channel widths advertised in the PHY capabilities
+        let mut mcs_len = 4;
+        if phy_cap[0] & HE_PHY_CAP0_160MHZ_IN_5G != 0 {
+            mcs_len += 4;
+        }
+        if phy_cap[0] & HE_PHY_CAP0_80PLUS80_MHZ_IN_5G != 0 {
+            mcs_len += 4;
+        }
+        let (mcs_set, ppe) = rest.split_at_checked(mcs_len)?;
+        Some(HeCapabilities::decode(mac_cap, phy_cap, mcs_set, ppe))
+    }
+
+    /// Decode the raw attributes, the PHY capabilities tell which MCS and NSS sets follow.
+    pub(crate) fn decode(mac_cap: &[u8], phy_cap: &[u8], mcs_set: &[u8], ppe: &[u8]) -> Self {
+        let mut he = HeCapabilities::default();
+        let mac_len = mac_cap.len().min(he.mac_cap.len());
+        he.mac_cap[..mac_len].copy_from_slice(&mac_cap[..mac_len]);
+        let phy_len = phy_cap.len().min(he.phy_cap.len());
+        he.phy_cap[..phy_len].copy_from_slice(&phy_cap[..phy_len]);
+
+        let mac = he.mac_cap;
+        let phy = he.phy_cap;
+        he.twt_requester = mac[0] & HE_MAC_CAP0_TWT_REQ != 0;
+        he.twt_responder = mac[0] & HE_MAC_CAP0_TWT_RES != 0;
+        he.he40_2ghz = phy[0] & HE_PHY_CAP0_40MHZ_IN_2G != 0;
+        he.he40_80_5ghz = phy[0] & HE_PHY_CAP0_40MHZ_80MHZ_IN_5G != 0;
+        he.he160 = phy[0] & HE_PHY_CAP0_160MHZ_IN_5G != 0;
+        he.he80p80 = phy[0] & HE_PHY_CAP0_80PLUS80_MHZ_IN_5G != 0;
+        he.ldpc = phy[1] & HE_PHY_CAP1_LDPC_CODING_IN_PAYLOAD != 0;
+        he.su_beamformer = phy[3] & HE_PHY_CAP3_SU_BEAMFORMER != 0;
+        he.su_beamformee = phy[4] & HE_PHY_CAP4_SU_BEAMFORMEE != 0;
+        he.mu_beamformer = phy[4] & HE_PHY_CAP4_MU_BEAMFORMER != 0;
+
+        // Pairs of receive and transmit maps, <= 80 MHz first, then 160 and 80+80 MHz if supported
+        let mut maps = mcs_set.chunks_exact(4).map(|map| {
+            HeMcsNss::from_maps(
+                u16::from_le_bytes([map[0], map[1]]),
+                u16::from_le_bytes([map[2], map[3]]),
+            )
+        });
+        he.mcs_80 = maps.next().unwrap_or_default();
+        if he.he160 {
+            he.mcs_160 = maps.next();
+        }
+        if he.he80p80 {
+            he.mcs_80p80 = maps.next();
+        }
+        if phy[6] & HE_PHY_CAP6_PPE_THRESHOLD_PRESENT != 0 {
+            he.ppe_thresholds = ppe.to_vec();
+        }
+        he
+    }
+
+    /// Highest receive rate in Mbps at the widest width with the 0.8us guard interval.
+    pub fn max_rate_mbps(&self) -> Option<f32> {
+        let (mcs_nss, factor) = match &self.mcs_160 {
+            Some(mcs_160) => (mcs_160, 2.0),
+            None => (&self.mcs_80, 1.0),
+        };
+        mcs_nss
+            .rx
+            .iter()
+            .enumerate()
+            .filter_map(|(nss, mcs)| {
+                mcs.map(|mcs| HE_RATES_80MHZ[mcs as usize] * factor * (nss + 1) as f32)
+            })
+            .reduce(f32::max)
+    }
+
+    pub fn summary(&self) -> String {
+        let mut parts: Vec<String> = Vec::new();
+        for (supported, name) in [
+            (self.he40_2ghz, "HE40/2.4GHz"),
+            (self.he40_80_5ghz, "HE40/HE80"),
+            (self.he160, "HE160"),
+            (self.he80p80, "HE80+80"),
+            (self.ldpc, "LDPC"),
+            (self.su_beamformer, "SU-Beamformer"),
+            (self.su_beamformee, "SU-Beamformee"),
+            (self.mu_beamformer, "MU-Beamformer"),
+            (self.twt_requester, "TWT-Requester"),
+            (self.twt_responder, "TWT-Responder"),
+            (!self.ppe_thresholds.is_empty(), "PPE"),
+        ] {
+            if supported {
+                parts.push(name.to_string());
+            }
+        }
+        let streams: Vec<String> = self
+            .mcs_80
+            .rx
+            .iter()
+            .enumerate()
+            .filter_map(|(nss, mcs)| mcs.map(|mcs| format!("{}ss MCS 0-{}", nss + 1, mcs)))
+            .collect();
+        if !streams.is_empty() {
+            parts.push(streams.join(", "));
+        }
+        if let Some(rate) = self.max_rate_mbps() {
+            parts.push(format!("{:.1} Mbps", rate));
+        }
+        parts.join(" ")
+    }
+}
+
+/// Highest MCS per number of spatial streams of a HE-MCS map, two bits per stream count.
+fn he_mcs_map(map: u16) -> [Option<u8>; 8] {
+    let mut mcs = [None; 8];
+    for (nss, entry) in mcs.iter_mut().enumerate() {
+        *entry = match (map >> (nss * 2)) & 0x3 {
+            0 => Some(7),
+            1 => Some(9),
+            2 => Some(11),
+            _ => None,
+        };
+    }
+    mcs
+}
+
+/// HE 6 GHz band capabilities (802.11ax-2021 9.4.2.263), from `BandIftypeAttrHe6ghzCapa`
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct He6GhzCapabilities {
+    pub capa: u16,                  // the raw capabilities
+    pub min_mpdu_start_spacing: u8, // encoded like HtCapabilities::ampdu_density
+    pub max_ampdu_exp: u8,          // maximum A-MPDU length exponent
+    pub max_mpdu_len: u16,          // 3895, 7991 or 11454 bytes
+    pub sm_power_save: u8,          // 0 static, 1 dynamic, 3 disabled
+    pub rd_responder: bool,
+    pub rx_antenna_pattern: bool, // receive antenna pattern consistency
+    pub tx_antenna_pattern: bool, // transmit antenna pattern consistency
+}
+
+impl He6GhzCapabilities {
+    pub(crate) fn from_capa(capa: u16) -> Self {
+        He6GhzCapabilities {
+            capa,
+            min_mpdu_start_spacing: (capa & 0x7) as u8,
+            max_ampdu_exp: ((capa >> 3) & 0x7) as u8,
+            max_mpdu_len: match (capa >> 6) & 0x3 {
+                0 => 3895,
+                1 => 7991,
+                _ => 11454,
+            },
+            sm_power_save: ((capa >> 9) & 0x3) as u8,
+            rd_responder: capa & (1 << 11) != 0,
+            rx_antenna_pattern: capa & (1 << 12) != 0,
+            tx_antenna_pattern: capa & (1 << 13) != 0,
+        }
+    }
+
+    /// Maximum A-MPDU length in bytes
+    pub fn max_ampdu_len(&self) -> u32 {
+        (1 << (13 + self.max_ampdu_exp)) - 1
+    }
+}
+
+// EHT MAC capabilities (802.11be D3.0 9.4.2.313.2)
+const EHT_MAC_CAP0_EPCS_PRIO_ACCESS: u8 = 1 << 0;
+const EHT_MAC_CAP0_OM_CONTROL: u8 = 1 << 1;
+const EHT_MAC_CAP0_RESTRICTED_TWT: u8 = 1 << 4;
+
+// EHT PHY capabilities (802.11be D3.0 9.4.2.313.3)
+const EHT_PHY_CAP0_320MHZ_IN_6GHZ: u8 = 1 << 1;
+const EHT_PHY_CAP0_SU_BEAMFORMER: u8 = 1 << 5;
+const EHT_PHY_CAP0_SU_BEAMFORMEE: u8 = 1 << 6;
+const EHT_PHY_CAP5_PPE_THRESHOLD_PRESENT: u8 = 1 << 3;
+
+/// Maximum number of spatial streams per EHT-MCS range for one channel width, (receive, transmit)
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct EhtMcsNss {
+    pub mcs_0_9: (u8, u8),
+    pub mcs_10_11: (u8, u8),
+    pub mcs_12_13: (u8, u8),
+}
+
+impl EhtMcsNss {
+    fn from_bytes(bytes: &[u8]) -> Self {
+        let nss = |byte: u8| (byte & 0xf, byte >> 4);
+        EhtMcsNss {
+            mcs_0_9: nss(bytes[0]),
+            mcs_10_11: nss(bytes[1]),
+            mcs_12_13: nss(bytes[2]),
+        }
+    }
+
+    /// Highest receive MCS, if any
+    pub fn rx_max_mcs(&self) -> Option<u8> {
+        if self.mcs_12_13.0 > 0 {
+            Some(13)
+        } else if self.mcs_10_11.0 > 0 {
+            Some(11)
+        } else if self.mcs_0_9.0 > 0 {
+            Some(9)
+        } else {
+            None
+        }
+    }
+}
+
+/// Maximum number of spatial streams per EHT-MCS range of a 20 MHz-only station, (receive, transmit)
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct EhtMcsNss20 {
+    pub mcs_0_7: (u8, u8),
+    pub mcs_8_9: (u8, u8),
+    pub mcs_10_11: (u8, u8),
+    pub mcs_12_13: (u8, u8),
+}
+
+impl EhtMcsNss20 {
+    fn from_bytes(bytes: &[u8]) -> Self {
+        let nss = |byte: u8| (byte & 0xf, byte >> 4);
+        EhtMcsNss20 {
+            mcs_0_7: nss(bytes[0]),
+            mcs_8_9: nss(bytes[1]),
+            mcs_10_11: nss(bytes[2]),
+            mcs_12_13: nss(bytes[3]),
+        }
+    }
+
+    /// Highest receive MCS, if any
+    pub fn rx_max_mcs(&self) -> Option<u8> {
+        [
+            (self.mcs_12_13, 13),
+            (self.mcs_10_11, 11),
+            (self.mcs_8_9, 9),
+            (self.mcs_0_7, 7),
+        ]
+        .into_iter()
+        .find(|(nss, _)| nss.0 > 0)
+        .map(|(_, mcs)| mcs)
+    }
+}
+
+/// EHT (802.11be) capabilities of a band for some interface types
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct EhtCapabilities {
+    pub mac_cap: [u8; 2], // BandIftypeAttrEhtCapMac, the raw MAC capabilities
+    pub phy_cap: [u8; 9], // BandIftypeAttrEhtCapPhy, the raw PHY capabilities
+    pub eht320: bool,     // 320 MHz channels in 6 GHz
+    pub su_beamformer: bool,
+    pub su_beamformee: bool,
+    pub epcs_priority_access: bool,
+    pub om_control: bool,
+    pub restricted_twt: bool,
+    pub mcs_nss_set: Vec<u8>, // BandIftypeAttrEhtCapMcsSet, the raw set
+    pub mcs_20: Option<EhtMcsNss20>, // 20 MHz-only stations
+    pub mcs_80: Option<EhtMcsNss>, // up to 80 MHz, unset for 20 MHz-only stations
+    pub mcs_160: Option<EhtMcsNss>,
+    pub mcs_320: Option<EhtMcsNss>,
+    pub ppe_thresholds: Vec<u8>, // BandIftypeAttrEhtCapPpe, empty if not present
+}
+
+impl EhtCapabilities {
+    /// Decode the raw attributes, the HE PHY capabilities tell which MCS and NSS sets follow.
+    pub(crate) fn decode(
+        mac_cap: &[u8],
+        phy_cap: &[u8],
+        mcs_set: &[u8],
+        ppe: &[u8],
+        he: Option<&HeCapabilities>,
+    ) -> Self {
+        let mut eht = EhtCapabilities::default();
+        let mac_len = mac_cap.len().min(eht.mac_cap.len());
+        eht.mac_cap[..mac_len].copy_from_slice(&mac_cap[..mac_len]);
+        let phy_len = phy_cap.len().min(eht.phy_cap.len());
+        eht.phy_cap[..phy_len].copy_from_slice(&phy_cap[..phy_len]);
+
+        let mac = eht.mac_cap;
+        let phy = eht.phy_cap;
+        eht.epcs_priority_access = mac[0] & EHT_MAC_CAP0_EPCS_PRIO_ACCESS != 0;
+        eht.om_control = mac[0] & EHT_MAC_CAP0_OM_CONTROL != 0;
+        eht.restricted_twt = mac[0] & EHT_MAC_CAP0_RESTRICTED_TWT != 0;
+        eht.eht320 = phy[0] & EHT_PHY_CAP0_320MHZ_IN_6GHZ != 0;
+        eht.su_beamformer = phy[0] & EHT_PHY_CAP0_SU_BEAMFORMER != 0;
+        eht.su_beamformee = phy[0] & EHT_PHY_CAP0_SU_BEAMFORMEE != 0;
+
+        eht.mcs_nss_set = mcs_set.to_vec();
+        let wide = he.is_some_and(|he| he.he40_2ghz || he.he40_80_5ghz || he.he160);
+        if !wide && mcs_set.len() >= 4 {
+            // 20 MHz-only non-AP stations use their own four byte layout
+            eht.mcs_20 = Some(EhtMcsNss20::from_bytes(mcs_set));
+        } else {
+            let mut sets = mcs_set.chunks_exact(3).map(EhtMcsNss::from_bytes);
+            eht.mcs_80 = sets.next();
+            if he.is_some_and(|he| he.he160) {
+                eht.mcs_160 = sets.next();
+            }
+            if eht.eht320 {
+                eht.mcs_320 = sets.next();
+            }
+        }
+        if phy[5] & EHT_PHY_CAP5_PPE_THRESHOLD_PRESENT != 0 {
+            eht.ppe_thresholds = ppe.to_vec();
+        }
+        eht
+    }
+
+    pub fn summary(&self) -> String {
+        let mut parts: Vec<String> = Vec::new();
+        for (supported, name) in [
+            (self.eht320, "EHT320"),
+            (self.su_beamformer, "SU-Beamformer"),
+            (self.su_beamformee, "SU-Beamformee"),
+            (self.epcs_priority_access, "EPCS"),
+            (self.om_control, "OM-Control"),
+            (self.restricted_twt, "R-TWT"),
+            (!self.ppe_thresholds.is_empty(), "PPE"),
+        ] {
+            if supported {
+                parts.push(name.to_string());
+            }
+        }
+        let streams = match (&self.mcs_80, &self.mcs_20) {
+            (Some(mcs_80), _) => mcs_80.rx_max_mcs().map(|mcs| {
+                let nss = [mcs_80.mcs_0_9.0, mcs_80.mcs_10_11.0, mcs_80.mcs_12_13.0];
+                (nss.into_iter().max().unwrap_or_default(), mcs)
+            }),
+            (None, Some(mcs_20)) => mcs_20.rx_max_mcs().map(|mcs| {
+                let nss = [
+                    mcs_20.mcs_0_7.0,
+                    mcs_20.mcs_8_9.0,
+                    mcs_20.mcs_10_11.0,
+                    mcs_20.mcs_12_13.0,
+                ];
+                (nss.into_iter().max().unwrap_or_default(), mcs)
+            }),
+            (None, None) => None,
+        };
+        if let Some((nss, mcs)) = streams {
+            parts.push(format!("{}ss MCS 0-{}", nss, mcs));
+        }
+        parts.join(" ")
+    }
+}
@@ -0,0 +1,203 @@
+use crate::attr::Nl80211Attr;
+use crate::cmd::Nl80211Cmd;
+use crate::decode::decode_messages;
+use crate::error::Nl80211Error;
+use neli::consts::rtnl::Rtm;
+
+use std::fmt::{self, Write as _};
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Linux netlink with the cooked pseudo-header, what tcpdump writes for an nlmon interface
+const LINKTYPE_NETLINK: u32 = 253;
+const SNAPLEN: u32 = 65535;
+const ARPHRD_NETLINK: u16 = 824;
+const PACKET_HOST: u16 = 0;
+const PACKET_OUTGOING: u16 = 4;
+
+/// The cooked (SLL) pseudo-header in front of every packet
+const SLL_HDRLEN: usize = 16;
+const NLMSG_HDRLEN: usize = 16;
+
+/// Writes netlink traffic to a pcap file Wireshark opens with its netlink, generic netlink and
+/// nl80211 dissectors.
+///
+/// Clones share the file, so the nl80211 and rtnetlink sockets can capture to the same one.
+#[derive(Clone)]
+pub struct PcapWriter {
+    out: Arc<Mutex<File>>,
+}
+
+impl fmt::Debug for PcapWriter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PcapWriter").finish_non_exhaustive()
+    }
+}
+
+impl PcapWriter {
+    /// Create the capture file, replacing an existing one.
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self, Nl80211Error> {
+        let mut out = File::create(path).map_err(|err| Nl80211Error::io("pcap", err))?;
+        let mut header = Vec::with_capacity(24);
+        header.extend_from_slice(&0xa1b2c3d4u32.to_ne_bytes());
+        header.extend_from_slice(&2u16.to_ne_bytes());
+        header.extend_from_slice(&4u16.to_ne_bytes());
+        header.extend_from_slice(&0i32.to_ne_bytes()); // thiszone
+        header.extend_from_slice(&0u32.to_ne_bytes()); // sigfigs
+        header.extend_from_slice(&SNAPLEN.to_ne_bytes());
+        header.extend_from_slice(&LINKTYPE_NETLINK.to_ne_bytes());
+        out.write_all(&header)
+            .map_err(|err| Nl80211Error::io("pcap", err))?;
+        Ok(PcapWriter {
+            out: Arc::new(Mutex::new(out)),
+        })
+    }
+
+    /// Append one datagram sent or received on a socket of the given netlink protocol
+    /// (`NETLINK_GENERIC`, `NETLINK_ROUTE`).
+    pub fn write_packet(
+        &self,
+        protocol: u16,
+        outgoing: bool,
+        data: &[u8],
+    ) -> Result<(), Nl80211Error> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let captured = data.len().min(SNAPLEN as usize - SLL_HDRLEN);
+
+        let mut record = Vec::with_capacity(16 + SLL_HDRLEN + captured);
+        record.extend_from_slice(&(timestamp.as_secs() as u32).to_ne_bytes());
+        record.extend_from_slice(&timestamp.subsec_micros().to_ne_bytes());
+        record.extend_from_slice(&((SLL_HDRLEN + captured) as u32).to_ne_bytes());
+        record.extend_from_slice(&((SLL_HDRLEN + data.len()) as u32).to_ne_bytes());
+        // The pseudo-header is big endian, the netlink messages stay in host byte order
+        let packet_type = if outgoing {
+            PACKET_OUTGOING
+        } else {
+            PACKET_HOST
+        };
+        record.extend_from_slice(&packet_type.to_be_bytes());
+        record.extend_from_slice(&ARPHRD_NETLINK.to_be_bytes());
+        record.extend_from_slice(&0u16.to_be_bytes()); // no link-layer address
+        record.extend_from_slice(&[0; 8]);
+        record.extend_from_slice(&protocol.to_be_bytes());
+        record.extend_from_slice(&data[..captured]);
+
+        let mut out = self
+            .out
+            .lock()
+            .map_err(|_| Nl80211Error::Parse("pcap writer poisoned".to_string()))?;
+        out.write_all(&record)
+            .map_err(|err| Nl80211Error::io("pcap", err))
+    }
+}
+
+/// A readable summary of the messages in a datagram, one line per message and indented lines for
+/// the nl80211 attributes.
+pub fn describe_datagram(protocol: u16, data: &[u8]) -> String {
+    let mut out = String::new();
+    let mut position = 0;
+    while position + NLMSG_HDRLEN <= data.len() {
+        let header = &data[position..position + NLMSG_HDRLEN];
+        let msg_len = u32::from_ne_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let msg_type = u16::from_ne_bytes([header[4], header[5]]);
+        let flags = u16::from_ne_bytes([header[6], header[7]]);
+        let seq = u32::from_ne_bytes([header[8], header[9], header[10], header[11]]);
+        if msg_len < NLMSG_HDRLEN || msg_len > data.len() - position {
+            let _ = writeln!(out, "truncated message ({} bytes)", data.len() - position);
+            break;
+        }
+        let msg = &data[position..position + msg_len];
+        position += (msg_len + 3) & !3;
+
+        let _ = write!(out, "seq {} flags {:#06x} ", seq, flags);
+        match msg_type {
+            ty if ty == libc::NLMSG_NOOP as u16 => out.push_str("NLMSG_NOOP\n"),
+            ty if ty == libc::NLMSG_DONE as u16 => out.push_str("NLMSG_DONE\n"),
+            ty if ty == libc::NLMSG_ERROR as u16 => {
+                let error = msg
+                    .get(NLMSG_HDRLEN..NLMSG_HDRLEN + 4)
+                    .map(|error| i32::from_ne_bytes([error[0], error[1], error[2], error[3]]))
+                    .unwrap_or_default();
+                if error == 0 {
+                    out.push_str("ACK\n");
+                } else {
+                    let _ = writeln!(
+                        out,
+                        "NLMSG_ERROR {}",
+                        std::io::Error::from_raw_os_error(-error)
+                    );
+                }
+            }
+            ty if protocol == libc::NETLINK_ROUTE as u16 => {
+                let index = msg
+                    .get(NLMSG_HDRLEN + 4..NLMSG_HDRLEN + 8)
+                    .map(|index| i32::from_ne_bytes([index[0], index[1], index[2], index[3]]));
+                let _ = writeln!(out, "{:?} ifindex {:?}", Rtm::from(ty), index);
+            }
+            ty if ty == libc::GENL_ID_CTRL as u16 => {
+                let cmd = msg.get(NLMSG_HDRLEN).copied().unwrap_or_default();
+                let _ = writeln!(out, "CTRL cmd {}", cmd);
+            }
+            ty => match decode_messages(msg) {
+                Ok(messages) if messages.len() == 1 => {
+                    describe_nl80211(&mut out, messages[0].cmd, &messages[0].get_attr_handle());
+                }
+                _ => {
+                    let _ = writeln!(out, "family {:#x}, {} bytes", ty, msg_len);
+                }
+            },
+        }
+    }
+    out
+}
+
+fn describe_nl80211(
+    out: &mut String,
+    cmd: Nl80211Cmd,
+    handle: &crate::attr::Attrs<'_, Nl80211Attr>,
+) {
+    let _ = writeln!(out, "{:?}", cmd);
+    for attr in handle.iter() {
+        let payload: &[u8] = attr.nla_payload.as_ref();
+        let _ = write!(out, "    {:?} ", attr.nla_type.nla_type);
+        match payload.len() {
+            _ if attr.nla_type.nla_nested => {
+                let _ = writeln!(out, "(nested, {} bytes)", payload.len());
+            }
+            0 => out.push_str("(flag)\n"),
+            1 => {
+                let _ = writeln!(out, "{}", payload[0]);
+            }
+            2 => {
+                let _ = writeln!(out, "{}", u16::from_ne_bytes([payload[0], payload[1]]));
+            }
+            4 => {
+                let _ = writeln!(
+                    out,
+                    "{}",
+                    u32::from_ne_bytes([payload[0], payload[1], payload[2], payload[3]])
+                );
+            }
+            _ => match std::str::from_utf8(payload) {
+                // NUL terminated names, e.g. AttrIfname
+                Ok(text) if text.ends_with('\0') && !text[..text.len() - 1].contains('\0') => {
+                    let _ = writeln!(out, "{:?}", &text[..text.len() - 1]);
+                }
+                _ => {
+                    for byte in payload.iter().take(64) {
+                        let _ = write!(out, "{:02x}", byte);
+                    }
+                    if payload.len() > 64 {
+                        let _ = write!(out, "... ({} bytes)", payload.len());
+                    }
+                    out.push('\n');
+                }
+            },
+        }
+    }
+}
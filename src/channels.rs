@@ -1,18 +1,23 @@
+use crate::attr::{Nl80211ChanWidth, Nl80211ChannelType, Nl80211DfsState, Nl80211Iftype};
+use crate::band::{Bitrate, HtCapabilities, IftypeCapabilities, VhtCapabilities};
+use crate::error::Nl80211Error;
+use std::fmt;
+
 pub fn map_str_to_band_and_channel(channel_str: &str) -> Option<(WiFiBand, u32)> {
-    if channel_str.ends_with(".6e") {
-        channel_str[..channel_str.len() - 3]
+    if let Some(channel) = channel_str.strip_suffix(".6e") {
+        channel
             .parse::<u32>()
             .ok()
             .map(|ch| (WiFiBand::Band6GHz, ch))
-    } else if channel_str.ends_with(".ay") {
-        channel_str[..channel_str.len() - 3]
+    } else if let Some(channel) = channel_str.strip_suffix(".ay") {
+        channel
             .parse::<u32>()
             .ok()
             .map(|ch| (WiFiBand::Band60GHz, ch))
     } else {
         match channel_str.parse::<u32>() {
             Ok(ch) => {
-                if ch >= 1 && ch <= 14 {
+                if (1..=14).contains(&ch) {
                     Some((WiFiBand::Band2GHz, ch))
                 } else if ch >= 14 {
                     Some((WiFiBand::Band5GHz, ch))
@@ -45,13 +50,16 @@ impl WiFiBand {
         }
     }
 
-    pub fn from_u8(band: u8) -> Result<WiFiBand, String> {
+    pub fn from_u8(band: u8) -> Result<WiFiBand, Nl80211Error> {
         match band {
             2 => Ok(WiFiBand::Band2GHz),
             5 => Ok(WiFiBand::Band5GHz),
             6 => Ok(WiFiBand::Band6GHz),
             60 => Ok(WiFiBand::Band60GHz),
-            _ => Err(String::from("BAND NOT FOUND")),
+            _ => Err(Nl80211Error::InvalidArgument(format!(
+                "Band {} not found",
+                band
+            ))),
         }
     }
 }
@@ -62,6 +70,18 @@ pub struct ChannelData {
     pub channel: u32,
     pub pwr: u32,
     pub status: FrequencyStatus,
+    pub no_ir: bool, // FrequencyAttrNoIr, no initiating radiation (passive only)
+    pub radar: bool, // FrequencyAttrRadar, radar detection (DFS) required
+    pub dfs_state: Option<Nl80211DfsState>, // FrequencyAttrDfsState
+    pub dfs_time: Option<u32>, // FrequencyAttrDfsTime, ms in the current DFS state
+    pub dfs_cac_time: Option<u32>, // FrequencyAttrDfsCacTime, ms
+    pub indoor_only: bool, // FrequencyAttrIndoorOnly
+    pub no_ht40_minus: bool, // FrequencyAttrNoHt40Minus
+    pub no_ht40_plus: bool, // FrequencyAttrNoHt40Plus
+    pub no_80mhz: bool, // FrequencyAttrNo80mhz
+    pub no_160mhz: bool, // FrequencyAttrNo160mhz
+    pub no_20mhz: bool, // FrequencyAttrNo20mhz
+    pub no_10mhz: bool, // FrequencyAttrNo10mhz
 }
 
 impl Default for ChannelData {
@@ -71,14 +91,50 @@ impl Default for ChannelData {
             channel: 0,
             pwr: 0,
             status: FrequencyStatus::Enabled,
+            no_ir: false,
+            radar: false,
+            dfs_state: None,
+            dfs_time: None,
+            dfs_cac_time: None,
+            indoor_only: false,
+            no_ht40_minus: false,
+            no_ht40_plus: false,
+            no_80mhz: false,
+            no_160mhz: false,
+            no_20mhz: false,
+            no_10mhz: false,
         }
     }
 }
 
+impl ChannelData {
+    /// Short markers for the restrictions of the channel, see `pretty_print_band_lists`.
+    pub fn annotation(&self) -> String {
+        let mut annotation = String::new();
+        if self.no_ir {
+            annotation.push('P');
+        }
+        if self.radar {
+            annotation.push('R');
+            if self.dfs_state == Some(Nl80211DfsState::DfsUnavailable) {
+                annotation.push('!');
+            }
+        }
+        if self.indoor_only {
+            annotation.push('I');
+        }
+        annotation
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct BandList {
     pub band: WiFiBand,
     pub channels: Vec<ChannelData>,
+    pub bitrates: Vec<Bitrate>,               // BandAttrRates
+    pub ht: Option<HtCapabilities>,           // BandAttrHtCapa, BandAttrHtMcsSet, BandAttrHtAmpdu*
+    pub vht: Option<VhtCapabilities>,         // BandAttrVhtCapa, BandAttrVhtMcsSet
+    pub iftype_data: Vec<IftypeCapabilities>, // BandAttrIftypeData, HE and EHT per interface type
 }
 
 impl Default for BandList {
@@ -86,29 +142,110 @@ impl Default for BandList {
         BandList {
             band: WiFiBand::Band2GHz, // Default to 2GHz band
             channels: Vec::new(),
+            bitrates: Vec::new(),
+            ht: None,
+            vht: None,
+            iftype_data: Vec::new(),
         }
     }
 }
 
+impl BandList {
+    /// Merge another part of the same band, split wiphy dumps spread a band over several messages.
+    pub fn merge(&mut self, other: BandList) {
+        self.channels.extend(other.channels);
+        self.bitrates.extend(other.bitrates);
+        if other.ht.is_some() {
+            self.ht = other.ht;
+        }
+        if other.vht.is_some() {
+            self.vht = other.vht;
+        }
+        self.iftype_data.extend(other.iftype_data);
+    }
+
+    /// The HE/EHT capabilities the band offers to an interface type.
+    pub fn iftype_capabilities(&self, iftype: Nl80211Iftype) -> Option<&IftypeCapabilities> {
+        self.iftype_data
+            .iter()
+            .find(|data| data.iftypes.contains(&iftype))
+    }
+
+    pub fn supports_he(&self) -> bool {
+        self.iftype_data.iter().any(|data| data.he.is_some())
+    }
+
+    pub fn supports_eht(&self) -> bool {
+        self.iftype_data.iter().any(|data| data.eht.is_some())
+    }
+
+    /// Whether the band's capabilities allow channels of the width, as cfg80211 checks them: HT for
+    /// 40 MHz, VHT for 80 MHz and its supported channel widths for 160 and 80+80 MHz, EHT for 320
+    /// MHz on 6 GHz. 6 GHz has no HT or VHT, the widths up to 160 MHz only need HE there.
+    ///
+    /// 5 and 10 MHz depend on a wiphy flag and S1G widths on S1G bands, both count as unsupported.
+    pub fn supports_width(&self, width: Nl80211ChanWidth) -> bool {
+        let six_ghz = self.band == WiFiBand::Band6GHz;
+        match width {
+            Nl80211ChanWidth::ChanWidth20Noht => true,
+            Nl80211ChanWidth::ChanWidth20 => self.ht.is_some() || (six_ghz && self.supports_he()),
+            Nl80211ChanWidth::ChanWidth40 if six_ghz => self.supports_he(),
+            Nl80211ChanWidth::ChanWidth40 => self.ht.as_ref().is_some_and(|ht| ht.ht40),
+            Nl80211ChanWidth::ChanWidth80
+            | Nl80211ChanWidth::ChanWidth160
+            | Nl80211ChanWidth::ChanWidth80p80
+                if six_ghz =>
+            {
+                self.supports_he()
+            }
+            Nl80211ChanWidth::ChanWidth80 => self.vht.is_some(),
+            Nl80211ChanWidth::ChanWidth160 => self.vht.as_ref().is_some_and(|vht| vht.vht160),
+            Nl80211ChanWidth::ChanWidth80p80 => self.vht.as_ref().is_some_and(|vht| vht.vht80p80),
+            Nl80211ChanWidth::ChanWidth320 => {
+                six_ghz
+                    && self
+                        .iftype_data
+                        .iter()
+                        .any(|data| data.eht.as_ref().is_some_and(|eht| eht.eht320))
+            }
+            _ => false,
+        }
+    }
+}
+
+/// The enabled channels of each band, `width` per line.
+///
+/// Restricted channels are annotated: P passive only (no IR), R radar detection required (R! while
+/// radar was detected), I indoor only.
 pub fn pretty_print_band_lists(band_lists: &[BandList], width: usize) -> String {
     let mut output = String::new();
+    let mut annotated = false;
     for band_list in band_lists {
-        if band_list.channels.iter().any(|channel| channel.status == FrequencyStatus::Enabled)
+        if band_list
+            .channels
+            .iter()
+            .any(|channel| channel.status.is_enabled())
         {
             output += &format!("{:?}:\n  ", band_list.band);
             let mut line = String::new();
             let mut count = 0;
             for channel in &band_list.channels {
-                if channel.status == FrequencyStatus::Enabled {
+                if channel.status.is_enabled() {
                     let channel_str = match freq_to_band(channel.frequency) {
                         WiFiBand::Band2GHz => format!("{}", channel.channel),
                         WiFiBand::Band5GHz => format!("{}", channel.channel),
                         WiFiBand::Band60GHz => format!("{}.ay", channel.channel),
                         WiFiBand::Band6GHz => format!("{}.6e", channel.channel),
-                        WiFiBand::Unknown => format!("Unknown"),
+                        WiFiBand::Unknown => "Unknown".to_string(),
+                    };
+                    let annotation = channel.annotation();
+                    let chanline = if annotation.is_empty() {
+                        format!("[{} ({})]", channel.frequency, channel_str)
+                    } else {
+                        annotated = true;
+                        format!("[{} ({}) {}]", channel.frequency, channel_str, annotation)
                     };
-                    let chanline = format!("[{} ({})]", channel.frequency, channel_str);
-                    line += &format!("{:<17}", chanline);
+                    line += &format!("{:<20}", chanline);
                     count += 1;
 
                     if count % width == 0 {
@@ -123,6 +260,9 @@ pub fn pretty_print_band_lists(band_lists: &[BandList], width: usize) -> String
             output += &line;
         }
     }
+    if annotated {
+        output += "P: passive only, R: radar detection (R!: radar detected), I: indoor only\n";
+    }
     output
 }
 
@@ -130,10 +270,32 @@ pub fn pretty_print_band_lists(band_lists: &[BandList], width: usize) -> String
 pub enum FrequencyStatus {
     Disabled,
     Enabled,
+    PassiveOnly, // No initiating radiation: no probing, beaconing or IBSS
+    Radar,       // Radar detection (DFS) required before transmitting
+}
+
+impl FrequencyStatus {
+    /// The most restrictive status of a channel's flags.
+    pub fn from_flags(disabled: bool, no_ir: bool, radar: bool) -> Self {
+        if disabled {
+            FrequencyStatus::Disabled
+        } else if radar {
+            FrequencyStatus::Radar
+        } else if no_ir {
+            FrequencyStatus::PassiveOnly
+        } else {
+            FrequencyStatus::Enabled
+        }
+    }
+
+    /// Whether the channel can be used at all, possibly only to listen.
+    pub fn is_enabled(&self) -> bool {
+        *self != FrequencyStatus::Disabled
+    }
 }
 
 pub fn chan_to_frequency(chan: u32, band: WiFiBand) -> u32 {
-    if chan <= 0 {
+    if chan == 0 {
         return 0; // not supported
     }
 
@@ -179,19 +341,26 @@ pub fn chan_from_frequency(freq: u32) -> u32 {
         return 0; // Not supported
     }
 
-    if freq == 2484 { // Band2Ghz
+    if freq == 2484 {
+        // Band2Ghz
         14
-    } else if freq == 5935 { // Band6Ghz
+    } else if freq == 5935 {
+        // Band6Ghz
         2
-    } else if freq < 2484 { // Band2Ghz
-       (freq - 2407) / 5
-    } else if (4910..=4980).contains(&freq) { //Band5Ghz
+    } else if freq < 2484 {
+        // Band2Ghz
+        freq.saturating_sub(2407) / 5
+    } else if (4910..=4980).contains(&freq) {
+        //Band5Ghz
         (freq - 4000) / 5
-    } else if freq < 5950 { //Band5Ghz or Band6Ghz !!! SEPERATE THESE
-        (freq - 5000) / 5
-    } else if freq <= 45000 { //Band60Ghz
+    } else if freq < 5950 {
+        //Band5Ghz or Band6Ghz !!! SEPERATE THESE
+        freq.saturating_sub(5000) / 5
+    } else if freq <= 45000 {
+        //Band60Ghz
         (freq - 5950) / 5
-    } else if (58320..=70200).contains(&freq) { //Band60Ghz
+    } else if (58320..=70200).contains(&freq) {
+        //Band60Ghz
         (freq - 56160) / 2160
     } else {
         0 // Not supported
@@ -207,18 +376,438 @@ pub fn freq_to_band(freq: u32) -> WiFiBand {
         WiFiBand::Band6GHz
     } else if freq < 2484 {
         WiFiBand::Band2GHz
-    } else if (4910..=4980).contains(&freq) {
-        WiFiBand::Band5GHz
-    } else if (5150..=5925).contains(&freq) {
+    } else if (4910..=4980).contains(&freq) || (5150..=5925).contains(&freq) {
         WiFiBand::Band5GHz
     } else if (5925..=7125).contains(&freq) {
         WiFiBand::Band6GHz
-    } else if freq <= 45000 {
-        WiFiBand::Band60GHz
-    } else if (58320..=70200).contains(&freq) {
+    } else if freq <= 45000 || (58320..=70200).contains(&freq) {
         WiFiBand::Band60GHz
     } else {
         WiFiBand::Unknown
     }
 }
 
+/// 5 GHz center channels of the 40/80/160 MHz blocks
+const CENTERS_5GHZ_40: [u32; 14] = [
+    38, 46, 54, 62, 102, 110, 118, 126, 134, 142, 151, 159, 167, 175,
+];
+const CENTERS_5GHZ_80: [u32; 7] = [42, 58, 106, 122, 138, 155, 171];
+const CENTERS_5GHZ_160: [u32; 3] = [50, 114, 163];
+
+/// Highest 20 MHz channel of the 6 GHz band
+const MAX_CHANNEL_6GHZ: u32 = 233;
+
+/// A channel definition as used by `CMD_SET_CHANNEL`: the primary 20 MHz channel plus the
+/// center frequencies of the whole channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelSpec {
+    pub frequency: u32,            // AttrWiphyFreq, primary channel (MHz)
+    pub width: Nl80211ChanWidth,   // AttrChannelWidth
+    pub center_freq1: u32,         // AttrCenterFreq1 (MHz)
+    pub center_freq2: Option<u32>, // AttrCenterFreq2 (MHz), second segment of 80+80
+}
+
+impl ChannelSpec {
+    /// Channel spec for the primary frequency and width, the center frequency is derived from the
+    /// 802.11 channel plan of the band.
+    ///
+    /// 40 MHz on 2.4 GHz uses HT40+ for channels 1-7 and HT40- above, use `ht40` to choose.
+    /// 80+80 MHz needs a second segment, use `vht80p80`.
+    pub fn new(frequency: u32, width: Nl80211ChanWidth) -> Result<ChannelSpec, ChannelError> {
+        let band = freq_to_band(frequency);
+        if band == WiFiBand::Unknown || (band == WiFiBand::Band2GHz && frequency < 2412) {
+            return Err(ChannelError::InvalidSpec(format!(
+                "Unsupported frequency {} MHz",
+                frequency
+            )));
+        }
+        let channel = chan_from_frequency(frequency);
+        if channel == 0 {
+            return Err(ChannelError::InvalidSpec(format!(
+                "Unsupported frequency {} MHz",
+                frequency
+            )));
+        }
+
+        let center_freq1 = match width {
+            Nl80211ChanWidth::ChanWidth20Noht
+            | Nl80211ChanWidth::ChanWidth20
+            | Nl80211ChanWidth::ChanWidth5
+            | Nl80211ChanWidth::ChanWidth10 => frequency,
+            Nl80211ChanWidth::ChanWidth40 if band == WiFiBand::Band2GHz => {
+                return ChannelSpec::ht40(frequency, channel <= 7);
+            }
+            Nl80211ChanWidth::ChanWidth40
+            | Nl80211ChanWidth::ChanWidth80
+            | Nl80211ChanWidth::ChanWidth160
+            | Nl80211ChanWidth::ChanWidth320 => {
+                let center =
+                    center_channel(channel, &band, width).ok_or(ChannelError::InvalidSpec(
+                        format!("No {:?} channel containing {} MHz", width, frequency),
+                    ))?;
+                chan_to_frequency(center, band)
+            }
+            Nl80211ChanWidth::ChanWidth80p80 => {
+                return Err(ChannelError::InvalidSpec(
+                    "80+80 MHz needs a second segment, use ChannelSpec::vht80p80".to_string(),
+                ));
+            }
+            _ => {
+                return Err(ChannelError::InvalidSpec(format!(
+                    "Unsupported channel width {:?}",
+                    width
+                )))
+            }
+        };
+
+        Ok(ChannelSpec {
+            frequency,
+            width,
+            center_freq1,
+            center_freq2: None,
+        })
+    }
+
+    /// 20 MHz channel without HT.
+    pub fn no_ht(frequency: u32) -> ChannelSpec {
+        ChannelSpec {
+            frequency,
+            width: Nl80211ChanWidth::ChanWidth20Noht,
+            center_freq1: frequency,
+            center_freq2: None,
+        }
+    }
+
+    /// HT40 channel, the secondary channel is above the primary for HT40+ and below it for HT40-.
+    pub fn ht40(frequency: u32, plus: bool) -> Result<ChannelSpec, ChannelError> {
+        let center_freq1 = if plus {
+            frequency + 10
+        } else {
+            frequency.saturating_sub(10)
+        };
+        let secondary = if plus {
+            frequency + 20
+        } else {
+            frequency.saturating_sub(20)
+        };
+        let band = freq_to_band(frequency);
+        let valid = match band {
+            WiFiBand::Band2GHz => frequency != 2484 && (2412..=2472).contains(&secondary),
+            WiFiBand::Band5GHz | WiFiBand::Band6GHz => {
+                center_channel(
+                    chan_from_frequency(frequency),
+                    &band,
+                    Nl80211ChanWidth::ChanWidth40,
+                )
+                .map(|center| chan_to_frequency(center, band.clone()))
+                    == Some(center_freq1)
+            }
+            _ => false,
+        };
+        if !valid {
+            return Err(ChannelError::InvalidSpec(format!(
+                "No HT40{} channel for {} MHz",
+                if plus { "+" } else { "-" },
+                frequency
+            )));
+        }
+        Ok(ChannelSpec {
+            frequency,
+            width: Nl80211ChanWidth::ChanWidth40,
+            center_freq1,
+            center_freq2: None,
+        })
+    }
+
+    /// 80+80 MHz channel, `segment2` is the center frequency of the second 80 MHz segment.
+    pub fn vht80p80(frequency: u32, segment2: u32) -> Result<ChannelSpec, ChannelError> {
+        let primary = ChannelSpec::new(frequency, Nl80211ChanWidth::ChanWidth80)?;
+        let band = freq_to_band(segment2);
+        if band != freq_to_band(frequency)
+            || center_channel(
+                chan_from_frequency(segment2),
+                &band,
+                Nl80211ChanWidth::ChanWidth80,
+            ) != Some(chan_from_frequency(segment2))
+        {
+            return Err(ChannelError::InvalidSpec(format!(
+                "{} MHz is not the center of an 80 MHz channel",
+                segment2
+            )));
+        }
+        if segment2 == primary.center_freq1 || segment2.abs_diff(primary.center_freq1) == 80 {
+            return Err(ChannelError::InvalidSpec(
+                "80+80 MHz segments must not overlap or be adjacent".to_string(),
+            ));
+        }
+        Ok(ChannelSpec {
+            width: Nl80211ChanWidth::ChanWidth80p80,
+            center_freq2: Some(segment2),
+            ..primary
+        })
+    }
+
+    /// Channel spec for a channel number in a band, see `new`.
+    pub fn from_channel(
+        channel: u32,
+        band: WiFiBand,
+        width: Nl80211ChanWidth,
+    ) -> Result<ChannelSpec, ChannelError> {
+        let frequency = chan_to_frequency(channel, band);
+        if frequency == 0 {
+            return Err(ChannelError::InvalidSpec(format!(
+                "Unsupported channel {}",
+                channel
+            )));
+        }
+        ChannelSpec::new(frequency, width)
+    }
+
+    /// The legacy channel type for 20/40 MHz channels (HT40+/HT40-), None for wider channels.
+    pub fn channel_type(&self) -> Option<Nl80211ChannelType> {
+        match self.width {
+            Nl80211ChanWidth::ChanWidth20Noht => Some(Nl80211ChannelType::ChanNoHt),
+            Nl80211ChanWidth::ChanWidth20 => Some(Nl80211ChannelType::ChanHt20),
+            Nl80211ChanWidth::ChanWidth40 if self.center_freq1 > self.frequency => {
+                Some(Nl80211ChannelType::ChanHt40plus)
+            }
+            Nl80211ChanWidth::ChanWidth40 => Some(Nl80211ChannelType::ChanHt40minus),
+            _ => None,
+        }
+    }
+
+    pub fn channel(&self) -> u32 {
+        chan_from_frequency(self.frequency)
+    }
+
+    /// Frequencies of every 20 MHz subchannel the channel spans, primary included.
+    pub fn subchannel_frequencies(&self) -> Vec<u32> {
+        let span = match self.width {
+            Nl80211ChanWidth::ChanWidth40 => 40,
+            Nl80211ChanWidth::ChanWidth80 | Nl80211ChanWidth::ChanWidth80p80 => 80,
+            Nl80211ChanWidth::ChanWidth160 => 160,
+            Nl80211ChanWidth::ChanWidth320 => 320,
+            _ => return vec![self.frequency],
+        };
+        let mut centers = vec![self.center_freq1];
+        centers.extend(self.center_freq2);
+        centers
+            .iter()
+            .flat_map(|center| (0..span / 20).map(move |i| center - span / 2 + 10 + i * 20))
+            .collect()
+    }
+
+    pub fn band(&self) -> WiFiBand {
+        freq_to_band(self.frequency)
+    }
+
+    pub fn pretty_print(&self) -> String {
+        let mut output = format!(
+            "{} MHz (channel {}), width {:?}",
+            self.frequency,
+            self.channel(),
+            self.width
+        );
+        if self.center_freq1 != self.frequency {
+            output += &format!(", center1 {} MHz", self.center_freq1);
+        }
+        if let Some(center2) = self.center_freq2 {
+            output += &format!(", center2 {} MHz", center2);
+        }
+        output
+    }
+}
+
+/// Center channel number of the 40/80/160/320 MHz block containing the 20 MHz channel.
+fn center_channel(channel: u32, band: &WiFiBand, width: Nl80211ChanWidth) -> Option<u32> {
+    let subchannels = match width {
+        Nl80211ChanWidth::ChanWidth40 => 2,
+        Nl80211ChanWidth::ChanWidth80 => 4,
+        Nl80211ChanWidth::ChanWidth160 => 8,
+        Nl80211ChanWidth::ChanWidth320 => 16,
+        _ => return None,
+    };
+    // Channel numbers are 5 MHz apart, the block spans 4 numbers per 20 MHz subchannel.
+    let half_span = (subchannels - 1) * 2;
+
+    match band {
+        WiFiBand::Band5GHz => {
+            let centers: &[u32] = match width {
+                Nl80211ChanWidth::ChanWidth40 => &CENTERS_5GHZ_40,
+                Nl80211ChanWidth::ChanWidth80 => &CENTERS_5GHZ_80,
+                Nl80211ChanWidth::ChanWidth160 => &CENTERS_5GHZ_160,
+                _ => return None,
+            };
+            centers
+                .iter()
+                .find(|center| **center - half_span <= channel && channel <= **center + half_span)
+                .copied()
+        }
+        WiFiBand::Band6GHz => {
+            // 20 MHz channels are 1, 5, 9 ... 233 and wider channels are aligned on channel 1.
+            // 320 MHz uses the 320-1 plan (centers 31, 95, 159).
+            if channel % 4 != 1 || channel > MAX_CHANNEL_6GHZ {
+                return None;
+            }
+            let start = (channel - 1) / (subchannels * 4) * (subchannels * 4) + 1;
+            let center = start + half_span;
+            if center + half_span > MAX_CHANNEL_6GHZ {
+                None
+            } else {
+                Some(center)
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Why a channel can't be used by a phy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChannelError {
+    InvalidSpec(String),       // The channel spec couldn't be built
+    UnsupportedFrequency(u32), // Not in the phy's frequency list
+    Disabled(u32),             // FrequencyAttrDisabled
+    NoIr(u32),                 // FrequencyAttrNoIr
+    Radar(u32),                // FrequencyAttrRadar
+    WidthNotAllowed {
+        frequency: u32,
+        width: Nl80211ChanWidth,
+    },
+}
+
+impl fmt::Display for ChannelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChannelError::InvalidSpec(reason) => write!(f, "Invalid channel: {}", reason),
+            ChannelError::UnsupportedFrequency(freq) => {
+                write!(f, "{} MHz is not supported by the phy", freq)
+            }
+            ChannelError::Disabled(freq) => write!(f, "{} MHz is disabled", freq),
+            ChannelError::NoIr(freq) => {
+                write!(
+                    f,
+                    "{} MHz does not allow initiating radiation (passive only)",
+                    freq
+                )
+            }
+            ChannelError::Radar(freq) => write!(f, "{} MHz requires radar detection", freq),
+            ChannelError::WidthNotAllowed { frequency, width } => {
+                write!(f, "{:?} is not allowed on {} MHz", width, frequency)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ChannelError {}
+
+/// Check every 20 MHz subchannel of the spec against the phy's frequency list, for an interface
+/// of the given type.
+///
+/// Monitor interfaces only listen, so like cfg80211 only disabled channels and the width
+/// restrictions apply to them. Other interfaces can't use passive only channels, and radar
+/// channels only once radar detection cleared them (`DfsAvailable`).
+pub fn validate_channel_spec(
+    spec: &ChannelSpec,
+    bands: &[BandList],
+    iftype: Nl80211Iftype,
+) -> Result<(), ChannelError> {
+    let find = |frequency: u32| {
+        bands
+            .iter()
+            .flat_map(|band| band.channels.iter())
+            .find(|channel| channel.frequency == frequency)
+            .ok_or(ChannelError::UnsupportedFrequency(frequency))
+    };
+    let width_not_allowed = |frequency: u32| ChannelError::WidthNotAllowed {
+        frequency,
+        width: spec.width,
+    };
+    let transmits = iftype != Nl80211Iftype::IftypeMonitor;
+
+    for frequency in spec.subchannel_frequencies() {
+        let channel = find(frequency)?;
+        if channel.status == FrequencyStatus::Disabled {
+            return Err(ChannelError::Disabled(frequency));
+        }
+        if transmits && channel.no_ir {
+            return Err(ChannelError::NoIr(frequency));
+        }
+        if transmits && channel.radar && channel.dfs_state != Some(Nl80211DfsState::DfsAvailable) {
+            return Err(ChannelError::Radar(frequency));
+        }
+        match spec.width {
+            Nl80211ChanWidth::ChanWidth20Noht | Nl80211ChanWidth::ChanWidth20
+                if channel.no_20mhz =>
+            {
+                return Err(width_not_allowed(frequency));
+            }
+            Nl80211ChanWidth::ChanWidth10 if channel.no_10mhz => {
+                return Err(width_not_allowed(frequency));
+            }
+            Nl80211ChanWidth::ChanWidth80 | Nl80211ChanWidth::ChanWidth80p80
+                if channel.no_80mhz =>
+            {
+                return Err(width_not_allowed(frequency));
+            }
+            Nl80211ChanWidth::ChanWidth160 | Nl80211ChanWidth::ChanWidth320
+                if channel.no_80mhz || channel.no_160mhz =>
+            {
+                return Err(width_not_allowed(frequency));
+            }
+            _ => (),
+        }
+    }
+
+    // The HT40 restrictions apply to the primary channel only.
+    let primary = find(spec.frequency)?;
+    match spec.channel_type() {
+        Some(Nl80211ChannelType::ChanHt40plus) if primary.no_ht40_plus => {
+            Err(width_not_allowed(spec.frequency))
+        }
+        Some(Nl80211ChannelType::ChanHt40minus) if primary.no_ht40_minus => {
+            Err(width_not_allowed(spec.frequency))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Every primary channel and width combination of the frequency list that the band's capabilities
+/// allow (`BandList::supports_width`) and that passes `validate_channel_spec` for the interface type.
+///
+/// 80+80 MHz combinations are not enumerated.
+pub fn supported_channel_specs(bands: &[BandList], iftype: Nl80211Iftype) -> Vec<ChannelSpec> {
+    let widths = [
+        Nl80211ChanWidth::ChanWidth80,
+        Nl80211ChanWidth::ChanWidth160,
+        Nl80211ChanWidth::ChanWidth320,
+    ];
+    let mut specs = Vec::new();
+    for band in bands {
+        for channel in &band.channels {
+            let mut candidates = vec![Ok(ChannelSpec::no_ht(channel.frequency))];
+            if band.supports_width(Nl80211ChanWidth::ChanWidth20) {
+                candidates.push(ChannelSpec::new(
+                    channel.frequency,
+                    Nl80211ChanWidth::ChanWidth20,
+                ));
+            }
+            if band.supports_width(Nl80211ChanWidth::ChanWidth40) {
+                candidates.push(ChannelSpec::ht40(channel.frequency, true));
+                candidates.push(ChannelSpec::ht40(channel.frequency, false));
+            }
+            candidates.extend(
+                widths
+                    .iter()
+                    .filter(|width| band.supports_width(**width))
+                    .map(|width| ChannelSpec::new(channel.frequency, *width)),
+            );
+            specs.extend(
+                candidates
+                    .into_iter()
+                    .flatten()
+                    .filter(|spec| validate_channel_spec(spec, bands, iftype).is_ok()),
+            );
+        }
+    }
+    specs
+}
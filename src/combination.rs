@@ -0,0 +1,164 @@
+use crate::attr::{Nl80211ChanWidth, Nl80211DfsRegions, Nl80211Iftype};
+use crate::interface::Interface;
+
+/// One entry of `AttrInterfaceCombinations`, a set of interfaces the phy can run concurrently
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct InterfaceCombination {
+    pub limits: Vec<InterfaceLimit>,     // IfaceCombLimits
+    pub max_interfaces: u32,             // IfaceCombMaxnum, over all limits
+    pub num_channels: u32,               // IfaceCombNumChannels, different channels in use
+    pub beacon_int_infra_match: bool, // IfaceCombStaApBiMatch, AP beacon intervals must match the station's
+    pub beacon_int_min_gcd: Option<u32>, // IfaceCombBiMinGcd, minimum GCD of different beacon intervals (TUs)
+    pub radar_detect_widths: u32,        // IfaceCombRadarDetectWidths, bitmap of Nl80211ChanWidth
+    pub radar_detect_regions: u32,       // IfaceCombRadarDetectRegions, bitmap of Nl80211DfsRegions
+}
+
+/// At most `max` interfaces of the listed types, from `IfaceCombLimits`
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct InterfaceLimit {
+    pub max: u32,                    // IfaceLimitMax
+    pub iftypes: Vec<Nl80211Iftype>, // IfaceLimitTypes
+}
+
+impl InterfaceCombination {
+    pub fn supports_radar_detect(&self, width: Nl80211ChanWidth) -> bool {
+        1u32.checked_shl(u16::from(width).into())
+            .is_some_and(|bit| self.radar_detect_widths & bit != 0)
+    }
+
+    /// An empty region bitmap allows radar detection in every region.
+    pub fn supports_radar_detect_region(&self, region: Nl80211DfsRegions) -> bool {
+        self.radar_detect_regions == 0
+            || 1u32
+                .checked_shl(u16::from(region).into())
+                .is_some_and(|bit| self.radar_detect_regions & bit != 0)
+    }
+
+    /// Whether the combination allows the request, the way cfg80211 checks combinations.
+    ///
+    /// Interfaces of the `software_iftypes` aren't limited by combinations and are skipped.
+    pub fn allows(&self, request: &ConcurrencyRequest, software_iftypes: &[Nl80211Iftype]) -> bool {
+        let iftypes: Vec<Nl80211Iftype> = request
+            .iftypes
+            .iter()
+            .filter(|iftype| !software_iftypes.contains(iftype))
+            .copied()
+            .collect();
+        if iftypes.len() as u32 > self.max_interfaces || request.num_channels > self.num_channels {
+            return false;
+        }
+
+        // Every interface counts against each limit listing its type
+        let mut remaining: Vec<u32> = self.limits.iter().map(|limit| limit.max).collect();
+        for iftype in &iftypes {
+            let mut listed = false;
+            for (limit, remaining) in self.limits.iter().zip(remaining.iter_mut()) {
+                if !limit.iftypes.contains(iftype) {
+                    continue;
+                }
+                if *remaining == 0 {
+                    return false;
+                }
+                *remaining -= 1;
+                listed = true;
+            }
+            if !listed {
+                return false;
+            }
+        }
+
+        if request.radar_detect.is_empty() {
+            return true;
+        }
+        request
+            .radar_detect
+            .iter()
+            .all(|width| self.supports_radar_detect(*width))
+            && request
+                .radar_detect_region
+                .is_none_or(|region| self.supports_radar_detect_region(region))
+    }
+}
+
+/// Interfaces to run at the same time on one phy, for `WirelessPhy::can_run`
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ConcurrencyRequest {
+    pub iftypes: Vec<Nl80211Iftype>,         // One entry per interface
+    pub num_channels: u32,                   // Different channels in use by the interfaces
+    pub radar_detect: Vec<Nl80211ChanWidth>, // Widths radar detection runs on
+    pub radar_detect_region: Option<Nl80211DfsRegions>, // DFS region of the radar detection, None isn't checked
+}
+
+impl ConcurrencyRequest {
+    pub fn new(iftypes: &[Nl80211Iftype], num_channels: u32) -> ConcurrencyRequest {
+        ConcurrencyRequest {
+            iftypes: iftypes.to_vec(),
+            num_channels,
+            radar_detect: Vec::new(),
+            radar_detect_region: None,
+        }
+    }
+
+    /// The running interfaces of a phy, channels counted from their frequencies.
+    pub fn from_interfaces(interfaces: &[Interface]) -> ConcurrencyRequest {
+        let mut frequencies: Vec<u32> = interfaces
+            .iter()
+            .filter_map(|interface| interface.frequency.frequency)
+            .collect();
+        frequencies.sort_unstable();
+        frequencies.dedup();
+        ConcurrencyRequest {
+            iftypes: interfaces
+                .iter()
+                .filter_map(|interface| interface.current_iftype)
+                .collect(),
+            num_channels: frequencies.len() as u32,
+            radar_detect: Vec::new(),
+            radar_detect_region: None,
+        }
+    }
+
+    /// Returns the request with one more interface, for chaining.
+    pub fn with_interface(mut self, iftype: Nl80211Iftype) -> ConcurrencyRequest {
+        self.iftypes.push(iftype);
+        self
+    }
+
+    /// Returns the request with the number of different channels set, for chaining.
+    pub fn with_channels(mut self, num_channels: u32) -> ConcurrencyRequest {
+        self.num_channels = num_channels;
+        self
+    }
+
+    /// Returns the request with radar detection on a channel of the width, for chaining.
+    pub fn with_radar_detect(mut self, width: Nl80211ChanWidth) -> ConcurrencyRequest {
+        self.radar_detect.push(width);
+        self
+    }
+
+    /// Returns the request with the DFS region radar detection runs in, for chaining.
+    pub fn with_radar_detect_region(mut self, region: Nl80211DfsRegions) -> ConcurrencyRequest {
+        self.radar_detect_region = Some(region);
+        self
+    }
+}
+
+/// Whether any of the combinations allows the request. A single interface on one channel without
+/// radar detection is always allowed, like mac80211 does.
+pub fn check_combinations(
+    combinations: &[InterfaceCombination],
+    software_iftypes: &[Nl80211Iftype],
+    request: &ConcurrencyRequest,
+) -> bool {
+    let counted = request
+        .iftypes
+        .iter()
+        .filter(|iftype| !software_iftypes.contains(iftype))
+        .count();
+    if counted <= 1 && request.num_channels <= 1 && request.radar_detect.is_empty() {
+        return true;
+    }
+    combinations
+        .iter()
+        .any(|combination| combination.allows(request, software_iftypes))
+}
@@ -0,0 +1,906 @@
+use crate::attr::*;
+use crate::band::{
+    Bitrate, EhtCapabilities, He6GhzCapabilities, HeCapabilities, HtCapabilities,
+    IftypeCapabilities, VhtCapabilities,
+};
+use crate::channels::*;
+use crate::cmd::Nl80211Cmd;
+use crate::combination::{InterfaceCombination, InterfaceLimit};
+use crate::error::Nl80211Error;
+use crate::interface::Interface;
+use crate::phy::{ExtFeatures, FeatureFlags, WirelessPhy};
+use crate::reg::{RegDomain, RegRule};
+use crate::scan::Bss;
+use crate::station::{RateInfo, StationBssParam, StationFlags, StationInfo, TidStats};
+use crate::survey::SurveyInfo;
+use crate::util::decode_iftypes;
+use neli::attr::{AttrHandle, Attribute};
+use neli::genl::{Genlmsghdr, Nlattr};
+use neli::types::{Buffer, GenlBuffer};
+use neli::FromBytesWithInput;
+
+use std::io::Cursor;
+
+/// A nl80211 generic netlink message, a reply or a notification.
+pub type Nl80211Message = Genlmsghdr<Nl80211Cmd, Nl80211Attr>;
+
+const NLMSG_HDRLEN: usize = 16;
+const NLMSG_MIN_TYPE: u16 = 0x10;
+
+/// Decode the nl80211 messages of a datagram. NLMSG_DONE and acks are skipped, an NLMSG_ERROR
+/// is returned as the kernel error it carries.
+pub fn decode_messages(bytes: &[u8]) -> Result<Vec<Nl80211Message>, Nl80211Error> {
+    let mut messages = Vec::new();
+    let mut position = 0;
+    while position + NLMSG_HDRLEN <= bytes.len() {
+        let header = &bytes[position..position + NLMSG_HDRLEN];
+        let msg_len = u32::from_ne_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let msg_type = u16::from_ne_bytes([header[4], header[5]]);
+        if msg_len < NLMSG_HDRLEN || msg_len > bytes.len() - position {
+            return Err(Nl80211Error::Parse("Truncated netlink message".to_string()));
+        }
+        let payload = &bytes[position + NLMSG_HDRLEN..position + msg_len];
+        position += (msg_len + 3) & !3;
+
+        if msg_type == libc::NLMSG_ERROR as u16 {
+            let error = match payload {
+                [a, b, c, d, ..] => i32::from_ne_bytes([*a, *b, *c, *d]),
+                _ => return Err(Nl80211Error::Parse("Truncated NLMSG_ERROR".to_string())),
+            };
+            if error != 0 {
+                return Err(Nl80211Error::Kernel {
+                    command: "NLMSG_ERROR",
+                    errno: error.saturating_neg(),
+                    message: None,
+                });
+            }
+            continue;
+        }
+        if msg_type < NLMSG_MIN_TYPE {
+            continue;
+        }
+        let msg =
+            Nl80211Message::from_bytes_with_input(&mut Cursor::new(payload), payload.len())
+                .map_err(|err| Nl80211Error::Parse(format!("Invalid nl80211 message: {}", err)))?;
+        messages.push(msg);
+    }
+    Ok(messages)
+}
+
+/// Decode a NUL terminated string attribute. Unlike neli's `String` decoding this doesn't panic on
+/// an empty payload.
+pub(crate) fn decode_string(payload: &[u8]) -> Result<String, Nl80211Error> {
+    let len = payload
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(payload.len());
+    String::from_utf8(payload[..len].to_vec())
+        .map_err(|err| Nl80211Error::Parse(format!("Invalid string attribute: {}", err)))
+}
+
+/// Merge the CmdNewWiphy messages of a (split) wiphy dump into one phy, other messages are
+/// skipped. The driver isn't part of the messages and is left unset.
+pub fn decode_wiphy<'a, I>(messages: I) -> Result<WirelessPhy, Nl80211Error>
+where
+    I: IntoIterator<Item = &'a Nl80211Message>,
+{
+    let mut phy = WirelessPhy::default();
+    for msg in messages {
+        if msg.cmd == Nl80211Cmd::CmdNewWiphy {
+            merge_wiphy(&mut phy, msg)?;
+        }
+    }
+    Ok(phy)
+}
+
+/// Merge one CmdNewWiphy message of a split wiphy dump into `phy`.
+pub fn merge_wiphy(phy: &mut WirelessPhy, msg: &Nl80211Message) -> Result<(), Nl80211Error> {
+    let handle = msg.get_attr_handle();
+    phy.phy = handle.get_attr_payload_as(Nl80211Attr::AttrWiphy)?;
+    let name = handle
+        .get_attribute(Nl80211Attr::AttrWiphyName)
+        .ok_or_else(|| Nl80211Error::Parse("CmdNewWiphy without AttrWiphyName".to_string()))?;
+    phy.phy_name = Some(decode_string(name.nla_payload.as_ref())?);
+
+    for attr in handle.iter() {
+        match attr.nla_type.nla_type {
+            Nl80211Attr::AttrWiphyFreq => {
+                let frequency = attr.get_payload_as()?;
+                phy.frequency.frequency = Some(frequency);
+                phy.frequency.channel = Some(chan_from_frequency(frequency));
+            }
+            // Channel Type (Width)
+            Nl80211Attr::AttrChannelWidth => {
+                phy.frequency.width = Some(attr.get_payload_as()?);
+            }
+            // Transmission Power Level
+            Nl80211Attr::AttrWiphyTxPowerLevel => {
+                phy.frequency.pwr = Some(attr.get_payload_as()?);
+            }
+            Nl80211Attr::AttrSupportedIftypes => {
+                phy.iftypes = Some(decode_iftypes(attr.get_payload_as_with_len()?));
+                phy.has_netlink = Some(true);
+            }
+            // Split dumps spread the channels of a band over several messages
+            Nl80211Attr::AttrWiphyBands => {
+                let frequency_list = phy.frequency_list.get_or_insert_with(Vec::new);
+                for new_band in decode_bands(attr)? {
+                    match frequency_list.iter_mut().find(|b| b.band == new_band.band) {
+                        Some(existing_band) => existing_band.merge(new_band),
+                        None => frequency_list.push(new_band),
+                    }
+                }
+            }
+            Nl80211Attr::AttrIftype => {
+                phy.current_iftype = Some(attr.get_payload_as()?);
+            }
+            _ => {
+                if let Err(err) = merge_wiphy_capability(phy, attr) {
+                    skip_malformed(phy.phy, attr.nla_type.nla_type, err);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Capabilities are optional, a malformed one leaves its field unset instead of failing the phy.
+fn merge_wiphy_capability(
+    phy: &mut WirelessPhy,
+    attr: &Nlattr<Nl80211Attr, Buffer>,
+) -> Result<(), Nl80211Error> {
+    match attr.nla_type.nla_type {
+        Nl80211Attr::AttrFeatureFlags => {
+            let feature_flags = FeatureFlags::from_bits(attr.get_payload_as()?);
+            phy.active_monitor =
+                Some(feature_flags.contains(Nl80211FeatureFlags::FeatureActiveMonitor));
+            phy.feature_flags = Some(feature_flags);
+        }
+        Nl80211Attr::AttrExtFeatures => {
+            phy.ext_features = Some(ExtFeatures::from_bytes(attr.nla_payload.as_ref()));
+        }
+        Nl80211Attr::AttrSoftwareIftypes => {
+            phy.software_iftypes = Some(decode_iftypes(attr.get_payload_as_with_len()?));
+        }
+        Nl80211Attr::AttrSupportedCommands => {
+            let handle = attr.get_attr_handle::<u16>()?;
+            let mut commands = Vec::new();
+            for command in handle.iter() {
+                let command: u32 = command.get_payload_as()?;
+                commands.push(Nl80211Cmd::from(command as u8));
+            }
+            phy.supported_commands
+                .get_or_insert_with(Vec::new)
+                .extend(commands);
+        }
+        Nl80211Attr::AttrCipherSuites => {
+            phy.cipher_suites = Some(decode_u32_array(attr.nla_payload.as_ref()));
+        }
+        Nl80211Attr::AttrAkmSuites => {
+            phy.akm_suites = Some(decode_u32_array(attr.nla_payload.as_ref()));
+        }
+        Nl80211Attr::AttrMaxNumScanSsids => {
+            phy.max_scan_ssids = Some(attr.get_payload_as()?);
+        }
+        Nl80211Attr::AttrMaxScanIeLen => {
+            phy.max_scan_ie_len = Some(attr.get_payload_as()?);
+        }
+        Nl80211Attr::AttrMaxNumSchedScanSsids => {
+            phy.max_sched_scan_ssids = Some(attr.get_payload_as()?);
+        }
+        Nl80211Attr::AttrMaxSchedScanIeLen => {
+            phy.max_sched_scan_ie_len = Some(attr.get_payload_as()?);
+        }
+        Nl80211Attr::AttrMaxMatchSets => {
+            phy.max_match_sets = Some(attr.get_payload_as()?);
+        }
+        Nl80211Attr::AttrMaxRemainOnChannelDuration => {
+            phy.max_remain_on_channel_duration = Some(attr.get_payload_as()?);
+        }
+        Nl80211Attr::AttrWiphyAntennaAvailTx => {
+            phy.antenna_avail_tx = Some(attr.get_payload_as()?);
+        }
+        Nl80211Attr::AttrWiphyAntennaAvailRx => {
+            phy.antenna_avail_rx = Some(attr.get_payload_as()?);
+        }
+        Nl80211Attr::AttrWiphyAntennaTx => {
+            phy.antenna_tx = Some(attr.get_payload_as()?);
+        }
+        Nl80211Attr::AttrWiphyAntennaRx => {
+            phy.antenna_rx = Some(attr.get_payload_as()?);
+        }
+        Nl80211Attr::AttrWiphyRetryShort => {
+            phy.retry_short = Some(attr.get_payload_as()?);
+        }
+        Nl80211Attr::AttrWiphyRetryLong => {
+            phy.retry_long = Some(attr.get_payload_as()?);
+        }
+        Nl80211Attr::AttrWiphyFragThreshold => {
+            phy.frag_threshold = Some(attr.get_payload_as()?);
+        }
+        Nl80211Attr::AttrWiphyRtsThreshold => {
+            phy.rts_threshold = Some(attr.get_payload_as()?);
+        }
+        Nl80211Attr::AttrWiphyCoverageClass => {
+            phy.coverage_class = Some(attr.get_payload_as()?);
+        }
+        Nl80211Attr::AttrInterfaceCombinations => {
+            let handle = attr.get_attr_handle::<u16>()?;
+            let mut combinations = Vec::new();
+            for combination in handle.iter() {
+                combinations.push(decode_interface_combination(combination)?);
+            }
+            phy.num_interface_combinations = Some(combinations.len() as u32);
+            phy.interface_combinations = Some(combinations);
+        }
+        _ => {} // TODO implement other attributes
+    }
+    Ok(())
+}
+
+#[cfg_attr(not(feature = "log"), allow(unused_variables))]
+fn skip_malformed(phy: u32, attr: Nl80211Attr, err: Nl80211Error) {
+    #[cfg(feature = "log")]
+    log::warn!("phy{}: skipping malformed {:?}: {}", phy, attr, err);
+}
+
+/// Decode one entry of `AttrInterfaceCombinations`.
+pub fn decode_interface_combination(
+    attr: &Nlattr<u16, Buffer>,
+) -> Result<InterfaceCombination, Nl80211Error> {
+    let handle = attr.get_attr_handle::<Nl80211IfCombinationAttrs>()?;
+    let mut combination = InterfaceCombination::default();
+
+    for combattr in handle.iter() {
+        match combattr.nla_type.nla_type {
+            Nl80211IfCombinationAttrs::IfaceCombLimits => {
+                let limits = combattr.get_attr_handle::<u16>()?;
+                for limit in limits.iter() {
+                    let limithandle = limit.get_attr_handle::<Nl80211IfaceLimitAttrs>()?;
+                    let mut interface_limit = InterfaceLimit::default();
+                    for limitattr in limithandle.iter() {
+                        match limitattr.nla_type.nla_type {
+                            Nl80211IfaceLimitAttrs::IfaceLimitMax => {
+                                interface_limit.max = limitattr.get_payload_as()?;
+                            }
+                            Nl80211IfaceLimitAttrs::IfaceLimitTypes => {
+                                interface_limit.iftypes =
+                                    decode_iftypes(limitattr.get_payload_as_with_len()?);
+                            }
+                            _ => (),
+                        }
+                    }
+                    combination.limits.push(interface_limit);
+                }
+            }
+            Nl80211IfCombinationAttrs::IfaceCombMaxnum => {
+                combination.max_interfaces = combattr.get_payload_as()?;
+            }
+            Nl80211IfCombinationAttrs::IfaceCombStaApBiMatch => {
+                combination.beacon_int_infra_match = true;
+            }
+            Nl80211IfCombinationAttrs::IfaceCombNumChannels => {
+                combination.num_channels = combattr.get_payload_as()?;
+            }
+            Nl80211IfCombinationAttrs::IfaceCombRadarDetectWidths => {
+                combination.radar_detect_widths = combattr.get_payload_as()?;
+            }
+            Nl80211IfCombinationAttrs::IfaceCombRadarDetectRegions => {
+                combination.radar_detect_regions = combattr.get_payload_as()?;
+            }
+            Nl80211IfCombinationAttrs::IfaceCombBiMinGcd => {
+                combination.beacon_int_min_gcd = Some(combattr.get_payload_as()?);
+            }
+            _ => (),
+        }
+    }
+    Ok(combination)
+}
+
+/// Native endian u32s, a trailing partial value is ignored.
+fn decode_u32_array(bytes: &[u8]) -> Vec<u32> {
+    bytes
+        .chunks_exact(4)
+        .map(|chunk| u32::from_ne_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect()
+}
+
+/// Decode the nested `AttrWiphyBands` attribute, one `BandList` per band.
+pub fn decode_bands(attr: &Nlattr<Nl80211Attr, Buffer>) -> Result<Vec<BandList>, Nl80211Error> {
+    let handle: AttrHandle<'_, GenlBuffer<Nl80211Bandc, Buffer>, Nlattr<Nl80211Bandc, Buffer>> =
+        attr.get_attr_handle()?;
+    let mut bands = Vec::new();
+
+    for band in handle.iter() {
+        let mut bandlist = BandList::default();
+        match band.nla_type.nla_type {
+            Nl80211Bandc::Band2ghz => bandlist.band = WiFiBand::Band2GHz,
+            Nl80211Bandc::Band5ghz => bandlist.band = WiFiBand::Band5GHz,
+            Nl80211Bandc::Band60ghz => bandlist.band = WiFiBand::Band60GHz,
+            Nl80211Bandc::Band6ghz => bandlist.band = WiFiBand::Band6GHz,
+            Nl80211Bandc::BandS1ghz => {}
+            Nl80211Bandc::BandLC => {}
+            Nl80211Bandc::UnrecognizedConst(_) => {}
+        }
+
+        let bandhandle = band.get_attr_handle::<Nl80211BandAttr>()?;
+        for bandattr in bandhandle.iter() {
+            match bandattr.nla_type.nla_type {
+                Nl80211BandAttr::BandAttrFreqs => {
+                    let freqhandle = bandattr.get_attr_handle::<u16>()?;
+                    for freq in freqhandle.iter() {
+                        bandlist.channels.push(decode_channel(freq)?);
+                    }
+                }
+                Nl80211BandAttr::BandAttrRates => {
+                    let ratehandle = bandattr.get_attr_handle::<u16>()?;
+                    for rate in ratehandle.iter() {
+                        bandlist.bitrates.push(decode_bitrate(rate)?);
+                    }
+                }
+                Nl80211BandAttr::BandAttrHtMcsSet => {
+                    let ht = bandlist.ht.get_or_insert_with(HtCapabilities::default);
+                    ht.set_mcs_set(bandattr.nla_payload.as_ref());
+                }
+                Nl80211BandAttr::BandAttrHtCapa => {
+                    let ht = bandlist.ht.get_or_insert_with(HtCapabilities::default);
+                    ht.set_capa(bandattr.get_payload_as()?);
+                }
+                Nl80211BandAttr::BandAttrHtAmpduFactor => {
+                    let ht = bandlist.ht.get_or_insert_with(HtCapabilities::default);
+                    ht.ampdu_factor = bandattr.get_payload_as()?;
+                }
+                Nl80211BandAttr::BandAttrHtAmpduDensity => {
+                    let ht = bandlist.ht.get_or_insert_with(HtCapabilities::default);
+                    ht.ampdu_density = bandattr.get_payload_as()?;
+                }
+                Nl80211BandAttr::BandAttrVhtMcsSet => {
+                    let vht = bandlist.vht.get_or_insert_with(VhtCapabilities::default);
+                    vht.set_mcs_set(bandattr.nla_payload.as_ref());
+                }
+                Nl80211BandAttr::BandAttrVhtCapa => {
+                    let vht = bandlist.vht.get_or_insert_with(VhtCapabilities::default);
+                    vht.set_capa(bandattr.get_payload_as()?);
+                }
+                Nl80211BandAttr::BandAttrIftypeData => {
+                    let datahandle = bandattr.get_attr_handle::<u16>()?;
+                    for data in datahandle.iter() {
+                        bandlist.iftype_data.push(decode_iftype_data(data)?);
+                    }
+                }
+                _ => {}
+            }
+        }
+        bands.push(bandlist);
+    }
+    Ok(bands)
+}
+
+/// Decode one entry of `BandAttrIftypeData`.
+pub fn decode_iftype_data(attr: &Nlattr<u16, Buffer>) -> Result<IftypeCapabilities, Nl80211Error> {
+    let handle = attr.get_attr_handle::<Nl80211BandIftypeAttr>()?;
+    let mut data = IftypeCapabilities::default();
+    let payload = |ty: Nl80211BandIftypeAttr| {
+        handle
+            .get_attribute(ty)
+            .map(|attr| attr.nla_payload.as_ref())
+    };
+
+    if let Some(iftypes) = payload(Nl80211BandIftypeAttr::BandIftypeAttrIftypes) {
+        data.iftypes = decode_iftypes(iftypes.to_vec());
+    }
+    if let (Some(mac), Some(phy)) = (
+        payload(Nl80211BandIftypeAttr::BandIftypeAttrHeCapMac),
+        payload(Nl80211BandIftypeAttr::BandIftypeAttrHeCapPhy),
+    ) {
+        data.he = Some(HeCapabilities::decode(
+            mac,
+            phy,
+            payload(Nl80211BandIftypeAttr::BandIftypeAttrHeCapMcsSet).unwrap_or_default(),
+            payload(Nl80211BandIftypeAttr::BandIftypeAttrHeCapPpe).unwrap_or_default(),
+        ));
+    }
+    if let Some(attr) = handle.get_attribute(Nl80211BandIftypeAttr::BandIftypeAttrHe6ghzCapa) {
+        data.he_6ghz = Some(He6GhzCapabilities::from_capa(attr.get_payload_as()?));
+    }
+    if let (Some(mac), Some(phy)) = (
+        payload(Nl80211BandIftypeAttr::BandIftypeAttrEhtCapMac),
+        payload(Nl80211BandIftypeAttr::BandIftypeAttrEhtCapPhy),
+    ) {
+        data.eht = Some(EhtCapabilities::decode(
+            mac,
+            phy,
+            payload(Nl80211BandIftypeAttr::BandIftypeAttrEhtCapMcsSet).unwrap_or_default(),
+            payload(Nl80211BandIftypeAttr::BandIftypeAttrEhtCapPpe).unwrap_or_default(),
+            data.he.as_ref(),
+        ));
+    }
+    Ok(data)
+}
+
+/// Decode one entry of `BandAttrRates`.
+pub fn decode_bitrate(attr: &Nlattr<u16, Buffer>) -> Result<Bitrate, Nl80211Error> {
+    let handle = attr.get_attr_handle::<Nl80211BitrateAttr>()?;
+    let mut bitrate = Bitrate {
+        rate: 0,
+        short_preamble: false,
+    };
+
+    for rateattr in handle.iter() {
+        match rateattr.nla_type.nla_type {
+            Nl80211BitrateAttr::BitrateAttrRate => bitrate.rate = rateattr.get_payload_as()?,
+            Nl80211BitrateAttr::BitrateAttr2ghzShortpreamble => bitrate.short_preamble = true,
+            _ => {}
+        }
+    }
+    Ok(bitrate)
+}
+
+/// Decode one entry of `BandAttrFreqs`.
+pub fn decode_channel(attr: &Nlattr<u16, Buffer>) -> Result<ChannelData, Nl80211Error> {
+    let handle = attr.get_attr_handle::<Nl80211FrequencyAttr>()?;
+    let mut channel = ChannelData::default();
+    let mut disabled = false;
+
+    for freqattr in handle.iter() {
+        match freqattr.nla_type.nla_type {
+            Nl80211FrequencyAttr::FrequencyAttrFreq => {
+                let frequency: u32 = freqattr.get_payload_as()?;
+                channel.frequency = frequency;
+                channel.channel = chan_from_frequency(frequency)
+            }
+            Nl80211FrequencyAttr::FrequencyAttrDisabled => disabled = true,
+            Nl80211FrequencyAttr::FrequencyAttrMaxTxPower => {
+                channel.pwr = freqattr.get_payload_as()?;
+            }
+            Nl80211FrequencyAttr::FrequencyAttrNoIr => channel.no_ir = true,
+            Nl80211FrequencyAttr::FrequencyAttrRadar => channel.radar = true,
+            Nl80211FrequencyAttr::FrequencyAttrDfsState => {
+                let state: u32 = freqattr.get_payload_as()?;
+                channel.dfs_state = Some(Nl80211DfsState::from(state as u16));
+            }
+            Nl80211FrequencyAttr::FrequencyAttrDfsTime => {
+                channel.dfs_time = Some(freqattr.get_payload_as()?);
+            }
+            Nl80211FrequencyAttr::FrequencyAttrDfsCacTime => {
+                channel.dfs_cac_time = Some(freqattr.get_payload_as()?);
+            }
+            Nl80211FrequencyAttr::FrequencyAttrIndoorOnly => channel.indoor_only = true,
+            Nl80211FrequencyAttr::FrequencyAttrNoHt40Minus => channel.no_ht40_minus = true,
+            Nl80211FrequencyAttr::FrequencyAttrNoHt40Plus => channel.no_ht40_plus = true,
+            Nl80211FrequencyAttr::FrequencyAttrNo80mhz => channel.no_80mhz = true,
+            Nl80211FrequencyAttr::FrequencyAttrNo160mhz => channel.no_160mhz = true,
+            Nl80211FrequencyAttr::FrequencyAttrNo20mhz => channel.no_20mhz = true,
+            Nl80211FrequencyAttr::FrequencyAttrNo10mhz => channel.no_10mhz = true,
+            _ => {}
+        }
+    }
+    channel.status = FrequencyStatus::from_flags(disabled, channel.no_ir, channel.radar);
+    Ok(channel)
+}
+
+/// Decode a CmdNewInterface message.
+pub fn decode_interface(msg: &Nl80211Message) -> Result<Interface, Nl80211Error> {
+    let handle = msg.get_attr_handle();
+    let wiphy: u32 = handle.get_attr_payload_as(Nl80211Attr::AttrWiphy)?; // The proper way
+
+    // Get iftype
+    let iftype_payload: u32 = handle.get_attr_payload_as(Nl80211Attr::AttrIftype)?;
+
+    let lsb: u8 = (iftype_payload & 0xFF) as u8;
+
+    let iftype = Nl80211Iftype::from_u8(lsb).unwrap_or(Nl80211Iftype::IftypeUnspecified);
+
+    let mut interface = Interface::new(wiphy);
+
+    interface.current_iftype = Some(iftype);
+
+    // Iterate other attributes
+    for attr in handle.iter() {
+        match attr.nla_type.nla_type {
+            // IfIndex (eg: wlan0)
+            Nl80211Attr::AttrIfindex => {
+                interface.index = Some(attr.get_payload_as()?);
+            }
+            // IFNAME (eg: wlan0)
+            Nl80211Attr::AttrIfname => {
+                interface.name = Some(attr.get_payload_as_with_len()?);
+            }
+            // Mac Address of the interface
+            Nl80211Attr::AttrMac => {
+                let mut mac = Vec::new();
+                let vecmac: Vec<u8> = attr.get_payload_as_with_len()?;
+                for byte in vecmac {
+                    mac.push(byte);
+                }
+
+                interface.mac = Some(mac);
+            }
+            // The SSID the interface is associated with
+            Nl80211Attr::AttrSsid => {
+                interface.ssid = Some(attr.get_payload_as_with_len()?);
+            }
+            // The frequency the wireless interface is using
+            Nl80211Attr::AttrWiphyFreq => {
+                let frequency = attr.get_payload_as()?;
+                interface.frequency.frequency = Some(frequency);
+                interface.frequency.channel = Some(chan_from_frequency(frequency));
+            }
+            // Channel Type (Width)
+            Nl80211Attr::AttrChannelWidth => {
+                interface.frequency.width = Some(attr.get_payload_as()?);
+            }
+            // Transmission Power Level
+            Nl80211Attr::AttrWiphyTxPowerLevel => {
+                interface.frequency.pwr = Some(attr.get_payload_as()?);
+            }
+            // Wireless Device
+            Nl80211Attr::AttrWdev => interface.device = Some(attr.get_payload_as()?),
+            _ => (),
+        }
+    }
+    Ok(interface)
+}
+
+/// Decode a CmdNewScanResults message, None if it doesn't carry a BSS.
+pub fn decode_bss(msg: &Nl80211Message) -> Result<Option<Bss>, Nl80211Error> {
+    let handle = msg.get_attr_handle();
+    let bss_handle = match handle.get_attribute(Nl80211Attr::AttrBss) {
+        Some(attr) => attr.get_attr_handle::<Nl80211Bss>()?,
+        None => return Ok(None),
+    };
+
+    let mut bss = Bss::default();
+    for attr in bss_handle.iter() {
+        match attr.nla_type.nla_type {
+            Nl80211Bss::BssBssid => {
+                let bssid: Vec<u8> = attr.get_payload_as_with_len()?;
+                bss.bssid = bssid
+                    .try_into()
+                    .map_err(|_| Nl80211Error::Parse("BSSID has invalid length".to_string()))?;
+            }
+            Nl80211Bss::BssFrequency => {
+                bss.set_frequency(attr.get_payload_as()?);
+            }
+            Nl80211Bss::BssTsf => {
+                bss.tsf = Some(attr.get_payload_as()?);
+            }
+            Nl80211Bss::BssBeaconInterval => {
+                bss.beacon_interval = Some(attr.get_payload_as()?);
+            }
+            Nl80211Bss::BssCapability => {
+                bss.capability = Some(attr.get_payload_as()?);
+            }
+            Nl80211Bss::BssSignalMbm => {
+                bss.signal_mbm = Some(attr.get_payload_as()?);
+            }
+            Nl80211Bss::BssSignalUnspec => {
+                bss.signal_unspec = Some(attr.get_payload_as()?);
+            }
+            Nl80211Bss::BssSeenMsAgo => {
+                bss.seen_ms_ago = Some(attr.get_payload_as()?);
+            }
+            Nl80211Bss::BssStatus => {
+                let status: u32 = attr.get_payload_as()?;
+                bss.status = Some(Nl80211BssStatus::from(status as u16));
+            }
+            Nl80211Bss::BssInformationElements => {
+                bss.information_elements = Some(attr.get_payload_as_with_len()?);
+            }
+            Nl80211Bss::BssBeaconIes => {
+                bss.beacon_ies = Some(attr.get_payload_as_with_len()?);
+            }
+            _ => (),
+        }
+    }
+    Ok(Some(bss))
+}
+
+/// Decode a CmdNewSurveyResults message, None if it doesn't carry survey info.
+pub fn decode_survey(msg: &Nl80211Message) -> Result<Option<SurveyInfo>, Nl80211Error> {
+    let handle = msg.get_attr_handle();
+    let survey_handle = match handle.get_attribute(Nl80211Attr::AttrSurveyInfo) {
+        Some(attr) => attr.get_attr_handle::<Nl80211SurveyInfo>()?,
+        None => return Ok(None),
+    };
+
+    let mut survey = SurveyInfo::default();
+    for attr in survey_handle.iter() {
+        match attr.nla_type.nla_type {
+            Nl80211SurveyInfo::SurveyInfoFrequency => {
+                survey.frequency = attr.get_payload_as()?;
+                survey.channel = chan_from_frequency(survey.frequency);
+            }
+            Nl80211SurveyInfo::SurveyInfoNoise => {
+                survey.noise = Some(attr.get_payload_as()?);
+            }
+            Nl80211SurveyInfo::SurveyInfoInUse => survey.in_use = true,
+            Nl80211SurveyInfo::SurveyInfoTime => {
+                survey.time = Some(attr.get_payload_as()?);
+            }
+            Nl80211SurveyInfo::SurveyInfoTimeBusy => {
+                survey.time_busy = Some(attr.get_payload_as()?);
+            }
+            Nl80211SurveyInfo::SurveyInfoTimeExtBusy => {
+                survey.time_ext_busy = Some(attr.get_payload_as()?);
+            }
+            Nl80211SurveyInfo::SurveyInfoTimeRx => {
+                survey.time_rx = Some(attr.get_payload_as()?);
+            }
+            Nl80211SurveyInfo::SurveyInfoTimeTx => {
+                survey.time_tx = Some(attr.get_payload_as()?);
+            }
+            Nl80211SurveyInfo::SurveyInfoTimeScan => {
+                survey.time_scan = Some(attr.get_payload_as()?);
+            }
+            _ => (),
+        }
+    }
+    Ok(Some(survey))
+}
+
+/// Decode a `CMD_GET_REG` reply.
+pub fn decode_regdomain(msg: &Nl80211Message) -> Result<RegDomain, Nl80211Error> {
+    let handle = msg.get_attr_handle();
+    let mut regdomain = RegDomain::default();
+
+    for attr in handle.iter() {
+        match attr.nla_type.nla_type {
+            Nl80211Attr::AttrRegAlpha2 => {
+                regdomain.alpha2 = decode_string(attr.nla_payload.as_ref())?;
+            }
+            Nl80211Attr::AttrDfsRegion => {
+                let region: u8 = attr.get_payload_as()?;
+                regdomain.dfs_region = Nl80211DfsRegions::from(region as u16);
+            }
+            Nl80211Attr::AttrWiphy => {
+                regdomain.wiphy = Some(attr.get_payload_as()?);
+            }
+            Nl80211Attr::AttrWiphySelfManagedReg => regdomain.self_managed = true,
+            Nl80211Attr::AttrRegRules => {
+                let rules = attr.get_attr_handle::<u16>()?;
+                for rule in rules.iter() {
+                    let rule_handle = rule.get_attr_handle::<Nl80211RegRuleAttr>()?;
+                    let mut reg_rule = RegRule::default();
+                    for rule_attr in rule_handle.iter() {
+                        match rule_attr.nla_type.nla_type {
+                            Nl80211RegRuleAttr::AttrRegRuleFlags => {
+                                reg_rule.flags = rule_attr.get_payload_as()?;
+                            }
+                            Nl80211RegRuleAttr::AttrFreqRangeStart => {
+                                reg_rule.start_freq_khz = rule_attr.get_payload_as()?;
+                            }
+                            Nl80211RegRuleAttr::AttrFreqRangeEnd => {
+                                reg_rule.end_freq_khz = rule_attr.get_payload_as()?;
+                            }
+                            Nl80211RegRuleAttr::AttrFreqRangeMaxBw => {
+                                reg_rule.max_bandwidth_khz = rule_attr.get_payload_as()?;
+                            }
+                            Nl80211RegRuleAttr::AttrPowerRuleMaxAntGain => {
+                                reg_rule.max_antenna_gain = rule_attr.get_payload_as()?;
+                            }
+                            Nl80211RegRuleAttr::AttrPowerRuleMaxEirp => {
+                                reg_rule.max_eirp = rule_attr.get_payload_as()?;
+                            }
+                            Nl80211RegRuleAttr::AttrDfsCacTime => {
+                                reg_rule.dfs_cac_time = Some(rule_attr.get_payload_as()?);
+                            }
+                            _ => (),
+                        }
+                    }
+                    regdomain.rules.push(reg_rule);
+                }
+            }
+            _ => (),
+        }
+    }
+    Ok(regdomain)
+}
+
+/// Decode a `CMD_NEW_STATION` message.
+pub fn decode_station(msg: &Nl80211Message) -> Result<StationInfo, Nl80211Error> {
+    let handle = msg.get_attr_handle();
+    let mac: Vec<u8> = handle.get_attr_payload_as_with_len(Nl80211Attr::AttrMac)?;
+    let mut station = StationInfo::new(
+        mac.try_into()
+            .map_err(|_| Nl80211Error::Parse("Station MAC has invalid length".to_string()))?,
+    );
+    station.interface_index = handle.get_attr_payload_as(Nl80211Attr::AttrIfindex).ok();
+
+    let sta_info = match handle.get_attribute(Nl80211Attr::AttrStaInfo) {
+        Some(attr) => attr.get_attr_handle::<Nl80211StaInfo>()?,
+        None => return Ok(station),
+    };
+
+    for attr in sta_info.iter() {
+        match attr.nla_type.nla_type {
+            Nl80211StaInfo::StaInfoInactiveTime => {
+                station.inactive_time = Some(attr.get_payload_as()?);
+            }
+            // The 64 bit counters take precedence when both are present
+            Nl80211StaInfo::StaInfoRxBytes if station.rx_bytes.is_none() => {
+                let bytes: u32 = attr.get_payload_as()?;
+                station.rx_bytes = Some(bytes as u64);
+            }
+            Nl80211StaInfo::StaInfoTxBytes if station.tx_bytes.is_none() => {
+                let bytes: u32 = attr.get_payload_as()?;
+                station.tx_bytes = Some(bytes as u64);
+            }
+            Nl80211StaInfo::StaInfoRxBytes64 => {
+                station.rx_bytes = Some(attr.get_payload_as()?);
+            }
+            Nl80211StaInfo::StaInfoTxBytes64 => {
+                station.tx_bytes = Some(attr.get_payload_as()?);
+            }
+            Nl80211StaInfo::StaInfoRxPackets => {
+                station.rx_packets = Some(attr.get_payload_as()?);
+            }
+            Nl80211StaInfo::StaInfoTxPackets => {
+                station.tx_packets = Some(attr.get_payload_as()?);
+            }
+            Nl80211StaInfo::StaInfoTxRetries => {
+                station.tx_retries = Some(attr.get_payload_as()?);
+            }
+            Nl80211StaInfo::StaInfoTxFailed => {
+                station.tx_failed = Some(attr.get_payload_as()?);
+            }
+            Nl80211StaInfo::StaInfoRxDropMisc => {
+                station.rx_drop_misc = Some(attr.get_payload_as()?);
+            }
+            Nl80211StaInfo::StaInfoBeaconLoss => {
+                station.beacon_loss = Some(attr.get_payload_as()?);
+            }
+            Nl80211StaInfo::StaInfoBeaconRx => {
+                station.beacon_rx = Some(attr.get_payload_as()?);
+            }
+            Nl80211StaInfo::StaInfoSignal => {
+                station.signal = Some(attr.get_payload_as()?);
+            }
+            Nl80211StaInfo::StaInfoSignalAvg => {
+                station.signal_avg = Some(attr.get_payload_as()?);
+            }
+            Nl80211StaInfo::StaInfoBeaconSignalAvg => {
+                station.beacon_signal_avg = Some(attr.get_payload_as()?);
+            }
+            Nl80211StaInfo::StaInfoChainSignal => {
+                station.chain_signal = parse_chain_signal(attr)?;
+            }
+            Nl80211StaInfo::StaInfoChainSignalAvg => {
+                station.chain_signal_avg = parse_chain_signal(attr)?;
+            }
+            Nl80211StaInfo::StaInfoTxBitrate => {
+                station.tx_bitrate = Some(parse_rate_info(attr)?);
+            }
+            Nl80211StaInfo::StaInfoRxBitrate => {
+                station.rx_bitrate = Some(parse_rate_info(attr)?);
+            }
+            Nl80211StaInfo::StaInfoExpectedThroughput => {
+                station.expected_throughput = Some(attr.get_payload_as()?);
+            }
+            Nl80211StaInfo::StaInfoConnectedTime => {
+                station.connected_time = Some(attr.get_payload_as()?);
+            }
+            Nl80211StaInfo::StaInfoStaFlags => {
+                station.flags = StationFlags::from_bytes(attr.nla_payload.as_ref());
+            }
+            Nl80211StaInfo::StaInfoBssParam => {
+                let handle = attr.get_attr_handle::<Nl80211StaBssParam>()?;
+                let mut param = StationBssParam::default();
+                for bss_attr in handle.iter() {
+                    match bss_attr.nla_type.nla_type {
+                        Nl80211StaBssParam::StaBssParamCtsProt => param.cts_protection = true,
+                        Nl80211StaBssParam::StaBssParamShortPreamble => param.short_preamble = true,
+                        Nl80211StaBssParam::StaBssParamShortSlotTime => {
+                            param.short_slot_time = true
+                        }
+                        Nl80211StaBssParam::StaBssParamDtimPeriod => {
+                            param.dtim_period = Some(bss_attr.get_payload_as()?);
+                        }
+                        Nl80211StaBssParam::StaBssParamBeaconInterval => {
+                            param.beacon_interval = Some(bss_attr.get_payload_as()?);
+                        }
+                        _ => (),
+                    }
+                }
+                station.bss_param = Some(param);
+            }
+            Nl80211StaInfo::StaInfoTidStats => {
+                let handle = attr.get_attr_handle::<u16>()?;
+                for tid in handle.iter() {
+                    let tid_handle = tid.get_attr_handle::<Nl80211TidStats>()?;
+                    // TIDs are nested at index tid + 1
+                    let mut stats = TidStats {
+                        tid: tid.nla_type.nla_type.saturating_sub(1),
+                        ..Default::default()
+                    };
+                    for tid_attr in tid_handle.iter() {
+                        let value: Option<u64> = tid_attr.get_payload_as().ok();
+                        match tid_attr.nla_type.nla_type {
+                            Nl80211TidStats::TidStatsRxMsdu => stats.rx_msdu = value,
+                            Nl80211TidStats::TidStatsTxMsdu => stats.tx_msdu = value,
+                            Nl80211TidStats::TidStatsTxMsduRetries => stats.tx_msdu_retries = value,
+                            Nl80211TidStats::TidStatsTxMsduFailed => stats.tx_msdu_failed = value,
+                            _ => (),
+                        }
+                    }
+                    station.tid_stats.push(stats);
+                }
+            }
+            _ => (),
+        }
+    }
+    Ok(station)
+}
+
+/// Decode a nested per-chain signal list (one s8 attribute per antenna chain).
+fn parse_chain_signal(attr: &Nlattr<Nl80211StaInfo, Buffer>) -> Result<Vec<i8>, Nl80211Error> {
+    let handle = attr.get_attr_handle::<u16>()?;
+    let mut chains = Vec::new();
+    for chain in handle.iter() {
+        chains.push(chain.get_payload_as()?);
+    }
+    Ok(chains)
+}
+
+/// Decode a nested `Nl80211RateInfo` attribute.
+fn parse_rate_info(attr: &Nlattr<Nl80211StaInfo, Buffer>) -> Result<RateInfo, Nl80211Error> {
+    let handle = attr.get_attr_handle::<Nl80211RateInfo>()?;
+    let mut rate = RateInfo::default();
+
+    for rate_attr in handle.iter() {
+        match rate_attr.nla_type.nla_type {
+            // The 32 bit bitrate takes precedence when both are present
+            Nl80211RateInfo::RateInfoBitrate if rate.bitrate.is_none() => {
+                let bitrate: u16 = rate_attr.get_payload_as()?;
+                rate.bitrate = Some(bitrate as u32);
+            }
+            Nl80211RateInfo::RateInfoBitrate32 => {
+                rate.bitrate = Some(rate_attr.get_payload_as()?);
+            }
+            Nl80211RateInfo::RateInfoMcs => {
+                rate.mcs = Some(rate_attr.get_payload_as()?);
+            }
+            Nl80211RateInfo::RateInfoVhtMcs => {
+                rate.vht_mcs = Some(rate_attr.get_payload_as()?);
+            }
+            Nl80211RateInfo::RateInfoVhtNss => {
+                rate.vht_nss = Some(rate_attr.get_payload_as()?);
+            }
+            Nl80211RateInfo::RateInfoHeMcs => {
+                rate.he_mcs = Some(rate_attr.get_payload_as()?);
+            }
+            Nl80211RateInfo::RateInfoHeNss => {
+                rate.he_nss = Some(rate_attr.get_payload_as()?);
+            }
+            Nl80211RateInfo::RateInfoHeGi => {
+                rate.he_gi = Some(rate_attr.get_payload_as()?);
+            }
+            Nl80211RateInfo::RateInfoHeDcm => {
+                rate.he_dcm = Some(rate_attr.get_payload_as()?);
+            }
+            Nl80211RateInfo::RateInfoEhtMcs => {
+                rate.eht_mcs = Some(rate_attr.get_payload_as()?);
+            }
+            Nl80211RateInfo::RateInfoEhtNss => {
+                rate.eht_nss = Some(rate_attr.get_payload_as()?);
+            }
+            Nl80211RateInfo::RateInfoEhtGi => {
+                rate.eht_gi = Some(rate_attr.get_payload_as()?);
+            }
+            Nl80211RateInfo::RateInfoShortGi => rate.short_gi = true,
+            Nl80211RateInfo::RateInfo5MhzWidth => rate.width = Nl80211ChanWidth::ChanWidth5,
+            Nl80211RateInfo::RateInfo10MhzWidth => rate.width = Nl80211ChanWidth::ChanWidth10,
+            Nl80211RateInfo::RateInfo40MhzWidth => rate.width = Nl80211ChanWidth::ChanWidth40,
+            Nl80211RateInfo::RateInfo80MhzWidth => rate.width = Nl80211ChanWidth::ChanWidth80,
+            Nl80211RateInfo::RateInfo80p80MhzWidth => rate.width = Nl80211ChanWidth::ChanWidth80p80,
+            Nl80211RateInfo::RateInfo160MhzWidth => rate.width = Nl80211ChanWidth::ChanWidth160,
+            Nl80211RateInfo::RateInfo320MhzWidth => rate.width = Nl80211ChanWidth::ChanWidth320,
+            _ => (),
+        }
+    }
+    // Without an explicit width flag an MCS rate is a 20 MHz HT (or later) rate
+    if rate.width == Nl80211ChanWidth::ChanWidth20Noht && !rate.is_legacy() {
+        rate.width = Nl80211ChanWidth::ChanWidth20;
+    }
+    Ok(rate)
+}
@@ -0,0 +1,221 @@
+use crate::attr::Nl80211Attr;
+use crate::channels::ChannelError;
+use crate::cmd::Nl80211Cmd;
+use neli::attr::Attribute;
+use neli::consts::nl::Nlmsg;
+use neli::err::{DeError, NlError, Nlmsgerr, SerError, WrappedError};
+use neli::genl::Genlmsghdr;
+
+use std::fmt;
+use std::io;
+
+/// `NLMSGERR_ATTR_MSG`, the extended ACK error string
+const NLMSGERR_ATTR_MSG: u16 = 1;
+
+/// Errors returned by the nl80211 and rtnetlink sockets
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Nl80211Error {
+    /// The kernel rejected the request
+    Kernel {
+        command: &'static str,
+        errno: i32,
+        message: Option<String>, // Extended ACK message
+    },
+    /// Sending or receiving on the netlink socket failed
+    Socket {
+        command: &'static str,
+        errno: Option<i32>,
+        reason: String,
+    },
+    /// An interface, wiphy, station or other object wasn't found
+    NotFound(String),
+    /// A reply couldn't be decoded
+    Parse(String),
+    /// The request was rejected before it was sent
+    InvalidArgument(String),
+    /// The channel can't be used by the phy
+    Channel(ChannelError),
+    /// The kernel aborted the scan
+    ScanAborted,
+}
+
+impl Nl80211Error {
+    /// Error from a neli receive, extracting the errno of kernel and IO errors.
+    pub(crate) fn netlink<T: fmt::Debug, P: fmt::Debug>(
+        command: &'static str,
+        err: NlError<T, P>,
+    ) -> Nl80211Error {
+        match err {
+            NlError::Nlmsgerr(err) => Nl80211Error::Kernel {
+                command,
+                errno: -err.error,
+                message: None,
+            },
+            NlError::De(err) => Nl80211Error::Parse(format!("{} {}", command, err)),
+            NlError::Ser(err) => Nl80211Error::ser(command, err),
+            NlError::Wrapped(WrappedError::IOError(err)) => Nl80211Error::io(command, err),
+            err => Nl80211Error::Socket {
+                command,
+                errno: None,
+                reason: err.to_string(),
+            },
+        }
+    }
+
+    /// Error from a nl80211 receive, including the extended ACK message if the kernel sent one.
+    pub(crate) fn genl(
+        command: &'static str,
+        err: NlError<Nlmsg, Genlmsghdr<Nl80211Cmd, Nl80211Attr>>,
+    ) -> Nl80211Error {
+        match err {
+            NlError::Nlmsgerr(err) => Nl80211Error::kernel(command, &err),
+            err => Nl80211Error::netlink(command, err),
+        }
+    }
+
+    /// Error from a nl80211 error message.
+    pub(crate) fn kernel<T>(
+        command: &'static str,
+        err: &Nlmsgerr<T, Genlmsghdr<Nl80211Cmd, Nl80211Attr>>,
+    ) -> Nl80211Error {
+        Nl80211Error::Kernel {
+            command,
+            errno: -err.error,
+            message: ext_ack_message(err),
+        }
+    }
+
+    /// Error from a netlink error message, without extended ACK parsing.
+    pub(crate) fn nlmsgerr<T, P>(command: &'static str, err: &Nlmsgerr<T, P>) -> Nl80211Error {
+        Nl80211Error::Kernel {
+            command,
+            errno: -err.error,
+            message: None,
+        }
+    }
+
+    pub(crate) fn io(command: &'static str, err: io::Error) -> Nl80211Error {
+        Nl80211Error::Socket {
+            command,
+            errno: err.raw_os_error(),
+            reason: err.to_string(),
+        }
+    }
+
+    pub(crate) fn ser(command: &'static str, err: SerError) -> Nl80211Error {
+        match err {
+            SerError::Wrapped(WrappedError::IOError(err)) => Nl80211Error::io(command, err),
+            err => Nl80211Error::Socket {
+                command,
+                errno: None,
+                reason: err.to_string(),
+            },
+        }
+    }
+
+    /// The errno reported by the kernel or the socket, if any.
+    pub fn errno(&self) -> Option<i32> {
+        match self {
+            Nl80211Error::Kernel { errno, .. } => Some(*errno),
+            Nl80211Error::Socket { errno, .. } => *errno,
+            _ => None,
+        }
+    }
+
+    /// The command that failed, for kernel and socket errors.
+    pub fn command(&self) -> Option<&'static str> {
+        match self {
+            Nl80211Error::Kernel { command, .. } | Nl80211Error::Socket { command, .. } => {
+                Some(command)
+            }
+            _ => None,
+        }
+    }
+
+    /// EBUSY, the device is in use (e.g. scanning or changing state)
+    pub fn is_busy(&self) -> bool {
+        self.errno() == Some(libc::EBUSY)
+    }
+
+    /// EOPNOTSUPP, the driver doesn't support the request
+    pub fn is_not_supported(&self) -> bool {
+        self.errno() == Some(libc::EOPNOTSUPP)
+    }
+
+    /// ENODEV, the interface or wiphy doesn't exist
+    pub fn is_no_device(&self) -> bool {
+        self.errno() == Some(libc::ENODEV)
+    }
+
+    /// EPERM, missing CAP_NET_ADMIN
+    pub fn is_permission_denied(&self) -> bool {
+        self.errno() == Some(libc::EPERM)
+    }
+
+    /// EINVAL from the kernel or an argument rejected before sending
+    pub fn is_invalid_argument(&self) -> bool {
+        matches!(self, Nl80211Error::InvalidArgument(_)) || self.errno() == Some(libc::EINVAL)
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, Nl80211Error::NotFound(_)) || self.is_no_device()
+    }
+}
+
+impl fmt::Display for Nl80211Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Nl80211Error::Kernel {
+                command,
+                errno,
+                message,
+            } => {
+                write!(f, "{} {}", command, io::Error::from_raw_os_error(*errno))?;
+                if let Some(message) = message {
+                    write!(f, ": {}", message)?;
+                }
+                Ok(())
+            }
+            Nl80211Error::Socket {
+                command, reason, ..
+            } => write!(f, "{} {}", command, reason),
+            Nl80211Error::NotFound(what) => write!(f, "{} Not Found", what),
+            Nl80211Error::Parse(reason) => write!(f, "Parse error: {}", reason),
+            Nl80211Error::InvalidArgument(reason) => write!(f, "Invalid argument: {}", reason),
+            Nl80211Error::Channel(err) => write!(f, "{}", err),
+            Nl80211Error::ScanAborted => write!(f, "Scan aborted"),
+        }
+    }
+}
+
+impl std::error::Error for Nl80211Error {}
+
+impl From<DeError> for Nl80211Error {
+    fn from(err: DeError) -> Self {
+        Nl80211Error::Parse(err.to_string())
+    }
+}
+
+impl From<ChannelError> for Nl80211Error {
+    fn from(err: ChannelError) -> Self {
+        Nl80211Error::Channel(err)
+    }
+}
+
+/// The extended ACK TLVs follow the echoed request, so neli parses them as trailing attributes of
+/// the request. Anything past the echoed request's length is an extended ACK attribute.
+fn ext_ack_message<T>(err: &Nlmsgerr<T, Genlmsghdr<Nl80211Cmd, Nl80211Attr>>) -> Option<String> {
+    // nlmsghdr + genlmsghdr
+    let mut offset: usize = 16 + 4;
+    let request_len = err.nlmsg.nl_len as usize;
+
+    for attr in err.nlmsg.nl_payload.get_attr_handle().iter() {
+        if offset >= request_len && u16::from(attr.nla_type.nla_type) == NLMSGERR_ATTR_MSG {
+            let bytes = attr.payload().as_ref();
+            let bytes = bytes.strip_suffix(&[0]).unwrap_or(bytes);
+            return Some(String::from_utf8_lossy(bytes).into_owned());
+        }
+        offset += (attr.nla_len as usize + 3) & !3;
+    }
+    None
+}
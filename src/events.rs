@@ -0,0 +1,432 @@
+use crate::attr::Attrs;
+use crate::attr::{
+    Nl80211Attr, Nl80211AttrCqm, Nl80211ChanWidth, Nl80211CqmRssiThresholdEvent, Nl80211Iftype,
+    Nl80211RegInitiator, Nl80211RegType,
+};
+use crate::cmd::Nl80211Cmd;
+use crate::decode::decode_string;
+use crate::error::Nl80211Error;
+use crate::NL_80211_GENL_NAME;
+use neli::consts::socket::NlFamily;
+use neli::genl::Genlmsghdr;
+use neli::socket::NlSocketHandle;
+
+use std::fmt;
+use std::os::unix::io::{AsRawFd, RawFd};
+
+/// The nl80211 multicast groups
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum McastGroup {
+    Config,     // Interfaces and wiphys added, removed or changed, channel switches
+    Scan,       // Scan started, finished and aborted
+    Regulatory, // Regulatory domain changes
+    Mlme,       // Connect, disconnect, (de)authentication, (dis)association, CQM
+    Vendor,     // Vendor specific events
+    Nan,        // Neighbor awareness networking
+    Testmode,   // Only present on kernels built with CONFIG_NL80211_TESTMODE
+}
+
+impl McastGroup {
+    pub const ALL: [McastGroup; 7] = [
+        McastGroup::Config,
+        McastGroup::Scan,
+        McastGroup::Regulatory,
+        McastGroup::Mlme,
+        McastGroup::Vendor,
+        McastGroup::Nan,
+        McastGroup::Testmode,
+    ];
+
+    /// Name of the group as registered with the generic netlink controller
+    pub fn name(&self) -> &'static str {
+        match self {
+            McastGroup::Config => "config",
+            McastGroup::Scan => "scan",
+            McastGroup::Regulatory => "regulatory",
+            McastGroup::Mlme => "mlme",
+            McastGroup::Vendor => "vendor",
+            McastGroup::Nan => "nan",
+            McastGroup::Testmode => "testmode",
+        }
+    }
+}
+
+/// A notification sent by the kernel on one of the nl80211 multicast groups
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Nl80211Event {
+    /// CmdNewWiphy
+    WiphyAdded {
+        wiphy: Option<u32>,
+        name: Option<String>,
+    },
+    /// CmdDelWiphy
+    WiphyRemoved { wiphy: Option<u32> },
+    /// CmdNewInterface
+    InterfaceAdded {
+        wiphy: Option<u32>,
+        ifindex: Option<u32>,
+        name: Option<String>,
+        iftype: Option<Nl80211Iftype>,
+    },
+    /// CmdDelInterface
+    InterfaceRemoved {
+        wiphy: Option<u32>,
+        ifindex: Option<u32>,
+        name: Option<String>,
+    },
+    /// CmdChSwitchNotify, or CmdChSwitchStartedNotify when `started` is set
+    ChannelSwitch {
+        ifindex: Option<u32>,
+        started: bool,
+        frequency: Option<u32>,
+        width: Option<Nl80211ChanWidth>,
+        center_freq1: Option<u32>,
+        center_freq2: Option<u32>,
+    },
+    /// CmdTriggerScan
+    ScanStarted { ifindex: Option<u32> },
+    /// CmdNewScanResults, the results can be fetched with `get_scan_results`
+    ScanFinished { ifindex: Option<u32> },
+    /// CmdScanAborted
+    ScanAborted { ifindex: Option<u32> },
+    /// CmdSchedScanResults
+    ScheduledScanResults { ifindex: Option<u32> },
+    /// CmdRegChange, or CmdWiphyRegChange for a self-managed wiphy
+    RegulatoryChange {
+        wiphy: Option<u32>,
+        initiator: Option<Nl80211RegInitiator>,
+        reg_type: Option<Nl80211RegType>,
+        alpha2: Option<String>,
+    },
+    /// CmdConnect
+    Connect {
+        ifindex: Option<u32>,
+        bssid: Option<[u8; 6]>,
+        status: Option<u16>,
+        timed_out: bool,
+    },
+    /// CmdRoam
+    Roam {
+        ifindex: Option<u32>,
+        bssid: Option<[u8; 6]>,
+    },
+    /// CmdDisconnect
+    Disconnect {
+        ifindex: Option<u32>,
+        reason: Option<u16>,
+        by_ap: bool,
+    },
+    /// CmdDeauthenticate, or CmdUnprotDeauthenticate when `unprotected` is set: an unprotected
+    /// frame was received and dropped because the connection uses management frame protection
+    Deauthenticate {
+        ifindex: Option<u32>,
+        unprotected: bool,
+        frame: Vec<u8>,
+    },
+    /// CmdDisassociate, or CmdUnprotDisassociate when `unprotected` is set: an unprotected frame
+    /// was received and dropped because the connection uses management frame protection
+    Disassociate {
+        ifindex: Option<u32>,
+        unprotected: bool,
+        frame: Vec<u8>,
+    },
+    /// CmdNotifyCqm, connection quality monitor
+    Cqm {
+        ifindex: Option<u32>,
+        rssi_event: Option<Nl80211CqmRssiThresholdEvent>,
+        packet_loss: Option<u32>,
+        beacon_loss: bool,
+    },
+    /// Any other notification
+    Other {
+        cmd: Nl80211Cmd,
+        wiphy: Option<u32>,
+        ifindex: Option<u32>,
+    },
+    /// The socket's receive buffer overflowed and notifications were lost, anything kept up to
+    /// date from them has to be queried again
+    Resync,
+}
+
+impl Nl80211Event {
+    /// Decode a multicast notification.
+    pub fn from_genl(msg: &Genlmsghdr<Nl80211Cmd, Nl80211Attr>) -> Nl80211Event {
+        let handle = msg.get_attr_handle();
+        let wiphy: Option<u32> = handle.get_attr_payload_as(Nl80211Attr::AttrWiphy).ok();
+        let ifindex: Option<u32> = handle.get_attr_payload_as(Nl80211Attr::AttrIfindex).ok();
+
+        match msg.cmd {
+            Nl80211Cmd::CmdNewWiphy => Nl80211Event::WiphyAdded {
+                wiphy,
+                name: string_attr(&handle, Nl80211Attr::AttrWiphyName),
+            },
+            Nl80211Cmd::CmdDelWiphy => Nl80211Event::WiphyRemoved { wiphy },
+            Nl80211Cmd::CmdNewInterface => Nl80211Event::InterfaceAdded {
+                wiphy,
+                ifindex,
+                name: string_attr(&handle, Nl80211Attr::AttrIfname),
+                iftype: handle
+                    .get_attr_payload_as::<u32>(Nl80211Attr::AttrIftype)
+                    .ok()
+                    .map(|iftype| Nl80211Iftype::from(iftype as u16)),
+            },
+            Nl80211Cmd::CmdDelInterface => Nl80211Event::InterfaceRemoved {
+                wiphy,
+                ifindex,
+                name: string_attr(&handle, Nl80211Attr::AttrIfname),
+            },
+            Nl80211Cmd::CmdChSwitchNotify | Nl80211Cmd::CmdChSwitchStartedNotify => {
+                Nl80211Event::ChannelSwitch {
+                    ifindex,
+                    started: msg.cmd == Nl80211Cmd::CmdChSwitchStartedNotify,
+                    frequency: handle.get_attr_payload_as(Nl80211Attr::AttrWiphyFreq).ok(),
+                    width: handle
+                        .get_attr_payload_as::<u32>(Nl80211Attr::AttrChannelWidth)
+                        .ok()
+                        .map(|width| Nl80211ChanWidth::from(width as u16)),
+                    center_freq1: handle
+                        .get_attr_payload_as(Nl80211Attr::AttrCenterFreq1)
+                        .ok(),
+                    center_freq2: handle
+                        .get_attr_payload_as(Nl80211Attr::AttrCenterFreq2)
+                        .ok(),
+                }
+            }
+            Nl80211Cmd::CmdTriggerScan => Nl80211Event::ScanStarted { ifindex },
+            Nl80211Cmd::CmdNewScanResults => Nl80211Event::ScanFinished { ifindex },
+            Nl80211Cmd::CmdScanAborted => Nl80211Event::ScanAborted { ifindex },
+            Nl80211Cmd::CmdSchedScanResults => Nl80211Event::ScheduledScanResults { ifindex },
+            Nl80211Cmd::CmdRegChange | Nl80211Cmd::CmdWiphyRegChange => {
+                Nl80211Event::RegulatoryChange {
+                    wiphy,
+                    initiator: handle
+                        .get_attr_payload_as::<u8>(Nl80211Attr::AttrRegInitiator)
+                        .ok()
+                        .map(|initiator| Nl80211RegInitiator::from(initiator as u16)),
+                    reg_type: handle
+                        .get_attr_payload_as::<u8>(Nl80211Attr::AttrRegType)
+                        .ok()
+                        .map(|reg_type| Nl80211RegType::from(reg_type as u16)),
+                    alpha2: string_attr(&handle, Nl80211Attr::AttrRegAlpha2),
+                }
+            }
+            Nl80211Cmd::CmdConnect => Nl80211Event::Connect {
+                ifindex,
+                bssid: mac_attr(&handle),
+                status: handle.get_attr_payload_as(Nl80211Attr::AttrStatusCode).ok(),
+                timed_out: handle.get_attribute(Nl80211Attr::AttrTimedOut).is_some(),
+            },
+            Nl80211Cmd::CmdRoam => Nl80211Event::Roam {
+                ifindex,
+                bssid: mac_attr(&handle),
+            },
+            Nl80211Cmd::CmdDisconnect => Nl80211Event::Disconnect {
+                ifindex,
+                reason: handle.get_attr_payload_as(Nl80211Attr::AttrReasonCode).ok(),
+                by_ap: handle
+                    .get_attribute(Nl80211Attr::AttrDisconnectedByAp)
+                    .is_some(),
+            },
+            Nl80211Cmd::CmdDeauthenticate | Nl80211Cmd::CmdUnprotDeauthenticate => {
+                Nl80211Event::Deauthenticate {
+                    ifindex,
+                    unprotected: msg.cmd == Nl80211Cmd::CmdUnprotDeauthenticate,
+                    frame: frame_attr(&handle),
+                }
+            }
+            Nl80211Cmd::CmdDisassociate | Nl80211Cmd::CmdUnprotDisassociate => {
+                Nl80211Event::Disassociate {
+                    ifindex,
+                    unprotected: msg.cmd == Nl80211Cmd::CmdUnprotDisassociate,
+                    frame: frame_attr(&handle),
+                }
+            }
+            Nl80211Cmd::CmdNotifyCqm => {
+                let mut rssi_event = None;
+                let mut packet_loss = None;
+                let mut beacon_loss = false;
+                if let Some(cqm) = handle.get_attribute(Nl80211Attr::AttrCqm) {
+                    if let Ok(cqm) = cqm.get_attr_handle::<Nl80211AttrCqm>() {
+                        rssi_event = cqm
+                            .get_attr_payload_as::<u32>(Nl80211AttrCqm::AttrCqmRssiThresholdEvent)
+                            .ok()
+                            .map(|event| Nl80211CqmRssiThresholdEvent::from(event as u16));
+                        packet_loss = cqm
+                            .get_attr_payload_as(Nl80211AttrCqm::AttrCqmPktLossEvent)
+                            .ok();
+                        beacon_loss = cqm
+                            .get_attribute(Nl80211AttrCqm::AttrCqmBeaconLossEvent)
+                            .is_some();
+                    }
+                }
+                Nl80211Event::Cqm {
+                    ifindex,
+                    rssi_event,
+                    packet_loss,
+                    beacon_loss,
+                }
+            }
+            cmd => Nl80211Event::Other {
+                cmd,
+                wiphy,
+                ifindex,
+            },
+        }
+    }
+
+    /// 802.11 reason code of a deauthentication or disassociation frame.
+    pub fn reason_code(&self) -> Option<u16> {
+        match self {
+            Nl80211Event::Disconnect { reason, .. } => *reason,
+            Nl80211Event::Deauthenticate { frame, .. }
+            | Nl80211Event::Disassociate { frame, .. } => frame
+                .get(24..26)
+                .map(|reason| u16::from_le_bytes([reason[0], reason[1]])),
+            _ => None,
+        }
+    }
+}
+
+fn string_attr(handle: &Attrs<'_, Nl80211Attr>, attr: Nl80211Attr) -> Option<String> {
+    handle
+        .get_attribute(attr)
+        .and_then(|attr| decode_string(attr.nla_payload.as_ref()).ok())
+}
+
+fn mac_attr(handle: &Attrs<'_, Nl80211Attr>) -> Option<[u8; 6]> {
+    handle
+        .get_attr_payload_as_with_len::<Vec<u8>>(Nl80211Attr::AttrMac)
+        .ok()
+        .and_then(|mac| mac.try_into().ok())
+}
+
+fn frame_attr(handle: &Attrs<'_, Nl80211Attr>) -> Vec<u8> {
+    handle
+        .get_attr_payload_as_with_len(Nl80211Attr::AttrFrame)
+        .unwrap_or_default()
+}
+
+/// A socket subscribed to nl80211 multicast groups, iterating yields the notifications as they arrive.
+pub struct Nl80211Events {
+    sock: NlSocketHandle,
+    family_id: u16,
+    groups: Vec<McastGroup>,
+}
+
+impl fmt::Debug for Nl80211Events {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Nl80211Events")
+            .field("sock", &format_args!("<NlSocketHandle>"))
+            .field("family_id", &self.family_id)
+            .field("groups", &self.groups)
+            .finish()
+    }
+}
+
+impl Nl80211Events {
+    /// Join the given groups, fails if one of them isn't registered by the kernel.
+    pub fn subscribe(groups: &[McastGroup]) -> Result<Self, Nl80211Error> {
+        let mut events = Nl80211Events::connect()?;
+        for group in groups {
+            events.join(*group)?;
+        }
+        Ok(events)
+    }
+
+    /// Join every group the kernel registers.
+    pub fn subscribe_all() -> Result<Self, Nl80211Error> {
+        let mut events = Nl80211Events::connect()?;
+        for group in McastGroup::ALL {
+            match events.join(group) {
+                Ok(()) => (),
+                Err(Nl80211Error::NotFound(_)) => continue,
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(events)
+    }
+
+    fn connect() -> Result<Self, Nl80211Error> {
+        let mut sock = NlSocketHandle::connect(NlFamily::Generic, None, &[])
+            .map_err(|err| Nl80211Error::io("connect", err))?;
+        let family_id = sock
+            .resolve_genl_family(NL_80211_GENL_NAME)
+            .map_err(|err| Nl80211Error::netlink("CTRL_CMD_GETFAMILY", err))?;
+        Ok(Nl80211Events {
+            sock,
+            family_id,
+            groups: Vec::new(),
+        })
+    }
+
+    /// Join another group.
+    pub fn join(&mut self, group: McastGroup) -> Result<(), Nl80211Error> {
+        if self.groups.contains(&group) {
+            return Ok(());
+        }
+        // Resolve on a separate socket, this one may already be receiving notifications.
+        let mut ctrl = NlSocketHandle::connect(NlFamily::Generic, None, &[])
+            .map_err(|err| Nl80211Error::io("connect", err))?;
+        let id = ctrl
+            .resolve_nl_mcast_group(NL_80211_GENL_NAME, group.name())
+            .map_err(|_| Nl80211Error::NotFound(format!("Multicast group {}", group.name())))?;
+        self.sock
+            .add_mcast_membership(&[id])
+            .map_err(|err| Nl80211Error::io("add_mcast_membership", err))?;
+        self.groups.push(group);
+        Ok(())
+    }
+
+    pub fn groups(&self) -> &[McastGroup] {
+        &self.groups
+    }
+
+    /// Switch the socket between blocking (the default) and non-blocking reads.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> Result<(), Nl80211Error> {
+        if nonblocking {
+            self.sock.nonblock()
+        } else {
+            self.sock.block()
+        }
+        .map_err(|err| Nl80211Error::io("fcntl", err))
+    }
+
+    /// Wait for the next event, or return None if the socket is non-blocking and nothing is pending.
+    pub fn next_event(&mut self) -> Result<Option<Nl80211Event>, Nl80211Error> {
+        loop {
+            let msg = match self.sock.recv::<u16, Genlmsghdr<Nl80211Cmd, Nl80211Attr>>() {
+                Ok(msg) => msg,
+                Err(err) => {
+                    let err = Nl80211Error::netlink("recv", err);
+                    if err.errno() == Some(libc::ENOBUFS) {
+                        return Ok(Some(Nl80211Event::Resync));
+                    }
+                    return Err(err);
+                }
+            };
+            let Some(msg) = msg else {
+                return Ok(None);
+            };
+            if msg.nl_type != self.family_id {
+                continue;
+            }
+            if let Some(payload) = msg.nl_payload.get_payload() {
+                return Ok(Some(Nl80211Event::from_genl(payload)));
+            }
+        }
+    }
+}
+
+impl Iterator for Nl80211Events {
+    type Item = Result<Nl80211Event, Nl80211Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event().transpose()
+    }
+}
+
+impl AsRawFd for Nl80211Events {
+    fn as_raw_fd(&self) -> RawFd {
+        self.sock.as_raw_fd()
+    }
+}
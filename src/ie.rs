@@ -0,0 +1,588 @@
+// The capability elements share their layout with the nl80211 band attributes
+pub use crate::band::{HeCapabilities, HtCapabilities, VhtCapabilities};
+
+/// Element IDs from IEEE 802.11-2020 9.4.2.1
+pub const EID_SSID: u8 = 0;
+pub const EID_SUPPORTED_RATES: u8 = 1;
+pub const EID_DS_PARAMETER_SET: u8 = 3;
+pub const EID_TIM: u8 = 5;
+pub const EID_COUNTRY: u8 = 7;
+pub const EID_HT_CAPABILITIES: u8 = 45;
+pub const EID_RSN: u8 = 48;
+pub const EID_EXTENDED_SUPPORTED_RATES: u8 = 50;
+pub const EID_HT_OPERATION: u8 = 61;
+pub const EID_EXTENDED_CAPABILITIES: u8 = 127;
+pub const EID_VHT_CAPABILITIES: u8 = 191;
+pub const EID_VHT_OPERATION: u8 = 192;
+pub const EID_VENDOR_SPECIFIC: u8 = 221;
+pub const EID_EXTENSION: u8 = 255;
+
+/// Element ID Extensions carried in `EID_EXTENSION`
+pub const EID_EXT_HE_CAPABILITIES: u8 = 35;
+pub const EID_EXT_HE_OPERATION: u8 = 36;
+
+/// Microsoft / Wi-Fi Alliance OUI used by the WPA and WPS vendor elements
+pub const OUI_MICROSOFT: [u8; 3] = [0x00, 0x50, 0xf2];
+/// IEEE 802.11 OUI used by RSN cipher and AKM suites
+pub const OUI_IEEE80211: [u8; 3] = [0x00, 0x0f, 0xac];
+
+const VENDOR_TYPE_WPA: u8 = 1;
+const VENDOR_TYPE_WPS: u8 = 4;
+
+/// A decoded Information Element
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InformationElement {
+    Ssid(Vec<u8>),
+    SupportedRates(Vec<SupportedRate>),
+    ExtendedSupportedRates(Vec<SupportedRate>),
+    DsParameterSet(u8), // Current channel
+    Tim(Tim),
+    Country(Country),
+    Rsn(Rsn),
+    Wpa(Rsn),
+    HtCapabilities(HtCapabilities),
+    HtOperation(HtOperation),
+    VhtCapabilities(VhtCapabilities),
+    VhtOperation(VhtOperation),
+    HeCapabilities(HeCapabilities),
+    HeOperation(HeOperation),
+    ExtendedCapabilities(ExtendedCapabilities),
+    Wps(Wps),
+    Vendor { oui: [u8; 3], data: Vec<u8> },
+    Unknown { id: u8, data: Vec<u8> },
+}
+
+/// Parse a buffer of Information Elements (e.g. `BssInformationElements` or `BssBeaconIes`).
+///
+/// Elements that cannot be decoded are kept as `Unknown` (or `Vendor`), parsing stops at a truncated element.
+pub fn parse_information_elements(bytes: &[u8]) -> Vec<InformationElement> {
+    let mut elements = Vec::new();
+    let mut offset = 0;
+
+    while offset + 2 <= bytes.len() {
+        let id = bytes[offset];
+        let len = bytes[offset + 1] as usize;
+        let start = offset + 2;
+        if start + len > bytes.len() {
+            break;
+        }
+        elements.push(InformationElement::parse(id, &bytes[start..start + len]));
+        offset = start + len;
+    }
+    elements
+}
+
+impl InformationElement {
+    /// Decode a single element body, falling back to the raw TLV.
+    pub fn parse(id: u8, data: &[u8]) -> InformationElement {
+        let parsed = match id {
+            EID_SSID => Some(InformationElement::Ssid(data.to_vec())),
+            EID_SUPPORTED_RATES => Some(InformationElement::SupportedRates(
+                data.iter().map(|b| SupportedRate::from_u8(*b)).collect(),
+            )),
+            EID_EXTENDED_SUPPORTED_RATES => Some(InformationElement::ExtendedSupportedRates(
+                data.iter().map(|b| SupportedRate::from_u8(*b)).collect(),
+            )),
+            EID_DS_PARAMETER_SET => data.first().map(|c| InformationElement::DsParameterSet(*c)),
+            EID_TIM => Tim::parse(data).map(InformationElement::Tim),
+            EID_COUNTRY => Country::parse(data).map(InformationElement::Country),
+            EID_RSN => Rsn::parse(data, OUI_IEEE80211).map(InformationElement::Rsn),
+            EID_HT_CAPABILITIES => {
+                HtCapabilities::parse(data).map(InformationElement::HtCapabilities)
+            }
+            EID_HT_OPERATION => HtOperation::parse(data).map(InformationElement::HtOperation),
+            EID_VHT_CAPABILITIES => {
+                VhtCapabilities::parse(data).map(InformationElement::VhtCapabilities)
+            }
+            EID_VHT_OPERATION => VhtOperation::parse(data).map(InformationElement::VhtOperation),
+            EID_EXTENDED_CAPABILITIES => Some(InformationElement::ExtendedCapabilities(
+                ExtendedCapabilities(data.to_vec()),
+            )),
+            EID_VENDOR_SPECIFIC => parse_vendor(data),
+            EID_EXTENSION => match data.split_first() {
+                Some((&EID_EXT_HE_CAPABILITIES, body)) => {
+                    HeCapabilities::parse(body).map(InformationElement::HeCapabilities)
+                }
+                Some((&EID_EXT_HE_OPERATION, body)) => {
+                    HeOperation::parse(body).map(InformationElement::HeOperation)
+                }
+                _ => None,
+            },
+            _ => None,
+        };
+        parsed.unwrap_or_else(|| InformationElement::Unknown {
+            id,
+            data: data.to_vec(),
+        })
+    }
+}
+
+fn parse_vendor(data: &[u8]) -> Option<InformationElement> {
+    if data.len() < 3 {
+        return None;
+    }
+    let oui = [data[0], data[1], data[2]];
+    if oui == OUI_MICROSOFT {
+        match data.get(3) {
+            Some(&VENDOR_TYPE_WPA) => {
+                if let Some(wpa) = Rsn::parse(&data[4..], OUI_MICROSOFT) {
+                    return Some(InformationElement::Wpa(wpa));
+                }
+            }
+            Some(&VENDOR_TYPE_WPS) => return Some(InformationElement::Wps(Wps::parse(&data[4..]))),
+            _ => {}
+        }
+    }
+    Some(InformationElement::Vendor {
+        oui,
+        data: data[3..].to_vec(),
+    })
+}
+
+fn le_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn le_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+/// An entry of the (Extended) Supported Rates element
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SupportedRate {
+    pub rate: u8,    // Rate in units of 500 kbps
+    pub basic: bool, // Part of the BSS basic rate set
+}
+
+impl SupportedRate {
+    pub fn from_u8(value: u8) -> SupportedRate {
+        SupportedRate {
+            rate: value & 0x7f,
+            basic: value & 0x80 != 0,
+        }
+    }
+
+    pub fn mbps(&self) -> f32 {
+        self.rate as f32 / 2.0
+    }
+}
+
+/// Traffic Indication Map
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tim {
+    pub dtim_count: u8,
+    pub dtim_period: u8,
+    pub bitmap_control: u8,
+    pub partial_virtual_bitmap: Vec<u8>,
+}
+
+impl Tim {
+    pub fn parse(data: &[u8]) -> Option<Tim> {
+        if data.len() < 3 {
+            return None;
+        }
+        Some(Tim {
+            dtim_count: data[0],
+            dtim_period: data[1],
+            bitmap_control: data[2],
+            partial_virtual_bitmap: data[3..].to_vec(),
+        })
+    }
+
+    /// Group addressed traffic is buffered at the AP.
+    pub fn multicast_buffered(&self) -> bool {
+        self.bitmap_control & 0x01 != 0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Country {
+    pub country: String, // Two letter country code
+    pub environment: u8, // b' ' (any), b'I' (indoor), b'O' (outdoor) or b'X' (non-country)
+    pub triplets: Vec<CountryTriplet>,
+}
+
+/// Subband triplet of the Country element
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CountryTriplet {
+    pub first_channel: u8, // Values >= 201 indicate an operating extension triplet
+    pub num_channels: u8,
+    pub max_tx_power: u8, // dBm
+}
+
+impl Country {
+    pub fn parse(data: &[u8]) -> Option<Country> {
+        if data.len() < 3 {
+            return None;
+        }
+        Some(Country {
+            country: String::from_utf8_lossy(&data[0..2]).to_string(),
+            environment: data[2],
+            triplets: data[3..]
+                .chunks_exact(3)
+                .map(|t| CountryTriplet {
+                    first_channel: t[0],
+                    num_channels: t[1],
+                    max_tx_power: t[2],
+                })
+                .collect(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CipherSuite {
+    UseGroup,
+    Wep40,
+    Tkip,
+    Ccmp128,
+    Wep104,
+    BipCmac128,
+    GroupAddressedTrafficNotAllowed,
+    Gcmp128,
+    Gcmp256,
+    Ccmp256,
+    BipGmac128,
+    BipGmac256,
+    BipCmac256,
+    Other([u8; 4]),
+}
+
+impl CipherSuite {
+    pub fn from_selector(selector: [u8; 4], oui: [u8; 3]) -> CipherSuite {
+        if selector[0..3] != oui {
+            return CipherSuite::Other(selector);
+        }
+        match selector[3] {
+            0 => CipherSuite::UseGroup,
+            1 => CipherSuite::Wep40,
+            2 => CipherSuite::Tkip,
+            4 => CipherSuite::Ccmp128,
+            5 => CipherSuite::Wep104,
+            6 => CipherSuite::BipCmac128,
+            7 => CipherSuite::GroupAddressedTrafficNotAllowed,
+            8 => CipherSuite::Gcmp128,
+            9 => CipherSuite::Gcmp256,
+            10 => CipherSuite::Ccmp256,
+            11 => CipherSuite::BipGmac128,
+            12 => CipherSuite::BipGmac256,
+            13 => CipherSuite::BipCmac256,
+            _ => CipherSuite::Other(selector),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AkmSuite {
+    Ieee8021x,
+    Psk,
+    FtIeee8021x,
+    FtPsk,
+    Ieee8021xSha256,
+    PskSha256,
+    Tdls,
+    Sae,
+    FtSae,
+    ApPeerKey,
+    SuiteB,
+    SuiteB192,
+    FtIeee8021xSha384,
+    FilsSha256,
+    FilsSha384,
+    FtFilsSha256,
+    FtFilsSha384,
+    Owe,
+    FtPskSha384,
+    PskSha384,
+    SaeExtKey,
+    FtSaeExtKey,
+    Other([u8; 4]),
+}
+
+impl AkmSuite {
+    pub fn from_selector(selector: [u8; 4], oui: [u8; 3]) -> AkmSuite {
+        if selector[0..3] != oui {
+            return AkmSuite::Other(selector);
+        }
+        match selector[3] {
+            1 => AkmSuite::Ieee8021x,
+            2 => AkmSuite::Psk,
+            3 => AkmSuite::FtIeee8021x,
+            4 => AkmSuite::FtPsk,
+            5 => AkmSuite::Ieee8021xSha256,
+            6 => AkmSuite::PskSha256,
+            7 => AkmSuite::Tdls,
+            8 => AkmSuite::Sae,
+            9 => AkmSuite::FtSae,
+            10 => AkmSuite::ApPeerKey,
+            11 => AkmSuite::SuiteB,
+            12 => AkmSuite::SuiteB192,
+            13 => AkmSuite::FtIeee8021xSha384,
+            14 => AkmSuite::FilsSha256,
+            15 => AkmSuite::FilsSha384,
+            16 => AkmSuite::FtFilsSha256,
+            17 => AkmSuite::FtFilsSha384,
+            18 => AkmSuite::Owe,
+            19 => AkmSuite::FtPskSha384,
+            20 => AkmSuite::PskSha384,
+            24 => AkmSuite::SaeExtKey,
+            25 => AkmSuite::FtSaeExtKey,
+            _ => AkmSuite::Other(selector),
+        }
+    }
+}
+
+/// RSN element, also used for the body of the legacy WPA vendor element
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Rsn {
+    pub version: u16,
+    pub group_cipher: Option<CipherSuite>,
+    pub pairwise_ciphers: Vec<CipherSuite>,
+    pub akm_suites: Vec<AkmSuite>,
+    pub capabilities: Option<u16>,
+    pub pmkids: Vec<[u8; 16]>,
+    pub group_management_cipher: Option<CipherSuite>,
+}
+
+impl Rsn {
+    /// Every field after the version is optional, parsing stops at the end of the element.
+    pub fn parse(data: &[u8], oui: [u8; 3]) -> Option<Rsn> {
+        let mut rsn = Rsn {
+            version: le_u16(data, 0)?,
+            ..Default::default()
+        };
+        let mut offset = 2;
+        let selector =
+            |offset: usize| -> Option<[u8; 4]> { data.get(offset..offset + 4)?.try_into().ok() };
+
+        let Some(group) = selector(offset) else {
+            return Some(rsn);
+        };
+        rsn.group_cipher = Some(CipherSuite::from_selector(group, oui));
+        offset += 4;
+
+        let Some(count) = le_u16(data, offset) else {
+            return Some(rsn);
+        };
+        offset += 2;
+        for _ in 0..count {
+            rsn.pairwise_ciphers
+                .push(CipherSuite::from_selector(selector(offset)?, oui));
+            offset += 4;
+        }
+
+        let Some(count) = le_u16(data, offset) else {
+            return Some(rsn);
+        };
+        offset += 2;
+        for _ in 0..count {
+            rsn.akm_suites
+                .push(AkmSuite::from_selector(selector(offset)?, oui));
+            offset += 4;
+        }
+
+        rsn.capabilities = le_u16(data, offset);
+        if rsn.capabilities.is_none() {
+            return Some(rsn);
+        }
+        offset += 2;
+
+        let Some(count) = le_u16(data, offset) else {
+            return Some(rsn);
+        };
+        offset += 2;
+        for _ in 0..count {
+            rsn.pmkids
+                .push(data.get(offset..offset + 16)?.try_into().ok()?);
+            offset += 16;
+        }
+
+        rsn.group_management_cipher = selector(offset).map(|s| CipherSuite::from_selector(s, oui));
+        Some(rsn)
+    }
+
+    pub fn preauthentication(&self) -> bool {
+        self.capabilities.unwrap_or(0) & 0x0001 != 0
+    }
+
+    pub fn mfp_required(&self) -> bool {
+        self.capabilities.unwrap_or(0) & 0x0040 != 0
+    }
+
+    pub fn mfp_capable(&self) -> bool {
+        self.capabilities.unwrap_or(0) & 0x0080 != 0
+    }
+}
+
+/// HT Operation element
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HtOperation {
+    pub primary_channel: u8,
+    pub info: [u8; 5],
+    pub basic_mcs_set: [u8; 16],
+}
+
+impl HtOperation {
+    pub fn parse(data: &[u8]) -> Option<HtOperation> {
+        if data.len() < 22 {
+            return None;
+        }
+        Some(HtOperation {
+            primary_channel: data[0],
+            info: data[1..6].try_into().ok()?,
+            basic_mcs_set: data[6..22].try_into().ok()?,
+        })
+    }
+
+    /// 0: no secondary channel, 1: secondary above, 3: secondary below
+    pub fn secondary_channel_offset(&self) -> u8 {
+        self.info[0] & 0x3
+    }
+
+    /// The STA may use any channel width in its supported channel width set
+    pub fn any_channel_width(&self) -> bool {
+        self.info[0] & 0x4 != 0
+    }
+}
+
+/// VHT Operation element
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VhtOperation {
+    pub channel_width: u8, // 0: 20/40 MHz, 1: 80/160/80+80 MHz
+    pub center_freq_seg0: u8,
+    pub center_freq_seg1: u8,
+    pub basic_mcs_nss_set: u16,
+}
+
+impl VhtOperation {
+    pub fn parse(data: &[u8]) -> Option<VhtOperation> {
+        if data.len() < 5 {
+            return None;
+        }
+        Some(VhtOperation {
+            channel_width: data[0],
+            center_freq_seg0: data[1],
+            center_freq_seg1: data[2],
+            basic_mcs_nss_set: le_u16(data, 3)?,
+        })
+    }
+}
+
+/// HE Operation element
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeOperation {
+    pub parameters: u32, // 24 bits
+    pub bss_color_info: u8,
+    pub basic_mcs_nss_set: u16,
+    pub optional: Vec<u8>, // VHT operation info, max co-hosted BSSID indicator, 6 GHz operation info
+}
+
+impl HeOperation {
+    pub fn parse(data: &[u8]) -> Option<HeOperation> {
+        if data.len() < 6 {
+            return None;
+        }
+        Some(HeOperation {
+            parameters: u32::from_le_bytes([data[0], data[1], data[2], 0]),
+            bss_color_info: data[3],
+            basic_mcs_nss_set: le_u16(data, 4)?,
+            optional: data[6..].to_vec(),
+        })
+    }
+
+    pub fn bss_color(&self) -> u8 {
+        self.bss_color_info & 0x3f
+    }
+
+    pub fn six_ghz_operation_present(&self) -> bool {
+        self.parameters & (1 << 17) != 0
+    }
+}
+
+/// Extended Capabilities element, a little endian bitfield
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ExtendedCapabilities(pub Vec<u8>);
+
+impl ExtendedCapabilities {
+    pub fn has(&self, bit: usize) -> bool {
+        self.0
+            .get(bit / 8)
+            .map(|byte| byte & (1 << (bit % 8)) != 0)
+            .unwrap_or(false)
+    }
+
+    pub fn bss_transition(&self) -> bool {
+        self.has(19)
+    }
+
+    pub fn interworking(&self) -> bool {
+        self.has(31)
+    }
+}
+
+/// Wi-Fi Protected Setup vendor element
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Wps {
+    pub version: Option<u8>,
+    pub version2: Option<u8>,
+    pub state: Option<u8>, // 1: not configured, 2: configured
+    pub ap_setup_locked: Option<bool>,
+    pub selected_registrar: Option<bool>,
+    pub device_password_id: Option<u16>,
+    pub config_methods: Option<u16>,
+    pub response_type: Option<u8>,
+    pub uuid: Option<Vec<u8>>,
+    pub manufacturer: Option<String>,
+    pub model_name: Option<String>,
+    pub model_number: Option<String>,
+    pub serial_number: Option<String>,
+    pub primary_device_type: Option<Vec<u8>>,
+    pub device_name: Option<String>,
+    pub rf_bands: Option<u8>,
+}
+
+impl Wps {
+    /// WPS attributes are big endian type/length/value triplets.
+    pub fn parse(data: &[u8]) -> Wps {
+        let mut wps = Wps::default();
+        let mut offset = 0;
+        while offset + 4 <= data.len() {
+            let attr_type = u16::from_be_bytes([data[offset], data[offset + 1]]);
+            let len = u16::from_be_bytes([data[offset + 2], data[offset + 3]]) as usize;
+            let start = offset + 4;
+            let Some(value) = data.get(start..start + len) else {
+                break;
+            };
+            let string = || Some(String::from_utf8_lossy(value).to_string());
+            let be_u16 = || value.get(0..2).map(|v| u16::from_be_bytes([v[0], v[1]]));
+            match attr_type {
+                0x104a => wps.version = value.first().copied(),
+                0x1044 => wps.state = value.first().copied(),
+                0x1057 => wps.ap_setup_locked = value.first().map(|v| *v != 0),
+                0x1041 => wps.selected_registrar = value.first().map(|v| *v != 0),
+                0x1012 => wps.device_password_id = be_u16(),
+                0x1008 | 0x1053 => wps.config_methods = be_u16(),
+                0x103b => wps.response_type = value.first().copied(),
+                0x1047 | 0x1048 => wps.uuid = Some(value.to_vec()),
+                0x1021 => wps.manufacturer = string(),
+                0x1023 => wps.model_name = string(),
+                0x1024 => wps.model_number = string(),
+                0x1042 => wps.serial_number = string(),
+                0x1054 => wps.primary_device_type = Some(value.to_vec()),
+                0x1011 => wps.device_name = string(),
+                0x103c => wps.rf_bands = value.first().copied(),
+                // WFA vendor extension carrying Version2 (subelement 0x00)
+                0x1049 if value.len() >= 6 && value[0..4] == [0x00, 0x37, 0x2a, 0x00] => {
+                    wps.version2 = Some(value[5]);
+                }
+                _ => {}
+            }
+            offset = start + len;
+        }
+        wps
+    }
+}
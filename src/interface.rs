@@ -1,4 +1,5 @@
 use crate::attr::Nl80211Iftype;
+use crate::attr::Nl80211MntrFlags;
 use crate::attr::Operstate;
 use crate::channels::pretty_print_band_lists;
 use crate::channels::BandList;
@@ -9,6 +10,14 @@ use crate::phy::WirelessPhy;
 use crate::util::wrap_in_box;
 use std::collections::HashMap;
 
+/// Key of an interface: its netdev index, or the wireless device id for netdev-less interfaces
+/// such as P2P devices and NAN
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum InterfaceId {
+    Index(u32), // AttrIfindex
+    Wdev(u64),  // AttrWdev
+}
+
 /// A struct representing a wifi interface
 #[non_exhaustive]
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
@@ -22,7 +31,8 @@ pub struct Interface {
     pub phy_name: u32,                         // AttrWiphy
     pub device: Option<u64>,                   // Attr
     pub current_iftype: Option<Nl80211Iftype>, // AttrIftype
-    pub frequency: Frequency,          // PHY's operating frequency
+    pub monitor_flags: Option<MonitorFlags>, // AttrMntrFlags, as applied through Nl80211, the kernel doesn't report them
+    pub frequency: Frequency,                // PHY's operating frequency
 }
 
 impl Interface {
@@ -37,6 +47,7 @@ impl Interface {
             phy_name: wiphy,
             device: None,
             current_iftype: None,
+            monitor_flags: None,
             frequency: Frequency::default(),
         }
     }
@@ -51,7 +62,7 @@ impl Interface {
                     let bandu8 = band.band.to_u8();
                     let mut channels: Vec<u32> = Vec::new();
                     for channel in band.channels {
-                        if channel.status == FrequencyStatus::Enabled {
+                        if channel.status.is_enabled() {
                             channels.push(channel.channel)
                         }
                     }
@@ -70,7 +81,14 @@ impl Interface {
             .map(|n| String::from_utf8(n.clone()).unwrap_or_else(|_| "Invalid UTF-8".to_string()))
             .unwrap_or("Unknown".to_string());
         let stripped_name = name.strip_suffix('\0');
-        stripped_name.unwrap().to_string()
+        stripped_name.unwrap_or(&name).to_string()
+    }
+
+    /// The ifindex, or the wdev id if the interface has no netdev.
+    pub fn id(&self) -> Option<InterfaceId> {
+        self.index
+            .map(InterfaceId::Index)
+            .or(self.device.map(InterfaceId::Wdev))
     }
 
     pub fn index_as_string(&self) -> String {
@@ -99,12 +117,15 @@ impl Interface {
             self.index_as_string(),
             self.driver_as_string()
         );
-        let mode_monitor_line = format!(
+        let mut mode_monitor_line = format!(
             "Mode: {:?} | Active Monitor: {:?}",
             self.current_iftype
                 .unwrap_or(Nl80211Iftype::IftypeUnspecified),
             self.phy.clone().unwrap().active_monitor.unwrap()
         );
+        if let Some(flags) = self.monitor_flags {
+            mode_monitor_line += &format!(" | Monitor Flags: {}", flags.to_string_list());
+        }
         let modes_line = format!(
             "Modes: {}",
             iftypes_to_string_list(&self.phy.clone().unwrap().iftypes.clone().unwrap())
@@ -161,5 +182,117 @@ impl Interface {
         if self.current_iftype.is_none() {
             self.current_iftype = other.current_iftype;
         }
+        if self.monitor_flags.is_none() {
+            self.monitor_flags = other.monitor_flags;
+        }
+    }
+}
+
+/// Optional settings for a virtual interface created with `CMD_NEW_INTERFACE`
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct InterfaceOptions {
+    pub monitor_flags: MonitorFlags, // AttrMntrFlags, only used for monitor interfaces
+    pub four_address: Option<bool>,  // Attr4addr
+    pub mac: Option<[u8; 6]>,        // AttrMac
+}
+
+impl InterfaceOptions {
+    /// Options for a monitor interface with the given flags.
+    pub fn monitor(flags: MonitorFlags) -> InterfaceOptions {
+        InterfaceOptions {
+            monitor_flags: flags,
+            ..Default::default()
+        }
+    }
+}
+
+/// A set of `Nl80211MntrFlags`, one bit per flag
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MonitorFlags(u32);
+
+impl MonitorFlags {
+    const ALL: [Nl80211MntrFlags; 6] = [
+        Nl80211MntrFlags::MntrFlagFcsfail,
+        Nl80211MntrFlags::MntrFlagPlcpfail,
+        Nl80211MntrFlags::MntrFlagControl,
+        Nl80211MntrFlags::MntrFlagOtherBss,
+        Nl80211MntrFlags::MntrFlagCookFrames,
+        Nl80211MntrFlags::MntrFlagActive,
+    ];
+
+    pub fn new() -> MonitorFlags {
+        MonitorFlags(0)
+    }
+
+    pub fn from_flags(flags: &[Nl80211MntrFlags]) -> MonitorFlags {
+        flags
+            .iter()
+            .fold(MonitorFlags::new(), |set, flag| set.with(*flag))
+    }
+
+    /// Only `MntrFlagActive`, the flags `set_interface_monitor(true, ..)` applies.
+    pub fn active() -> MonitorFlags {
+        MonitorFlags::new().with(Nl80211MntrFlags::MntrFlagActive)
+    }
+
+    /// Returns the set with the flag added, for chaining.
+    pub fn with(mut self, flag: Nl80211MntrFlags) -> MonitorFlags {
+        self.insert(flag);
+        self
+    }
+
+    pub fn insert(&mut self, flag: Nl80211MntrFlags) {
+        self.0 |= Self::bit(flag);
+    }
+
+    pub fn remove(&mut self, flag: Nl80211MntrFlags) {
+        self.0 &= !Self::bit(flag);
+    }
+
+    pub fn contains(&self, flag: Nl80211MntrFlags) -> bool {
+        self.0 & Self::bit(flag) != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+
+    /// The flags in the set, in attribute order.
+    pub fn flags(&self) -> Vec<Nl80211MntrFlags> {
+        Self::ALL
+            .iter()
+            .filter(|flag| self.contains(**flag))
+            .copied()
+            .collect()
+    }
+
+    pub fn to_string_list(&self) -> String {
+        if self.is_empty() {
+            return "none".to_string();
+        }
+        self.flags()
+            .iter()
+            .map(|flag| match flag {
+                Nl80211MntrFlags::MntrFlagFcsfail => "fcsfail",
+                Nl80211MntrFlags::MntrFlagPlcpfail => "plcpfail",
+                Nl80211MntrFlags::MntrFlagControl => "control",
+                Nl80211MntrFlags::MntrFlagOtherBss => "otherbss",
+                Nl80211MntrFlags::MntrFlagCookFrames => "cook",
+                Nl80211MntrFlags::MntrFlagActive => "active",
+                _ => "unknown",
+            })
+            .collect::<Vec<&str>>()
+            .join(", ")
+    }
+
+    fn bit(flag: Nl80211MntrFlags) -> u32 {
+        match u16::from(flag) {
+            n @ 1..=31 => 1 << n,
+            _ => 0,
+        }
     }
 }
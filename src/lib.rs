@@ -1,75 +1,338 @@
 #![allow(dead_code)]
 #![allow(unused_imports)]
+#[cfg(feature = "tokio")]
+pub mod asyncsocket;
 pub mod attr;
+pub mod band;
+pub mod capture;
 pub mod channels;
 pub mod cmd;
+pub mod combination;
+pub mod decode;
+pub mod error;
+pub mod events;
+pub mod ie;
 pub mod interface;
 pub mod ntsocket;
 pub mod phy;
+pub mod reg;
+pub mod request;
 pub mod rtsocket;
+pub mod scan;
+pub mod station;
+pub mod survey;
+mod transaction;
+pub mod transport;
 pub mod util;
 
+#[cfg(feature = "tokio")]
+pub use asyncsocket::{AsyncLinkMonitor, AsyncNl80211Events, AsyncNtSocket, AsyncRtSocket};
 use attr::{Nl80211ChanWidth, Nl80211ChannelType, Nl80211Iftype, Operstate};
-use channels::{chan_to_frequency, WiFiBand};
-pub use interface::Interface;
+pub use capture::PcapWriter;
+use channels::{chan_from_frequency, chan_to_frequency, WiFiBand};
+pub use channels::{ChannelError, ChannelSpec};
+use cmd::Nl80211Cmd;
+pub use combination::{ConcurrencyRequest, InterfaceCombination};
+pub use error::Nl80211Error;
+pub use events::{McastGroup, Nl80211Event, Nl80211Events};
+pub use interface::{Interface, InterfaceId, InterfaceOptions, MonitorFlags};
 use ntsocket::NtSocket;
 use phy::WirelessPhy;
+pub use reg::RegDomain;
+pub use request::Nl80211Request;
 use rtsocket::RtSocket;
+pub use rtsocket::{LinkEvent, LinkInfo, LinkMonitor};
+pub use scan::{Bss, ScanRequest};
+pub use station::StationInfo;
+use survey::{rank_channels, ChannelUtilization};
 
 use std::collections::HashMap;
+use std::path::Path;
 
 pub const NL_80211_GENL_NAME: &str = "nl80211";
 pub const NL_80211_GENL_VERSION: u8 = 1;
 
+/// How `Nl80211` brings `interfaces` and `wirelessphys` up to date after a command
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CacheMode {
+    /// Re-dump every wiphy and interface
+    #[default]
+    Full,
+    /// Only re-query the interface the command touched, use `refresh()` for a full resync
+    Incremental,
+}
+
 #[derive(Debug)]
 pub struct Nl80211 {
     pub nt_socket: NtSocket,
     pub rt_socket: RtSocket,
     pub wirelessphys: HashMap<u32, WirelessPhy>,
-    pub interfaces: HashMap<u32, Interface>,
+    pub interfaces: HashMap<InterfaceId, Interface>,
+    cache_mode: CacheMode,
+    /// Monitor flags applied by ifindex, nl80211 accepts them but never reports them back
+    monitor_flags: HashMap<u32, MonitorFlags>,
+    events: Option<Nl80211Events>,
+    link_monitor: Option<LinkMonitor>,
 }
 
 impl Nl80211 {
     /// Creates a new instance of the Nl80211 Struct.
-    pub fn new() -> Result<Nl80211, String> {
+    pub fn new() -> Result<Nl80211, Nl80211Error> {
         let mut nt_socket: NtSocket = NtSocket::connect()?;
         let mut rt_socket: RtSocket = RtSocket::connect()?;
 
         let wirelessphys: HashMap<u32, phy::WirelessPhy> = nt_socket.cmd_get_all_wiphy()?;
-        let mut interfaces: HashMap<u32, Interface> = nt_socket.cmd_get_interfaces()?;
-
-        for (phy, interface) in &mut interfaces {
-            if wirelessphys.contains_key(phy) {
-                if let Some(index) = interface.index {
-                    interface.phy = wirelessphys.get(phy).cloned();
-                    interface.state = Some(rt_socket.get_interface_status(index)?);
-                }
-            }
-        }
+        let mut interfaces: HashMap<InterfaceId, Interface> = nt_socket.cmd_get_interfaces()?;
+        resolve_interfaces(&mut interfaces, &wirelessphys, &mut rt_socket)?;
 
         Ok(Nl80211 {
             nt_socket,
             rt_socket,
             wirelessphys,
             interfaces,
+            cache_mode: CacheMode::Full,
+            monitor_flags: HashMap::new(),
+            events: None,
+            link_monitor: None,
         })
     }
 
-    /// Updates the interfaces and Wiphy lists of the struct.
-    fn update_interfaces(&mut self) -> Result<(), String> {
-        let wirelessphys: HashMap<u32, phy::WirelessPhy> = self.nt_socket.cmd_get_all_wiphy()?;
-        let mut interfaces: HashMap<u32, Interface> = self.nt_socket.cmd_get_interfaces()?;
+    /// Creates a new instance that only refreshes what a command touched.
+    pub fn with_cache_mode(cache_mode: CacheMode) -> Result<Nl80211, Nl80211Error> {
+        let mut nl80211 = Nl80211::new()?;
+        nl80211.cache_mode = cache_mode;
+        Ok(nl80211)
+    }
+
+    pub fn cache_mode(&self) -> CacheMode {
+        self.cache_mode
+    }
+
+    pub fn set_cache_mode(&mut self, cache_mode: CacheMode) {
+        self.cache_mode = cache_mode;
+    }
+
+    /// Re-dump every wiphy and interface, regardless of the cache mode.
+    pub fn refresh(&mut self) -> Result<(), Nl80211Error> {
+        self.update_interfaces()
+    }
+
+    /// Called after a command changed the interface.
+    fn interface_changed(&mut self, index: u32) -> Result<(), Nl80211Error> {
+        match self.cache_mode {
+            CacheMode::Full => self.update_interfaces(),
+            CacheMode::Incremental => self.refresh_interface(index),
+        }
+    }
+
+    /// Re-query a single interface and its operstate, fetching the wiphy if it isn't known yet.
+    fn refresh_interface(&mut self, index: u32) -> Result<(), Nl80211Error> {
+        let mut interface = match self.nt_socket.cmd_get_interface(index) {
+            Ok(interface) => interface,
+            Err(err) if err.is_not_found() => {
+                self.remove_interface(index);
+                return Ok(());
+            }
+            Err(err) => return Err(err),
+        };
+        let phy = interface.phy_name;
+        if !self.wirelessphys.contains_key(&phy) {
+            let wiphy = self.nt_socket.cmd_get_wiphy(phy)?;
+            self.wirelessphys.insert(phy, wiphy);
+        }
+        interface.phy = self.wirelessphys.get(&phy).cloned();
+        interface.state = Some(self.rt_socket.get_interface_status(index)?);
+        self.interfaces.insert(InterfaceId::Index(index), interface);
+        self.restore_monitor_flags();
+        Ok(())
+    }
+
+    /// Re-query a wiphy, e.g. after a regulatory change, and update the interfaces using it.
+    fn refresh_wiphy(&mut self, phy: u32) -> Result<(), Nl80211Error> {
+        let wiphy = match self.nt_socket.cmd_get_wiphy(phy) {
+            Ok(wiphy) => wiphy,
+            Err(err) if err.is_not_found() => {
+                self.wirelessphys.remove(&phy);
+                self.interfaces
+                    .retain(|_, interface| interface.phy_name != phy);
+                return Ok(());
+            }
+            Err(err) => return Err(err),
+        };
+        for interface in self.interfaces.values_mut() {
+            if interface.phy_name == phy {
+                interface.phy = Some(wiphy.clone());
+            }
+        }
+        self.wirelessphys.insert(phy, wiphy);
+        Ok(())
+    }
+
+    fn remove_interface(&mut self, index: u32) {
+        self.interfaces.remove(&InterfaceId::Index(index));
+        self.monitor_flags.remove(&index);
+    }
+
+    /// Remember the monitor flags applied to an interface, None when the kernel picks them.
+    fn applied_monitor_flags(&mut self, index: u32, flags: Option<MonitorFlags>) {
+        match flags {
+            Some(flags) => self.monitor_flags.insert(index, flags),
+            None => self.monitor_flags.remove(&index),
+        };
+    }
+
+    /// Put the applied monitor flags back on the cached interfaces after they were re-queried,
+    /// forgetting those of interfaces that are gone or left monitor mode.
+    fn restore_monitor_flags(&mut self) {
+        let interfaces = &mut self.interfaces;
+        self.monitor_flags.retain(|index, flags| {
+            match interfaces.get_mut(&InterfaceId::Index(*index)) {
+                Some(interface)
+                    if interface.current_iftype == Some(Nl80211Iftype::IftypeMonitor) =>
+                {
+                    interface.monitor_flags = Some(*flags);
+                    true
+                }
+                _ => false,
+            }
+        });
+    }
+
+    /// Subscribe to the nl80211 config and regulatory groups and to rtnetlink link
+    /// notifications, `process_events` then applies them to the cache.
+    pub fn enable_events(&mut self) -> Result<(), Nl80211Error> {
+        let events = Nl80211Events::subscribe(&[McastGroup::Config, McastGroup::Regulatory])?;
+        events.set_nonblocking(true)?;
+        let link_monitor = LinkMonitor::connect(true)?;
+        link_monitor.set_nonblocking(true)?;
+        self.events = Some(events);
+        self.link_monitor = Some(link_monitor);
+        Ok(())
+    }
+
+    pub fn disable_events(&mut self) {
+        self.events = None;
+        self.link_monitor = None;
+    }
 
-        for (phy, interface) in &mut interfaces {
-            if wirelessphys.contains_key(phy) {
-                if let Some(index) = interface.index {
-                    interface.phy = wirelessphys.get(phy).cloned();
-                    interface.state = Some(self.rt_socket.get_interface_status(index)?);
+    /// Capture the nl80211 and rtnetlink traffic of both sockets to a pcap file for Wireshark.
+    pub fn enable_capture<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Nl80211Error> {
+        let capture = PcapWriter::create(path)?;
+        self.rt_socket.set_capture(Some(capture.clone()));
+        self.nt_socket.set_capture(Some(capture))
+    }
+
+    pub fn disable_capture(&mut self) {
+        self.rt_socket.set_capture(None);
+        // Only resolves the family when a capture is set
+        let _ = self.nt_socket.set_capture(None);
+    }
+
+    /// Apply every pending notification to the cache without blocking, returns how many were
+    /// applied. Does nothing unless `enable_events` was called. Notifications read before a
+    /// receive error are still applied before the error is returned.
+    pub fn process_events(&mut self) -> Result<usize, Nl80211Error> {
+        let mut read_error = None;
+        let mut events = Vec::new();
+        if let Some(subscription) = &mut self.events {
+            loop {
+                match subscription.next_event() {
+                    Ok(Some(event)) => events.push(event),
+                    Ok(None) => break,
+                    Err(err) => {
+                        read_error = Some(err);
+                        break;
+                    }
                 }
             }
         }
+        let mut link_events = Vec::new();
+        if let Some(monitor) = &mut self.link_monitor {
+            loop {
+                match monitor.next_event() {
+                    Ok(Some(event)) => link_events.push(event),
+                    Ok(None) => break,
+                    Err(err) => {
+                        read_error.get_or_insert(err);
+                        break;
+                    }
+                }
+            }
+        }
+
+        for event in &events {
+            self.apply_event(event)?;
+        }
+        for event in &link_events {
+            self.apply_link_event(event)?;
+        }
+        match read_error {
+            Some(err) => Err(err),
+            None => Ok(events.len() + link_events.len()),
+        }
+    }
+
+    /// Apply a nl80211 notification to the cached wiphys and interfaces.
+    pub fn apply_event(&mut self, event: &Nl80211Event) -> Result<(), Nl80211Error> {
+        match event {
+            Nl80211Event::WiphyAdded {
+                wiphy: Some(phy), ..
+            } => self.refresh_wiphy(*phy)?,
+            Nl80211Event::WiphyRemoved { wiphy: Some(phy) } => {
+                self.wirelessphys.remove(phy);
+                self.interfaces
+                    .retain(|_, interface| interface.phy_name != *phy);
+            }
+            Nl80211Event::InterfaceAdded {
+                ifindex: Some(index),
+                ..
+            }
+            | Nl80211Event::Other {
+                cmd: Nl80211Cmd::CmdSetInterface,
+                ifindex: Some(index),
+                ..
+            } => self.refresh_interface(*index)?,
+            Nl80211Event::InterfaceRemoved {
+                ifindex: Some(index),
+                ..
+            } => self.remove_interface(*index),
+            Nl80211Event::ChannelSwitch {
+                ifindex: Some(index),
+                started: false,
+                frequency,
+                width,
+                ..
+            } => {
+                if let Some(interface) = self.interface_mut(*index) {
+                    interface.frequency.frequency = *frequency;
+                    interface.frequency.channel = frequency.map(chan_from_frequency);
+                    interface.frequency.width = *width;
+                }
+            }
+            Nl80211Event::Resync => self.update_interfaces()?,
+            Nl80211Event::RegulatoryChange {
+                wiphy: Some(phy), ..
+            } => self.refresh_wiphy(*phy)?,
+            Nl80211Event::RegulatoryChange { wiphy: None, .. } => {
+                let phys: Vec<u32> = self.wirelessphys.keys().copied().collect();
+                for phy in phys {
+                    self.refresh_wiphy(phy)?;
+                }
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// Updates the interfaces and Wiphy lists of the struct.
+    fn update_interfaces(&mut self) -> Result<(), Nl80211Error> {
+        let wirelessphys: HashMap<u32, phy::WirelessPhy> = self.nt_socket.cmd_get_all_wiphy()?;
+        let mut interfaces: HashMap<InterfaceId, Interface> =
+            self.nt_socket.cmd_get_interfaces()?;
+        resolve_interfaces(&mut interfaces, &wirelessphys, &mut self.rt_socket)?;
         self.interfaces = interfaces;
         self.wirelessphys = wirelessphys;
+        self.restore_monitor_flags();
         Ok(())
     }
 
@@ -81,10 +344,21 @@ impl Nl80211 {
     }
 
     pub fn interface(&self, idx: u32) -> Option<Interface> {
-        self.interfaces.values().find(|f| f.index == Some(idx)).cloned()
+        self.interfaces.get(&InterfaceId::Index(idx)).cloned()
+    }
+
+    /// Every interface of the wiphy, including netdev-less ones, ordered by id.
+    pub fn interfaces_on_phy(&self, phy: u32) -> Vec<&Interface> {
+        let mut interfaces: Vec<&Interface> = self
+            .interfaces
+            .values()
+            .filter(|interface| interface.phy_name == phy)
+            .collect();
+        interfaces.sort_by_key(|interface| interface.id());
+        interfaces
     }
 
-    pub fn get_interfaces(&self) -> &HashMap<u32, Interface> {
+    pub fn get_interfaces(&self) -> &HashMap<InterfaceId, Interface> {
         &self.interfaces
     }
 
@@ -92,7 +366,7 @@ impl Nl80211 {
         &self.wirelessphys
     }
 
-    pub fn get_mut_interfaces(&mut self) -> &mut HashMap<u32, Interface> {
+    pub fn get_mut_interfaces(&mut self) -> &mut HashMap<InterfaceId, Interface> {
         &mut self.interfaces
     }
 
@@ -100,67 +374,261 @@ impl Nl80211 {
         &mut self.wirelessphys
     }
 
-    pub fn set_interface_monitor(&mut self, active: bool, index: u32) -> Result<(), String> {
+    /// Apply a `LinkMonitor` event to the cached interfaces. New wireless links are queried over
+    /// nl80211 since only that side knows the wiphy and iftype.
+    pub fn apply_link_event(&mut self, event: &LinkEvent) -> Result<(), Nl80211Error> {
+        match event {
+            LinkEvent::Created(link) => {
+                if link.wireless {
+                    self.interface_changed(link.index)?;
+                }
+            }
+            LinkEvent::Deleted(link) => self.remove_interface(link.index),
+            LinkEvent::Renamed {
+                index, new_name, ..
+            } => {
+                if let Some(interface) = self.interface_mut(*index) {
+                    // Interface names are kept NUL terminated, as nl80211 reports them
+                    interface.name = new_name.as_ref().map(|name| {
+                        let mut name = name.as_bytes().to_vec();
+                        name.push(0);
+                        name
+                    });
+                }
+            }
+            LinkEvent::OperstateChanged { index, new, .. } => {
+                if let Some(interface) = self.interface_mut(*index) {
+                    interface.state = new.clone();
+                }
+            }
+            LinkEvent::MacChanged { index, new, .. } => {
+                if let Some(interface) = self.interface_mut(*index) {
+                    interface.mac = new.map(|mac| mac.to_vec());
+                }
+            }
+            LinkEvent::CarrierChanged { .. } | LinkEvent::FlagsChanged { .. } => (),
+        }
+        Ok(())
+    }
+
+    fn interface_mut(&mut self, index: u32) -> Option<&mut Interface> {
+        self.interfaces.get_mut(&InterfaceId::Index(index))
+    }
+
+    pub fn set_interface_monitor(&mut self, active: bool, index: u32) -> Result<(), Nl80211Error> {
         self.nt_socket
             .set_type_vec(index, Nl80211Iftype::IftypeMonitor, active)?;
-        self.update_interfaces()?;
+        self.applied_monitor_flags(index, active.then(MonitorFlags::active));
+        self.interface_changed(index)?;
         Ok(())
     }
 
-    pub fn set_interface_station(&mut self, index: u32) -> Result<(), String> {
+    /// Switch the interface to monitor mode with exactly the given monitor flags.
+    pub fn set_interface_monitor_flags(
+        &mut self,
+        index: u32,
+        flags: MonitorFlags,
+    ) -> Result<(), Nl80211Error> {
+        self.nt_socket
+            .set_interface_type(index, Nl80211Iftype::IftypeMonitor, Some(flags))?;
+        self.applied_monitor_flags(index, Some(flags));
+        self.interface_changed(index)?;
+        Ok(())
+    }
+
+    pub fn set_interface_station(&mut self, index: u32) -> Result<(), Nl80211Error> {
         self.nt_socket
             .set_type_vec(index, Nl80211Iftype::IftypeStation, false)?;
-        self.update_interfaces()?;
+        self.interface_changed(index)?;
         Ok(())
     }
 
-    pub fn set_interface_chan(&mut self, index: u32, channel: u32, band: u8) -> Result<(), String> {
-        let band = WiFiBand::from_u8(band)?;
+    /// Create a virtual interface on the wiphy alongside the existing ones, returns its interface index.
+    pub fn create_interface(
+        &mut self,
+        wiphy: u32,
+        name: &str,
+        iftype: Nl80211Iftype,
+        options: &InterfaceOptions,
+    ) -> Result<u32, Nl80211Error> {
+        let index = self
+            .nt_socket
+            .create_interface(wiphy, name, iftype, options)?;
+        if iftype == Nl80211Iftype::IftypeMonitor && !options.monitor_flags.is_empty() {
+            self.applied_monitor_flags(index, Some(options.monitor_flags));
+        }
+        self.interface_changed(index)?;
+        Ok(index)
+    }
+
+    /// Create a monitor interface on the wiphy, e.g. a `mon0` next to `wlan0`.
+    pub fn create_monitor_interface(
+        &mut self,
+        wiphy: u32,
+        name: &str,
+        flags: MonitorFlags,
+    ) -> Result<u32, Nl80211Error> {
+        self.create_interface(
+            wiphy,
+            name,
+            Nl80211Iftype::IftypeMonitor,
+            &InterfaceOptions::monitor(flags),
+        )
+    }
+
+    pub fn delete_interface(&mut self, index: u32) -> Result<(), Nl80211Error> {
+        self.nt_socket.delete_interface(index)?;
+        match self.cache_mode {
+            CacheMode::Full => self.update_interfaces()?,
+            CacheMode::Incremental => self.remove_interface(index),
+        }
+        Ok(())
+    }
+
+    pub fn set_interface_chan(
+        &mut self,
+        index: u32,
+        channel: u32,
+        band: u8,
+    ) -> Result<(), Nl80211Error> {
+        let frequency = channel_frequency(channel, band)?;
+        self.validate_channel_spec(index, &ChannelSpec::no_ht(frequency))?;
         self.nt_socket.set_frequency(
             index,
-                chan_to_frequency(channel, band),
+            frequency,
             Nl80211ChanWidth::ChanWidth20Noht,
             Nl80211ChannelType::ChanNoHt,
         )?;
-        self.update_interfaces()?;
+        self.interface_changed(index)?;
         Ok(())
     }
 
-    pub fn set_powersave_off(&mut self, index: u32) -> Result<(), String> {
-        self.nt_socket.set_powersave_off(
-            index,
-        )?;
-        self.update_interfaces()?;
+    /// Check the channel against the frequency list of the interface's phy, for the interface's
+    /// current type: monitor interfaces may listen on passive only and radar channels.
+    ///
+    /// Interfaces and phys that aren't cached are fetched, channels of a phy without frequency
+    /// list are not checked.
+    pub fn validate_channel_spec(
+        &mut self,
+        index: u32,
+        spec: &ChannelSpec,
+    ) -> Result<(), Nl80211Error> {
+        let interface = match self.interface(index) {
+            Some(interface) => interface,
+            None => self.nt_socket.cmd_get_interface(index)?,
+        };
+        let iftype = interface
+            .current_iftype
+            .unwrap_or(Nl80211Iftype::IftypeUnspecified);
+        match self.wirelessphys.get(&interface.phy_name) {
+            Some(phy) => check_channel_spec(phy, spec, iftype),
+            None => {
+                let phy = self.nt_socket.cmd_get_wiphy(interface.phy_name)?;
+                check_channel_spec(&phy, spec, iftype)
+            }
+        }
+    }
+
+    /// Every usable primary channel and width combination of the wiphy for an interface of the given type.
+    pub fn supported_channel_specs(
+        &self,
+        phy: u32,
+        iftype: Nl80211Iftype,
+    ) -> Option<Vec<ChannelSpec>> {
+        self.wirelessphys
+            .get(&phy)
+            .map(|phy| phy.supported_channel_specs(iftype))
+    }
+
+    /// Whether the wiphy's interface combinations allow another interface of the type next to the
+    /// ones it has, on one of their channels. `None` for an unknown wiphy.
+    ///
+    /// Interfaces that are down are counted too, so the answer errs on the side of no.
+    pub fn can_add_interface(&self, phy: u32, iftype: Nl80211Iftype) -> Option<bool> {
+        let interfaces: Vec<Interface> = self.interfaces_on_phy(phy).into_iter().cloned().collect();
+        let request = ConcurrencyRequest::from_interfaces(&interfaces).with_interface(iftype);
+        self.wirelessphys.get(&phy).map(|phy| phy.can_run(&request))
+    }
+
+    /// Set the interface channel including its width, e.g. an 80 or 160 MHz channel for wide captures.
+    pub fn set_interface_channel_spec(
+        &mut self,
+        index: u32,
+        spec: &ChannelSpec,
+    ) -> Result<(), Nl80211Error> {
+        self.validate_channel_spec(index, spec)?;
+        self.nt_socket.set_channel_spec(index, spec)?;
+        self.interface_changed(index)?;
+        Ok(())
+    }
+
+    pub fn set_powersave_off(&mut self, index: u32) -> Result<(), Nl80211Error> {
+        self.nt_socket.set_powersave_off(index)?;
+        self.interface_changed(index)?;
+        Ok(())
+    }
+
+    /// Survey the interface's channels and rank them by utilization, least busy first.
+    pub fn get_channel_utilization(
+        &mut self,
+        index: u32,
+    ) -> Result<Vec<ChannelUtilization>, Nl80211Error> {
+        let bands = self
+            .interface(index)
+            .and_then(|interface| interface.phy)
+            .and_then(|phy| phy.frequency_list)
+            .ok_or(Nl80211Error::NotFound("Interface".to_string()))?;
+        let surveys = self.nt_socket.get_survey(index)?;
+        Ok(rank_channels(&surveys, &bands))
+    }
+
+    /// Regulatory domain for the wiphy, or the global one if `wiphy` is None.
+    pub fn get_regdomain(&mut self, wiphy: Option<u32>) -> Result<RegDomain, Nl80211Error> {
+        self.nt_socket.get_regdomain(wiphy)
+    }
+
+    /// Global regulatory domain followed by all self-managed wiphy domains.
+    pub fn get_regdomains(&mut self) -> Result<Vec<RegDomain>, Nl80211Error> {
+        self.nt_socket.dump_regdomains()
+    }
+
+    /// Request a regulatory domain change, e.g. `"US"`. The kernel applies it asynchronously.
+    pub fn set_regdomain(&mut self, alpha2: &str) -> Result<(), Nl80211Error> {
+        self.nt_socket.request_regdomain(alpha2)?;
+        // The change is applied asynchronously, incremental caches pick it up from the regulatory event.
+        if self.cache_mode == CacheMode::Full {
+            self.update_interfaces()?;
+        }
         Ok(())
     }
 
     // rtnetlink commands- all use interface index.
 
-    pub fn set_interface_up(&mut self, index: u32) -> Result<(), String> {
+    pub fn set_interface_up(&mut self, index: u32) -> Result<(), Nl80211Error> {
         self.rt_socket.set_interface_up(index)?;
-        self.update_interfaces()?;
+        self.interface_changed(index)?;
         Ok(())
     }
 
-    pub fn set_interface_down(&mut self, index: u32) -> Result<(), String> {
+    pub fn set_interface_down(&mut self, index: u32) -> Result<(), Nl80211Error> {
         self.rt_socket.set_interface_down(index)?;
-        self.update_interfaces()?;
+        self.interface_changed(index)?;
         Ok(())
     }
 
-    pub fn set_interface_mac(&mut self, index: u32, mac: &[u8; 6]) -> Result<(), String> {
+    pub fn set_interface_mac(&mut self, index: u32, mac: &[u8; 6]) -> Result<(), Nl80211Error> {
         self.rt_socket.set_interface_mac(index, mac)?;
-        self.update_interfaces()?;
+        self.interface_changed(index)?;
         Ok(())
     }
 
-    pub fn set_interface_mac_random(&mut self, index: u32) -> Result<(), String> {
+    pub fn set_interface_mac_random(&mut self, index: u32) -> Result<(), Nl80211Error> {
         self.rt_socket.set_interface_mac_random(index)?;
-        self.update_interfaces()?;
+        self.interface_changed(index)?;
         Ok(())
     }
 
-    fn get_interface_state(&mut self, index: u32) -> Result<Operstate, String> {
+    fn get_interface_state(&mut self, index: u32) -> Result<Operstate, Nl80211Error> {
         self.rt_socket.get_interface_status(index)
     }
 }
@@ -169,127 +637,191 @@ impl Nl80211 {
 ///
 /// The following functions are designed to be used more independently than the Nl80211 struct, making them easier to use in multi-threaded code.
 /// They can be fired off in a "one-shot" style.
-fn get_interfaces_info() -> Result<HashMap<u32, Interface>, String> {
+fn get_interfaces_info() -> Result<HashMap<InterfaceId, Interface>, Nl80211Error> {
     let mut nt_socket: NtSocket = NtSocket::connect()?;
     let mut rt_socket: RtSocket = RtSocket::connect()?;
 
     let wiphys: HashMap<u32, phy::WirelessPhy> = nt_socket.cmd_get_all_wiphy()?;
-    let mut interfaces: HashMap<u32, Interface> = nt_socket.cmd_get_interfaces()?;
-
-    for (phy, interface) in &mut interfaces {
-        if wiphys.contains_key(phy) {
-            if let Some(index) = interface.index {
-                interface.phy = wiphys.get(&phy).cloned();
-                interface.state = Some(rt_socket.get_interface_status(index)?);
-            }
-        }
-    }
+    let mut interfaces: HashMap<InterfaceId, Interface> = nt_socket.cmd_get_interfaces()?;
+    resolve_interfaces(&mut interfaces, &wiphys, &mut rt_socket)?;
     Ok(interfaces)
 }
 
-pub fn get_interface_info_idx(interface_index: u32) -> Result<Interface, String> {
-    let mut nt_socket: NtSocket = NtSocket::connect()?;
-    let mut rt_socket: RtSocket = RtSocket::connect()?;
+pub fn get_interface_info_idx(interface_index: u32) -> Result<Interface, Nl80211Error> {
+    get_interfaces_info()?
+        .remove(&InterfaceId::Index(interface_index))
+        .ok_or(Nl80211Error::NotFound("Interface".to_string()))
+}
 
-    let wiphys: HashMap<u32, phy::WirelessPhy> = nt_socket.cmd_get_all_wiphy()?;
-    let mut interfaces: HashMap<u32, Interface> = nt_socket.cmd_get_interfaces()?;
+pub fn get_interface_info_name(interface_name: &String) -> Result<Interface, Nl80211Error> {
+    let interface = get_interfaces_info()?
+        .into_values()
+        .find(|interface| {
+            interface.index.is_some() && &interface.name_as_string() == interface_name
+        })
+        .ok_or(Nl80211Error::NotFound("Interface".to_string()))?;
+    if interface.phy.is_none() {
+        return Err(Nl80211Error::NotFound("Phy".to_string()));
+    }
+    Ok(interface)
+}
 
-    for (phy, interface) in &mut interfaces {
-        if let Some(index) = interface.index {
-            if wiphys.contains_key(phy) {
-                interface.phy = wiphys.get(phy).cloned();
+/// Attach the wiphy to every interface and the operstate to those with a netdev.
+fn resolve_interfaces(
+    interfaces: &mut HashMap<InterfaceId, Interface>,
+    wiphys: &HashMap<u32, WirelessPhy>,
+    rt_socket: &mut RtSocket,
+) -> Result<(), Nl80211Error> {
+    for interface in interfaces.values_mut() {
+        if let Some(phy) = wiphys.get(&interface.phy_name) {
+            interface.phy = Some(phy.clone());
+            if let Some(index) = interface.index {
                 interface.state = Some(rt_socket.get_interface_status(index)?);
             }
-            if index == interface_index {
-                return Ok(interface.clone());
-            }
         }
     }
-    Err("Interface Not Found".to_string())
+    Ok(())
 }
 
-pub fn get_interface_info_name(interface_name: &String) -> Result<Interface, String> {
-    let mut nt_socket: NtSocket = NtSocket::connect()?;
-    let mut rt_socket: RtSocket = RtSocket::connect()?;
+/// Check the channel against the phy's frequency list, phys without one are not checked.
+fn check_channel_spec(
+    phy: &WirelessPhy,
+    spec: &ChannelSpec,
+    iftype: Nl80211Iftype,
+) -> Result<(), Nl80211Error> {
+    if phy.frequency_list.is_some() {
+        phy.validate_channel_spec(spec, iftype)?;
+    }
+    Ok(())
+}
 
-    let wiphys: HashMap<u32, phy::WirelessPhy> = nt_socket.cmd_get_all_wiphy()?;
-    let mut interfaces: HashMap<u32, Interface> = nt_socket.cmd_get_interfaces()?;
+/// Fetch the interface and its phy to check the channel for the interface's current type.
+fn validate_interface_channel(
+    nt_socket: &mut NtSocket,
+    interface_index: u32,
+    spec: &ChannelSpec,
+) -> Result<(), Nl80211Error> {
+    let interface = nt_socket.cmd_get_interface(interface_index)?;
+    let phy = nt_socket.cmd_get_wiphy(interface.phy_name)?;
+    let iftype = interface
+        .current_iftype
+        .unwrap_or(Nl80211Iftype::IftypeUnspecified);
+    check_channel_spec(&phy, spec, iftype)
+}
 
-    for (phy, interface) in &mut interfaces {
-        if let Some(index) = interface.index {
-            if wiphys.contains_key(phy) {
-                interface.phy = wiphys.get(phy).cloned();
-                interface.state = Some(rt_socket.get_interface_status(index)?);
-            } else {
-                return Err("Phy does not exist...".to_string());
-            }
-            if &interface.name_as_string() == interface_name {
-                return Ok(interface.clone());
-            }
-        }
+/// The frequency of a channel number in the band, rejecting channels the band doesn't have.
+fn channel_frequency(channel: u32, band: u8) -> Result<u32, Nl80211Error> {
+    let band = WiFiBand::from_u8(band)?;
+    match chan_to_frequency(channel, band.clone()) {
+        0 => Err(Nl80211Error::InvalidArgument(format!(
+            "Invalid channel {} for band {:?}",
+            channel, band
+        ))),
+        frequency => Ok(frequency),
     }
-    Err("Interface Not Found".to_string())
 }
 
-pub fn set_interface_monitor(interface_index: u32, active: bool) -> Result<(), String> {
+pub fn set_interface_monitor(interface_index: u32, active: bool) -> Result<(), Nl80211Error> {
     let mut nt_socket = NtSocket::connect()?;
     nt_socket.set_type_vec(interface_index, Nl80211Iftype::IftypeMonitor, active)?;
     Ok(())
 }
 
-pub fn set_interface_station(interface_index: u32) -> Result<(), String> {
+pub fn set_interface_monitor_flags(
+    interface_index: u32,
+    flags: MonitorFlags,
+) -> Result<(), Nl80211Error> {
+    let mut nt_socket = NtSocket::connect()?;
+    nt_socket.set_interface_type(interface_index, Nl80211Iftype::IftypeMonitor, Some(flags))?;
+    Ok(())
+}
+
+pub fn set_interface_station(interface_index: u32) -> Result<(), Nl80211Error> {
     let mut nt_socket = NtSocket::connect()?;
     nt_socket.set_type_vec(interface_index, Nl80211Iftype::IftypeStation, false)?;
     Ok(())
 }
 
-pub fn set_interface_chan(interface_index: u32, channel: u32, band: u8) -> Result<(), String> {
+pub fn create_interface(
+    wiphy: u32,
+    name: &str,
+    iftype: Nl80211Iftype,
+    options: &InterfaceOptions,
+) -> Result<u32, Nl80211Error> {
     let mut nt_socket = NtSocket::connect()?;
-    let band = WiFiBand::from_u8(band)?;
+    nt_socket.create_interface(wiphy, name, iftype, options)
+}
+
+pub fn delete_interface(interface_index: u32) -> Result<(), Nl80211Error> {
+    let mut nt_socket = NtSocket::connect()?;
+    nt_socket.delete_interface(interface_index)?;
+    Ok(())
+}
+
+pub fn set_interface_chan(
+    interface_index: u32,
+    channel: u32,
+    band: u8,
+) -> Result<(), Nl80211Error> {
+    let frequency = channel_frequency(channel, band)?;
+    let mut nt_socket = NtSocket::connect()?;
+    validate_interface_channel(
+        &mut nt_socket,
+        interface_index,
+        &ChannelSpec::no_ht(frequency),
+    )?;
     nt_socket.set_frequency(
         interface_index,
-        chan_to_frequency(channel, band),
+        frequency,
         Nl80211ChanWidth::ChanWidth20Noht,
         Nl80211ChannelType::ChanNoHt,
     )?;
     Ok(())
 }
 
-pub fn set_powersave_off(index: u32) -> Result<(), String> {
+pub fn set_interface_channel_spec(
+    interface_index: u32,
+    spec: &ChannelSpec,
+) -> Result<(), Nl80211Error> {
     let mut nt_socket = NtSocket::connect()?;
-    nt_socket.set_powersave_off(
-        index,
-    )?;
+    validate_interface_channel(&mut nt_socket, interface_index, spec)?;
+    nt_socket.set_channel_spec(interface_index, spec)?;
+    Ok(())
+}
+
+pub fn set_powersave_off(index: u32) -> Result<(), Nl80211Error> {
+    let mut nt_socket = NtSocket::connect()?;
+    nt_socket.set_powersave_off(index)?;
     Ok(())
 }
 
 // rtnetlink commands- all use interface index.
 
-pub fn set_interface_up(interface_index: u32) -> Result<(), String> {
+pub fn set_interface_up(interface_index: u32) -> Result<(), Nl80211Error> {
     let mut rt_socket = RtSocket::connect()?;
     rt_socket.set_interface_up(interface_index)?;
     Ok(())
 }
 
-pub fn set_interface_down(interface_index: u32) -> Result<(), String> {
+pub fn set_interface_down(interface_index: u32) -> Result<(), Nl80211Error> {
     let mut rt_socket = RtSocket::connect()?;
     rt_socket.set_interface_down(interface_index)?;
     Ok(())
 }
 
-pub fn set_interface_mac(interface_index: u32, mac: &[u8; 6]) -> Result<(), String> {
+pub fn set_interface_mac(interface_index: u32, mac: &[u8; 6]) -> Result<(), Nl80211Error> {
     let mut rt_socket = RtSocket::connect()?;
     rt_socket.set_interface_mac(interface_index, mac)?;
     Ok(())
 }
 
-pub fn set_interface_mac_random(interface_index: u32) -> Result<(), String> {
+pub fn set_interface_mac_random(interface_index: u32) -> Result<(), Nl80211Error> {
     let mut rt_socket = RtSocket::connect()?;
     rt_socket.set_interface_mac_random(interface_index)?;
     Ok(())
 }
 // This should only be called when "updating" an interface, so we won't update it after doing this.
-fn get_interface_state(interface_index: u32) -> Result<Operstate, String> {
-    let mut rt_socket = RtSocket::connect().map_err(|e| e.to_string())?;
+fn get_interface_state(interface_index: u32) -> Result<Operstate, Nl80211Error> {
+    let mut rt_socket = RtSocket::connect()?;
     rt_socket.get_interface_status(interface_index)
 }
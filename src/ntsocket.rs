@@ -1,16 +1,27 @@
 use crate::attr::Nl80211Attr;
 use crate::attr::*;
+use crate::capture::PcapWriter;
 use crate::channels::*;
 use crate::cmd::Nl80211Cmd;
-use crate::interface::Interface;
+use crate::decode::{
+    decode_bss, decode_interface, decode_regdomain, decode_station, decode_string, decode_survey,
+    decode_wiphy,
+};
+use crate::error::Nl80211Error;
+use crate::events::McastGroup;
+use crate::interface::{Interface, InterfaceId, InterfaceOptions, MonitorFlags};
 use crate::phy::Frequency;
-use crate::phy::WirelessPhy;
-use crate::util::decode_iftypes;
+use crate::phy::{read_driver, WirelessPhy};
+use crate::reg::RegDomain;
+use crate::request::Nl80211Request;
+use crate::scan::{Bss, ScanRequest};
+use crate::station::StationInfo;
+use crate::survey::SurveyInfo;
+use crate::transaction::{payloads, Transactor};
+use crate::transport::{NetlinkTransport, Transport};
 use crate::{NL_80211_GENL_NAME, NL_80211_GENL_VERSION};
-use neli::attr::{AttrHandle, Attribute};
-use neli::consts::{nl::NlmF, nl::NlmFFlags, nl::Nlmsg, socket::NlFamily};
-use neli::err::NlError;
-use neli::genl::AttrType;
+use neli::consts::genl::{CtrlAttr, CtrlAttrMcastGrp, CtrlCmd};
+use neli::consts::{nl::GenlId, nl::NlmF, nl::NlmFFlags, nl::Nlmsg, socket::NlFamily};
 use neli::genl::{Genlmsghdr, Nlattr};
 use neli::nl::{NlPayload, Nlmsghdr};
 use neli::socket::NlSocketHandle;
@@ -18,451 +29,195 @@ use neli::types::{Buffer, GenlBuffer};
 
 use std::collections::HashMap;
 use std::fmt;
-use std::fs;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::time::{Duration, Instant};
 
 /// A generic netlink socket to send commands and receive messages
-pub struct NtSocket {
-    pub(crate) sock: NlSocketHandle,
+pub struct NtSocket<T = NetlinkTransport> {
+    pub(crate) transport: T,
     pub(crate) family_id: u16,
+    pub(crate) mcast_groups: HashMap<String, u32>,
+    pub(crate) transactor: Transactor,
 }
 
-impl fmt::Debug for NtSocket {
+impl<T: fmt::Debug> fmt::Debug for NtSocket<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("NtSocket")
-            .field("sock", &format_args!("<NlSocketHandle>"))
+            .field("transport", &self.transport)
             .field("family_id", &self.family_id)
+            .field("mcast_groups", &self.mcast_groups)
+            .field("transactor", &self.transactor)
             .finish()
     }
 }
 
 impl NtSocket {
     /// Create a new nl80211 socket with netlink
-    pub fn connect() -> Result<Self, String> {
-        let mut sock =
-            NlSocketHandle::connect(NlFamily::Generic, None, &[]).map_err(|e| e.to_string())?;
-        sock.nonblock().map_err(|e| e.to_string())?;
-        let family_id = sock
-            .resolve_genl_family(NL_80211_GENL_NAME)
-            .map_err(|e| e.to_string())?;
-        Ok(Self { sock, family_id })
-    }
-
-    pub fn cmd_get_interfaces(&mut self) -> Result<HashMap<u32, Interface>, NlError<neli::consts::nl::Nlmsg, Genlmsghdr<Nl80211Cmd, Nl80211Attr>>> {
-        let msghdr = Genlmsghdr::<Nl80211Cmd, Nl80211Attr>::new(
+    pub fn connect() -> Result<Self, Nl80211Error> {
+        let transport = NetlinkTransport::connect(NlFamily::Generic)?;
+        enable_ext_ack(&transport.sock);
+        NtSocket::from_transport(transport)
+    }
+}
+
+impl<T: Transport> NtSocket<T> {
+    /// Send the requests over another transport, e.g. a `MockTransport` or a `Recorder`. The
+    /// nl80211 family id is resolved through it too.
+    pub fn from_transport(mut transport: T) -> Result<Self, Nl80211Error> {
+        let mut transactor = Transactor::new(transport.pid(), libc::NETLINK_GENERIC as u16);
+        let (family_id, mcast_groups) = resolve_family(&mut transactor, &mut transport)?;
+        Ok(NtSocket {
+            transport,
+            family_id,
+            mcast_groups,
+            transactor,
+        })
+    }
+
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    pub fn transport_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    /// How long a request waits for its reply, None waits forever.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.transactor.timeout()
+    }
+
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.transactor.set_timeout(timeout);
+    }
+
+    pub fn capture(&self) -> Option<&PcapWriter> {
+        self.transactor.capture()
+    }
+
+    /// Write every request and reply to a pcap file, None stops capturing. The nl80211 family is
+    /// resolved again so the capture holds the reply Wireshark needs to dissect the nl80211 messages.
+    pub fn set_capture(&mut self, capture: Option<PcapWriter>) -> Result<(), Nl80211Error> {
+        let resolve = capture.is_some();
+        self.transactor.set_capture(capture);
+        if resolve {
+            (self.family_id, self.mcast_groups) =
+                resolve_family(&mut self.transactor, &mut self.transport)?;
+        }
+        Ok(())
+    }
+
+    /// Send a command that only gets an ack back.
+    pub fn execute_ack(&mut self, request: Nl80211Request) -> Result<(), Nl80211Error> {
+        self.execute(request, &[NlmF::Request, NlmF::Ack])?;
+        Ok(())
+    }
+
+    /// Send a command answered by a single message, None if the kernel only acked it.
+    pub fn execute_get(
+        &mut self,
+        request: Nl80211Request,
+    ) -> Result<Option<Genlmsghdr<Nl80211Cmd, Nl80211Attr>>, Nl80211Error> {
+        Ok(self
+            .execute(request, &[NlmF::Request, NlmF::Ack])?
+            .into_iter()
+            .next())
+    }
+
+    /// Send a dump request and collect every message of the (multipart) reply.
+    pub fn execute_dump(
+        &mut self,
+        request: Nl80211Request,
+    ) -> Result<Vec<Genlmsghdr<Nl80211Cmd, Nl80211Attr>>, Nl80211Error> {
+        self.execute(request, &[NlmF::Request, NlmF::Dump])
+    }
+
+    fn execute(
+        &mut self,
+        request: Nl80211Request,
+        flags: &[NlmF],
+    ) -> Result<Vec<Genlmsghdr<Nl80211Cmd, Nl80211Attr>>, Nl80211Error> {
+        let name = request.name;
+        let replies = self.transactor.transact(
+            name,
+            &mut self.transport,
+            genl_request(self.family_id, request, flags)?,
+            Nl80211Error::kernel::<Nlmsg>,
+        )?;
+        Ok(payloads(replies))
+    }
+
+    /// Dump every interface, keyed by ifindex or by wdev id for interfaces without a netdev.
+    pub fn cmd_get_interfaces(&mut self) -> Result<HashMap<InterfaceId, Interface>, Nl80211Error> {
+        let replies = self.execute_dump(Nl80211Request::new(
+            "CMD_GET_INTERFACE",
             Nl80211Cmd::CmdGetInterface,
-            NL_80211_GENL_VERSION,
-            GenlBuffer::new(),
-        );
+        ))?;
 
-        let nlhdr: Nlmsghdr<u16, Genlmsghdr<Nl80211Cmd, Nl80211Attr>> = {
-            let len = None;
-            let nl_type = self.family_id;
-            let flags = NlmFFlags::new(&[NlmF::Request, NlmF::Dump]);
-            let seq = None;
-            let pid = None;
-            let payload = NlPayload::Payload(msghdr);
-            Nlmsghdr::new(len, nl_type, flags, seq, pid, payload)
-        };
+        let mut retval: HashMap<InterfaceId, Interface> = HashMap::new();
 
-        self.sock
-            .send(nlhdr)?;
-
-        let iter = self
-            .sock
-            .iter::<Nlmsg, Genlmsghdr<Nl80211Cmd, Nl80211Attr>>(false);
-
-        let mut retval: HashMap<u32, Interface> = HashMap::new();
-
-        for res in iter {
-            match res {
-                Ok(response) => {
-                    match response.nl_type {
-                        Nlmsg::Noop => (),
-                        Nlmsg::Error => return Err(NlError::Msg("Error".to_owned())),
-                        Nlmsg::Done => break,
-                        _ => {
-                            if let Some(p) = response.nl_payload.get_payload() {
-                                if p.cmd != Nl80211Cmd::CmdNewInterface {
-                                    continue;
-                                }
-                                let handle = p.get_attr_handle();
-        
-                                let wiphy: u32 = handle
-                                    .get_attr_payload_as(Nl80211Attr::AttrWiphy)?; // The proper way
-        
-                                // Get iftype
-                                let iftype_payload: u32 = handle
-                                    .get_attr_payload_as(Nl80211Attr::AttrIftype)?;
-        
-                                let lsb: u8 = (iftype_payload & 0xFF) as u8;
-        
-                                let iftype =
-                                    Nl80211Iftype::from_u8(lsb).unwrap_or(Nl80211Iftype::IftypeUnspecified);
-                                
-                                let interface = retval.entry(wiphy).or_insert(Interface::new(wiphy));
-                                
-                                interface.current_iftype = Some(iftype);
-                            
-                                // Iterate other attributes
-                                for attr in handle.iter() {
-                                    match attr.nla_type.nla_type {
-                                        // IfIndex (eg: wlan0)
-                                        Nl80211Attr::AttrIfindex => {
-                                            interface.index =
-                                                Some(attr.get_payload_as()?);
-                                        }
-                                        // IFNAME (eg: wlan0)
-                                        Nl80211Attr::AttrIfname => {
-                                            interface.name = Some(
-                                                attr.get_payload_as_with_len()?,
-                                            );
-                                        }
-                                        // Mac Address of the interface
-                                        Nl80211Attr::AttrMac => {
-                                            let mut mac = Vec::new();
-                                            let vecmac: Vec<u8> = attr
-                                                .get_payload_as_with_len()
-                                                ?;
-                                            for byte in vecmac {
-                                                mac.push(byte);
-                                            }
-        
-                                            interface.mac = Some(mac);
-                                        }
-                                        // The SSID the interface is associated with
-                                        Nl80211Attr::AttrSsid => {
-                                            interface.ssid = Some(
-                                                attr.get_payload_as_with_len()?,
-                                            );
-                                        }
-                                        // The frequency the wireless interface is using
-                                        Nl80211Attr::AttrWiphyFreq => {
-                                            interface.frequency.frequency =
-                                                Some(attr.get_payload_as()?);
-                                            interface.frequency.channel = Some(
-                                                chan_from_frequency(interface.frequency.frequency.unwrap()),
-                                            );
-                                        }
-                                        // Channel Type (Width)
-                                        Nl80211Attr::AttrChannelWidth => {
-                                            interface.frequency.width =
-                                                Some(attr.get_payload_as()?);
-                                        }
-                                        // Transmission Power Level
-                                        Nl80211Attr::AttrWiphyTxPowerLevel => {
-                                            interface.frequency.pwr =
-                                                Some(attr.get_payload_as()?);
-                                        }
-                                        // Wireless Device
-                                        Nl80211Attr::AttrWdev => {
-                                            interface.device =
-                                                Some(attr.get_payload_as()?)
-                                        }
-                                        _ => (),
-                                    }
-                                }        
-                            }
-                        }
-                    }
-                },
-                Err(e) => {
-                    return Err(e);
-                }
+        for p in replies {
+            if p.cmd != Nl80211Cmd::CmdNewInterface {
+                continue;
+            }
+            let interface = decode_interface(&p)?;
+            if let Some(id) = interface.id() {
+                retval.insert(id, interface);
             }
         }
         Ok(retval)
     }
 
-    pub fn cmd_get_wiphy(&mut self, phy: u32) -> Result<WirelessPhy, String> {
-        let msghdr = Genlmsghdr::<Nl80211Cmd, Nl80211Attr>::new(
-            Nl80211Cmd::CmdGetWiphy,
-            NL_80211_GENL_VERSION,
-            {
-                let mut attrs = GenlBuffer::new();
-                attrs.push(Nlattr::new(false, false, Nl80211Attr::AttrWiphy, phy).unwrap());
-                attrs.push(Nlattr {
-                    nla_len: 4,
-                    nla_type: AttrType {
-                        nla_nested: false,
-                        nla_network_order: false,
-                        nla_type: Nl80211Attr::AttrSplitWiphyDump,
-                    },
-                    nla_payload: Buffer::new(),
-                });
-                attrs
-            },
-        );
+    /// Query a single interface instead of dumping all of them.
+    pub fn cmd_get_interface(&mut self, interface_index: u32) -> Result<Interface, Nl80211Error> {
+        let reply = self.execute_get(
+            Nl80211Request::new("CMD_GET_INTERFACE", Nl80211Cmd::CmdGetInterface)
+                .attr(Nl80211Attr::AttrIfindex, interface_index),
+        )?;
+        match reply {
+            Some(p) if p.cmd == Nl80211Cmd::CmdNewInterface => decode_interface(&p),
+            _ => Err(Nl80211Error::NotFound("Interface".to_string())),
+        }
+    }
 
-        let nlhdr: Nlmsghdr<u16, Genlmsghdr<Nl80211Cmd, Nl80211Attr>> = {
-            let len = None;
-            let nl_type = self.family_id;
-            let flags = NlmFFlags::new(&[NlmF::Request, NlmF::Ack, NlmF::Root, NlmF::Match]);
-            let seq = None;
-            let pid = Some(42069);
-            let payload = NlPayload::Payload(msghdr);
-            Nlmsghdr::new(len, nl_type, flags, seq, pid, payload)
-        };
+    pub fn cmd_get_wiphy(&mut self, phy: u32) -> Result<WirelessPhy, Nl80211Error> {
+        let replies = self.execute_dump(
+            Nl80211Request::new("CMD_GET_WIPHY", Nl80211Cmd::CmdGetWiphy)
+                .attr(Nl80211Attr::AttrWiphy, phy)
+                .flag(Nl80211Attr::AttrSplitWiphyDump),
+        )?;
 
-        self.sock.send(nlhdr).map_err(|err| err.to_string())?;
-
-        let iter = self
-            .sock
-            .iter::<Nlmsg, Genlmsghdr<Nl80211Cmd, Nl80211Attr>>(false);
-
-        let mut phy = WirelessPhy::default();
-
-        for response in iter {
-            let response = response.unwrap();
-            match response.nl_type {
-                Nlmsg::Noop => (),
-                Nlmsg::Error => panic!("Error with netlink during cmd_get_split_wiphy()"),
-                Nlmsg::Done => break,
-                _ => {
-                    if let Some(p) = response.nl_payload.get_payload() {
-                        if p.cmd != Nl80211Cmd::CmdNewWiphy {
-                            continue;
-                        }
-
-
-                        let handle = p.get_attr_handle();
-                        let wiphy: u32 = handle
-                            .get_attribute(Nl80211Attr::AttrWiphy)
-                            .unwrap()
-                            .get_payload_as()
-                            .unwrap();
-                        phy.phy = wiphy;
-
-                        let wiphy_name: String = handle
-                            .get_attribute(Nl80211Attr::AttrWiphyName)
-                            .unwrap()
-                            .get_payload_as_with_len()
-                            .map_err(|err| err.to_string())?;
-                        phy.phy_name = Some(wiphy_name.clone());
-
-                        let driver_path =
-                            format!("/sys/class/ieee80211/{}/device/driver", wiphy_name.clone());
-
-                        if let Ok(link_path) = fs::read_link(&driver_path) {
-                            if let Some(driver_name) = link_path.file_name() {
-                                if let Some(driver_name_str) = driver_name.to_str() {
-                                    phy.driver = Some(driver_name_str.to_string());
-                                }
-                            }
-                        }
-
-
-                        for attr in handle.get_attrs() {
-                            match attr.nla_type.nla_type {
-                                Nl80211Attr::AttrWiphyFreq => {
-                                    phy.frequency.frequency =
-                                        Some(attr.get_payload_as().map_err(|err| err.to_string())?);
-                                    phy.frequency.channel = Some(
-                                        chan_from_frequency(phy.frequency.frequency.unwrap()),
-                                    );
-                                }
-                                // Channel Type (Width)
-                                Nl80211Attr::AttrChannelWidth => {
-                                    phy.frequency.width =
-                                        Some(attr.get_payload_as().map_err(|err| err.to_string())?);
-
-                                }
-                                // Transmission Power Level
-                                Nl80211Attr::AttrWiphyTxPowerLevel => {
-                                    phy.frequency.pwr =
-                                        Some(attr.get_payload_as().map_err(|err| err.to_string())?);
-
-                                }
-                                Nl80211Attr::AttrSupportedIftypes => {
-                                    let payload = attr
-                                        .get_payload_as_with_len()
-                                        .map_err(|err| err.to_string())?;
-                                    phy.iftypes = Some(decode_iftypes(payload));
-                                    phy.has_netlink = Some(true);
-                                }
-                                Nl80211Attr::AttrWiphyBands => {
-                                    let handle: AttrHandle<
-                                        '_,
-                                        GenlBuffer<Nl80211Bandc, Buffer>,
-                                        Nlattr<Nl80211Bandc, Buffer>,
-                                    > = attr.get_attr_handle().unwrap();
-                                    let bands = handle.get_attrs();
-                                    let mut supported_bands: Vec<BandList> = Vec::new();
-
-                                    for band in bands {
-                                        let mut bandlist = BandList::default();
-
-                                        match band.nla_type.nla_type {
-                                            Nl80211Bandc::Band2ghz => {
-                                                bandlist.band = WiFiBand::Band2GHz
-                                            }
-                                            Nl80211Bandc::Band5ghz => {
-                                                bandlist.band = WiFiBand::Band5GHz
-                                            }
-                                            Nl80211Bandc::Band60ghz => {
-                                                bandlist.band = WiFiBand::Band60GHz
-                                            }
-                                            Nl80211Bandc::Band6ghz => {
-                                                bandlist.band = WiFiBand::Band6GHz
-                                            }
-                                            Nl80211Bandc::BandS1ghz => {}
-                                            Nl80211Bandc::BandLC => {}
-                                            Nl80211Bandc::UnrecognizedConst(_) => {}
-                                        }
-
-
-                                        let bandhandle = band.get_attr_handle().unwrap();
-
-                                        
-                                        for bandattr in bandhandle.get_attrs() {
-                                            match bandattr.nla_type.nla_type {
-                                                Nl80211BandAttr::BandAttrFreqs => {
-                                                    let freqhandle: AttrHandle<
-                                                        '_,
-                                                        GenlBuffer<u16, Buffer>,
-                                                        Nlattr<u16, Buffer>,
-                                                    > = bandattr.get_attr_handle().unwrap();
-                                                    let mut channels: Vec<ChannelData> =
-                                                        [].to_vec();
-                                                    for freq in freqhandle.get_attrs() {
-                                                        let freqdata_handle: AttrHandle<
-                                                            '_,
-                                                            GenlBuffer<
-                                                                Nl80211FrequencyAttr,
-                                                                Buffer,
-                                                            >,
-                                                            Nlattr<Nl80211FrequencyAttr, Buffer>,
-                                                        > = freq.get_attr_handle().unwrap();
-                                                        let mut channel: ChannelData =
-                                                            ChannelData::default();
-                                                        for freqattr in freqdata_handle.get_attrs()
-                                                        {
-                                                            match freqattr.nla_type.nla_type {
-                                                                    Nl80211FrequencyAttr::FrequencyAttrFreq => {
-                                                                        let frequency: u32 = freqattr.get_payload_as().map_err(|err| err.to_string())?;
-                                                                        channel.frequency = frequency;
-                                                                        channel.channel = chan_from_frequency(frequency)
-                                                                        
-                                                                    }
-                                                                    Nl80211FrequencyAttr::FrequencyAttrDisabled => {
-                                                                        channel.status = FrequencyStatus::Disabled;
-                                                                    },
-                                                                    Nl80211FrequencyAttr::FrequencyAttrMaxTxPower => {
-                                                                        channel.pwr = freqattr.get_payload_as().map_err(|err| err.to_string())?;
-                                                                    },
-                                                                    _ => {}
-                                                                }
-                                                        }
-                                                        channels.push(channel);
-                                                    }
-                                                    bandlist.channels = channels;
-                                                }
-                                                Nl80211BandAttr::BandAttrInvalid => {}
-                                                Nl80211BandAttr::BandAttrRates => {}
-                                                Nl80211BandAttr::BandAttrHtMcsSet => {}
-                                                Nl80211BandAttr::BandAttrHtCapa => {}
-                                                Nl80211BandAttr::BandAttrHtAmpduFactor => {}
-                                                Nl80211BandAttr::BandAttrHtAmpduDensity => {}
-                                                Nl80211BandAttr::BandAttrVhtMcsSet => {}
-                                                Nl80211BandAttr::BandAttrVhtCapa => {}
-                                                Nl80211BandAttr::UnrecognizedConst(_) => {}
-                                            }
-                                        }
-                                        supported_bands.push(bandlist);
-                                    }
-                                    if !supported_bands.is_empty() {
-                                        let frequency_list = phy.frequency_list.get_or_insert_with(Vec::new);
-                                    
-                                        for new_band in supported_bands {
-                                            if let Some(existing_band) = frequency_list.iter_mut().find(|b| b.band == new_band.band) {
-                                                existing_band.channels.extend(new_band.channels);
-                                            } else {
-                                                frequency_list.push(new_band);
-                                            }
-                                        }
-                                    }
-                                }
-                                Nl80211Attr::AttrFeatureFlags => {
-                                    const NL80211_FEATURE_ACTIVE_MONITOR: u32 = 1 << 17;
-                                    let feature_flags: u32 =
-                                        attr.get_payload_as().map_err(|err| err.to_string())?;
-                                    if feature_flags & NL80211_FEATURE_ACTIVE_MONITOR != 0 {
-                                        phy.active_monitor = Some(true);
-                                    } else {
-                                        phy.active_monitor = Some(false);
-                                    }
-                                }
-                                Nl80211Attr::AttrIftype => {
-                                    let payload =
-                                        attr.get_payload_as().map_err(|err| err.to_string())?;
-                                    //println!("AttrIftype: {:#?}", payload);
-                                    phy.current_iftype = Some(payload);
-                                }
-                                _ => {} // TODO implement other attributes
-                            }
-                        }
-                    }
-                }
-            }
-        }
+        let mut phy = decode_wiphy(&replies)?;
+        phy.driver = phy.phy_name.as_deref().and_then(read_driver);
         Ok(phy)
     }
 
     /// To protect against too much data (returning empty channels) this will return the phy's and then call each one independently
-    pub fn cmd_get_all_wiphy(&mut self) -> Result<HashMap<u32, WirelessPhy>, String> {
-        let msghdr = Genlmsghdr::<Nl80211Cmd, Nl80211Attr>::new(
+    pub fn cmd_get_all_wiphy(&mut self) -> Result<HashMap<u32, WirelessPhy>, Nl80211Error> {
+        let replies = self.execute_dump(Nl80211Request::new(
+            "CMD_GET_WIPHY",
             Nl80211Cmd::CmdGetWiphy,
-            NL_80211_GENL_VERSION,
-            GenlBuffer::new(),
-        );
-
-        let nlhdr: Nlmsghdr<u16, Genlmsghdr<Nl80211Cmd, Nl80211Attr>> = {
-            let len = None;
-            let nl_type = self.family_id;
-            let flags = NlmFFlags::new(&[NlmF::Request, NlmF::Dump]);
-            let seq = None;
-            let pid = None;
-            let payload = NlPayload::Payload(msghdr);
-            Nlmsghdr::new(len, nl_type, flags, seq, pid, payload)
-        };
-
-        self.sock
-            .send(nlhdr)
-            .map_err(|err| format!("CMD_GET_WIPHY {}", err))?;
-
-        let iter = self
-            .sock
-            .iter::<Nlmsg, Genlmsghdr<Nl80211Cmd, Nl80211Attr>>(false);
+        ))?;
 
         let mut phys: HashMap<u32, WirelessPhy> = HashMap::new();
         let mut phys_available: Vec<u32> = Vec::new();
 
-        for response in iter {
-            let response = response.unwrap();
-            match response.nl_type {
-                Nlmsg::Noop => (),
-                Nlmsg::Error => panic!("Error with netlink during cmd_get_split_wiphy()"),
-                Nlmsg::Done => break,
-                _ => {
-                    if let Some(p) = response.nl_payload.get_payload() {
-                        if p.cmd != Nl80211Cmd::CmdNewWiphy {
-                            continue;
-                        }
-                        let handle = p.get_attr_handle();
-                        let wiphy: u32 = handle
-                            .get_attribute(Nl80211Attr::AttrWiphy)
-                            .unwrap()
-                            .get_payload_as()
-                            .unwrap();
-                        phys_available.push(wiphy);
-                    }
-                }
+        for p in replies {
+            if p.cmd != Nl80211Cmd::CmdNewWiphy {
+                continue;
             }
+            let handle = p.get_attr_handle();
+            let wiphy: u32 = handle.get_attr_payload_as(Nl80211Attr::AttrWiphy)?;
+            phys_available.push(wiphy);
         }
         for phy in phys_available {
-            if let Ok(phy_data) = self.cmd_get_wiphy(phy) {
-                phys.insert(phy, phy_data);
-            } else {
-                continue;
+            match self.cmd_get_wiphy(phy) {
+                Ok(phy_data) => {
+                    phys.insert(phy, phy_data);
+                }
+                // Removed since the first dump
+                Err(err) if err.is_not_found() => continue,
+                Err(err) => return Err(err),
             }
         }
         Ok(phys)
@@ -473,149 +228,31 @@ impl NtSocket {
         interface_index: u32,
         iftype: Nl80211Iftype,
         active: bool,
-    ) -> Result<(), String> {
-        let msghdr = Genlmsghdr::<Nl80211Cmd, Nl80211Attr>::new(
-            Nl80211Cmd::CmdSetInterface,
-            NL_80211_GENL_VERSION,
-            {
-                let mut attrs = GenlBuffer::new();
-                attrs.push(
-                    Nlattr::new(false, false, Nl80211Attr::AttrIfindex, interface_index).unwrap(),
-                );
-                attrs.push(
-                    Nlattr::new(
-                        false,
-                        false,
-                        Nl80211Attr::AttrIftype,
-                        u16::from(iftype) as u32,
-                    )
-                    .unwrap(),
-                );
-                if iftype == Nl80211Iftype::IftypeMonitor && active {
-                    attrs.push(
-                        Nlattr::new(
-                            false,
-                            false,
-                            Nl80211Attr::AttrMntrFlags,
-                            Nlattr {
-                                nla_len: 4,
-                                nla_type: AttrType {
-                                    nla_nested: false,
-                                    nla_network_order: false,
-                                    nla_type: Nl80211MntrFlags::MntrFlagActive,
-                                },
-                                nla_payload: Buffer::new(),
-                            },
-                        )
-                        .unwrap(),
-                    );
-                }
-                attrs
-            },
-        );
-
-        let nlhdr: Nlmsghdr<u16, Genlmsghdr<Nl80211Cmd, Nl80211Attr>> = {
-            let len = None;
-            let nl_type = self.family_id;
-            let flags = NlmFFlags::new(&[NlmF::Request, NlmF::Ack]);
-            let seq = None;
-            let pid = None;
-            let payload = NlPayload::Payload(msghdr);
-            Nlmsghdr::new(len, nl_type, flags, seq, pid, payload)
+    ) -> Result<(), Nl80211Error> {
+        let flags = if iftype == Nl80211Iftype::IftypeMonitor && active {
+            Some(MonitorFlags::active())
+        } else {
+            None
         };
-
-        // Send the Netlink message
-        self.sock.send(nlhdr).map_err(|err| err.to_string())?;
-
-        let iter = self
-            .sock
-            .iter::<Nlmsg, Genlmsghdr<Nl80211Cmd, Nl80211Attr>>(true);
-
-        for response in iter.flatten() {
-            match response.nl_type {
-                Nlmsg::Noop => (),
-                Nlmsg::Error => match response.nl_payload {
-                    NlPayload::Ack(_ack) => continue,
-                    NlPayload::Err(err) => {
-                        return Err(err.to_string());
-                    }
-                    NlPayload::Payload(p) => {
-                        return Err(format!("{:?}", p));
-                    }
-                    NlPayload::Empty => {
-                        return Err("Payload was empty".to_string());
-                    }
-                },
-                Nlmsg::Done => break,
-                _ => (), //println!("Response: {:#?}", response.nl_payload),
-            }
-        }
-        Ok(())
+        self.set_interface_type(interface_index, iftype, flags)
     }
 
-    pub fn set_powersave_off(&mut self, interface_index: u32) -> Result<(), String> {
-        let mut buffer = Buffer::new();
-        buffer.extend_from_slice(&[0;4]);
-        let gmsghdr = Genlmsghdr::<Nl80211Cmd, Nl80211Attr>::new(
-            Nl80211Cmd::CmdSetPowerSave,
-            NL_80211_GENL_VERSION,
-            {
-                let mut attrs = GenlBuffer::new();
-                attrs.push(
-                    Nlattr::new(false, false, Nl80211Attr::AttrIfindex, interface_index).unwrap(),
-                );
-                attrs.push(
-                    Nlattr {
-                        nla_len: 8,
-                        nla_type: AttrType {
-                            nla_nested: false,
-                            nla_network_order: false,
-                            nla_type: Nl80211Attr::AttrPsState,
-                        },
-                        nla_payload: buffer,
-                    },
-                );
-                attrs
-            },
-        );
+    /// Change the interface type, `monitor_flags` replaces the monitor flags when switching to monitor mode (None leaves them to the kernel).
+    pub fn set_interface_type(
+        &mut self,
+        interface_index: u32,
+        iftype: Nl80211Iftype,
+        monitor_flags: Option<MonitorFlags>,
+    ) -> Result<(), Nl80211Error> {
+        self.execute_ack(set_interface_type_request(
+            interface_index,
+            iftype,
+            monitor_flags,
+        ))
+    }
 
-        let nlhdr: Nlmsghdr<u16, Genlmsghdr<Nl80211Cmd, Nl80211Attr>> = {
-            let len = None;
-            let nl_type = self.family_id;
-            let flags = NlmFFlags::new(&[NlmF::Request, NlmF::Ack]);
-            let seq = None;
-            let pid = None;
-            let payload = NlPayload::Payload(gmsghdr);
-            Nlmsghdr::new(len, nl_type, flags, seq, pid, payload)
-        };
-        
-        // Send the Netlink message
-        self.sock.send(nlhdr).map_err(|err| err.to_string())?;
-
-        let iter = self
-            .sock
-            .iter::<Nlmsg, Genlmsghdr<Nl80211Cmd, Nl80211Attr>>(false);
-
-        for response in iter.flatten() {
-            match response.nl_type {
-                Nlmsg::Noop => (),
-                Nlmsg::Error => match response.nl_payload {
-                    NlPayload::Ack(_ack) => continue,
-                    NlPayload::Err(err) => {
-                        return Err(err.to_string());
-                    }
-                    NlPayload::Payload(p) => {
-                        return Err(format!("{:?}", p));
-                    }
-                    NlPayload::Empty => {
-                        return Err("Payload was empty".to_string());
-                    }
-                },
-                Nlmsg::Done => break,
-                _ => (),
-            }
-        }
-        Ok(())
+    pub fn set_powersave_off(&mut self, interface_index: u32) -> Result<(), Nl80211Error> {
+        self.execute_ack(powersave_off_request(interface_index))
     }
 
     pub fn set_frequency(
@@ -624,89 +261,533 @@ impl NtSocket {
         frequency: u32,
         chan_width: Nl80211ChanWidth,
         chan_type: Nl80211ChannelType,
-    ) -> Result<(), String> {
-        let gmsghdr = Genlmsghdr::<Nl80211Cmd, Nl80211Attr>::new(
-            Nl80211Cmd::CmdSetChannel,
-            NL_80211_GENL_VERSION,
-            {
-                let mut attrs = GenlBuffer::new();
-                attrs.push(
-                    Nlattr::new(false, false, Nl80211Attr::AttrIfindex, interface_index).unwrap(),
-                );
-                attrs.push(
-                    Nlattr::new(false, false, Nl80211Attr::AttrWiphyFreq, frequency).unwrap(),
-                );
-                attrs.push(
-                    Nlattr::new(
-                        false,
-                        false,
-                        Nl80211Attr::AttrChannelWidth,
-                        u32::from(u16::from(chan_width)),
-                    )
-                    .unwrap(),
-                );
-                attrs.push(
-                    Nlattr::new(
-                        false,
-                        false,
-                        Nl80211Attr::AttrWiphyChannelType,
-                        u32::from(u16::from(chan_type)),
-                    )
-                    .unwrap(),
-                );
-                attrs.push(
-                    Nlattr::new(false, false, Nl80211Attr::AttrCenterFreq1, frequency).unwrap(),
-                );
-                attrs
-            },
-        );
+    ) -> Result<(), Nl80211Error> {
+        self.execute_ack(frequency_request(
+            interface_index,
+            frequency,
+            chan_width,
+            chan_type,
+        ))
+    }
 
-        let nlhdr: Nlmsghdr<u16, Genlmsghdr<Nl80211Cmd, Nl80211Attr>> = {
-            let len = None;
-            let nl_type = self.family_id;
-            let flags = NlmFFlags::new(&[NlmF::Request, NlmF::Ack]);
-            let seq = None;
-            let pid = None;
-            let payload = NlPayload::Payload(gmsghdr);
-            Nlmsghdr::new(len, nl_type, flags, seq, pid, payload)
-        };
+    /// Set the interface channel from a `ChannelSpec`, including the center frequencies of wide channels.
+    pub fn set_channel_spec(
+        &mut self,
+        interface_index: u32,
+        spec: &ChannelSpec,
+    ) -> Result<(), Nl80211Error> {
+        self.execute_ack(channel_spec_request(interface_index, spec))
+    }
+
+    /// Create a new virtual interface on the wiphy, returns the interface index of the new interface.
+    pub fn create_interface(
+        &mut self,
+        wiphy: u32,
+        name: &str,
+        iftype: Nl80211Iftype,
+        options: &InterfaceOptions,
+    ) -> Result<u32, Nl80211Error> {
+        let reply = self.execute_get(create_interface_request(wiphy, name, iftype, options))?;
+        new_interface_reply(reply)
+    }
+
+    /// Delete a virtual interface.
+    pub fn delete_interface(&mut self, interface_index: u32) -> Result<(), Nl80211Error> {
+        self.execute_ack(delete_interface_request(interface_index))
+    }
+
+    /// Trigger a scan on the interface and block until the kernel reports the results are ready
+    /// (or the scan was aborted). Gives up with ETIMEDOUT after the socket's timeout, scans of
+    /// every channel can take longer than `DEFAULT_TIMEOUT` so `set_timeout` may need raising.
+    pub fn trigger_scan(
+        &mut self,
+        interface_index: u32,
+        request: ScanRequest,
+    ) -> Result<(), Nl80211Error> {
+        let group = scan_group(&self.mcast_groups)?;
+        // Join the scan group before triggering so the completion notification can't be missed.
+        self.transport
+            .join_group(group)
+            .map_err(|err| Nl80211Error::io("CMD_TRIGGER_SCAN", err))?;
+        self.transactor.keep_notifications(true);
+        let result = self
+            .execute_ack(scan_request(interface_index, &request))
+            .and_then(|()| self.wait_for_scan(interface_index));
+        self.transactor.keep_notifications(false);
+        // Leftover scan notifications are dropped by later requests anyway
+        let _ = self.transport.leave_group(group);
+        result
+    }
 
-        // Send the Netlink message
-
-        self.sock
-            .send(nlhdr)
-            .map_err(|err| format!("Send Err: {}", err))?;
-
-        let iter = self
-            .sock
-            .iter::<Nlmsg, Genlmsghdr<Nl80211Cmd, Nl80211Attr>>(false);
-
-        for response in iter.flatten() {
-            match response.nl_type {
-                Nlmsg::Noop => (),
-                Nlmsg::Error => match response.nl_payload {
-                    NlPayload::Ack(_ack) => continue,
-                    NlPayload::Err(err) => {
-                        return Err(format!("NlPayload::Err {}", err));
-                    }
-                    NlPayload::Payload(p) => {
-                        return Err(format!("NlPayload::Payload {:?}", p));
-                    }
-                    NlPayload::Empty => {
-                        return Err("Payload was empty".to_string());
-                    }
-                },
-                Nlmsg::Done => break,
-                _ => (),
+    /// Wait on the scan group for this interface's scan to finish.
+    fn wait_for_scan(&mut self, interface_index: u32) -> Result<(), Nl80211Error> {
+        let deadline = self
+            .transactor
+            .timeout()
+            .map(|timeout| Instant::now() + timeout);
+        loop {
+            let msg = self.transactor.next_notification(
+                "CMD_TRIGGER_SCAN",
+                &mut self.transport,
+                deadline,
+            )?;
+            if let Some(result) = scan_outcome(self.family_id, interface_index, &msg) {
+                return result;
             }
         }
-        Ok(())
+    }
+
+    /// Dump the interface's current scan results.
+    pub fn get_scan_results(&mut self, interface_index: u32) -> Result<Vec<Bss>, Nl80211Error> {
+        let replies = self.execute_dump(scan_results_request(interface_index))?;
+        scan_results_reply(replies)
+    }
+
+    /// Get the statistics of a single station (peer) of the interface.
+    pub fn get_station(
+        &mut self,
+        interface_index: u32,
+        mac: &[u8; 6],
+    ) -> Result<StationInfo, Nl80211Error> {
+        let reply = self.execute_get(station_request(interface_index, Some(mac)))?;
+        station_reply(reply)
+    }
+
+    /// Dump the statistics of every station (peer) of the interface.
+    pub fn dump_stations(
+        &mut self,
+        interface_index: u32,
+    ) -> Result<Vec<StationInfo>, Nl80211Error> {
+        let replies = self.execute_dump(station_request(interface_index, None))?;
+        stations_reply(replies)
+    }
+
+    /// Dump the channel survey of the interface's phy.
+    pub fn get_survey(&mut self, interface_index: u32) -> Result<Vec<SurveyInfo>, Nl80211Error> {
+        let replies = self.execute_dump(survey_request(interface_index))?;
+        survey_reply(replies)
+    }
+
+    /// Get the regulatory domain in effect for the wiphy (its self-managed domain if it has one), or the global domain.
+    pub fn get_regdomain(&mut self, wiphy: Option<u32>) -> Result<RegDomain, Nl80211Error> {
+        let reply = self.execute_get(regdomain_request(wiphy))?;
+        regdomain_reply(reply)
+    }
+
+    /// Dump the global regulatory domain followed by every self-managed wiphy domain.
+    pub fn dump_regdomains(&mut self) -> Result<Vec<RegDomain>, Nl80211Error> {
+        let replies = self.execute_dump(regdomain_request(None))?;
+        regdomains_reply(replies)
+    }
+
+    /// Ask the kernel to apply the regulatory domain of the given ISO 3166-1 alpha2 country ("00" for world).
+    pub fn request_regdomain(&mut self, alpha2: &str) -> Result<(), Nl80211Error> {
+        self.execute_ack(set_regdomain_request(alpha2)?)
+    }
+}
+
+/// CTRL_CMD_GETFAMILY, the id of the nl80211 family and of its multicast groups by name.
+fn resolve_family<T: Transport>(
+    transactor: &mut Transactor,
+    transport: &mut T,
+) -> Result<(u16, HashMap<String, u32>), Nl80211Error> {
+    let mut attrs = GenlBuffer::new();
+    attrs.push(
+        Nlattr::new(false, false, CtrlAttr::FamilyName, NL_80211_GENL_NAME)
+            .map_err(|err| Nl80211Error::ser("CTRL_CMD_GETFAMILY", err))?,
+    );
+    let request = Nlmsghdr::new(
+        None,
+        GenlId::Ctrl,
+        NlmFFlags::new(&[NlmF::Request, NlmF::Ack]),
+        None,
+        None,
+        NlPayload::Payload(Genlmsghdr::<CtrlCmd, CtrlAttr>::new(
+            CtrlCmd::Getfamily,
+            2,
+            attrs,
+        )),
+    );
+    let replies = transactor.transact::<_, _, _, Nlmsg, Genlmsghdr<CtrlCmd, CtrlAttr>>(
+        "CTRL_CMD_GETFAMILY",
+        transport,
+        request,
+        Nl80211Error::nlmsgerr,
+    )?;
+    for reply in payloads(replies) {
+        let handle = reply.get_attr_handle();
+        let Ok(family_id) = handle.get_attr_payload_as::<u16>(CtrlAttr::FamilyId) else {
+            continue;
+        };
+        let mut mcast_groups = HashMap::new();
+        let groups = handle
+            .get_attribute(CtrlAttr::McastGroups)
+            .and_then(|groups| groups.get_attr_handle::<u16>().ok());
+        for group in groups.iter().flat_map(|groups| groups.iter()) {
+            let Ok(group) = group.get_attr_handle::<CtrlAttrMcastGrp>() else {
+                continue;
+            };
+            let name = group
+                .get_attribute(CtrlAttrMcastGrp::Name)
+                .and_then(|name| decode_string(name.nla_payload.as_ref()).ok());
+            let id = group.get_attr_payload_as::<u32>(CtrlAttrMcastGrp::Id).ok();
+            if let (Some(name), Some(id)) = (name, id) {
+                mcast_groups.insert(name, id);
+            }
+        }
+        return Ok((family_id, mcast_groups));
+    }
+    Err(Nl80211Error::NotFound("nl80211 family".to_string()))
+}
+
+/// The netlink message of a request, the sequence number and port id are filled in when it's sent.
+pub(crate) fn genl_request(
+    family_id: u16,
+    request: Nl80211Request,
+    flags: &[NlmF],
+) -> Result<Nlmsghdr<u16, Genlmsghdr<Nl80211Cmd, Nl80211Attr>>, Nl80211Error> {
+    let cmd = request.cmd;
+    let msghdr = Genlmsghdr::<Nl80211Cmd, Nl80211Attr>::new(
+        cmd,
+        NL_80211_GENL_VERSION,
+        request.into_attrs()?,
+    );
+    Ok(Nlmsghdr::new(
+        None,
+        family_id,
+        NlmFFlags::new(flags),
+        None,
+        None,
+        NlPayload::Payload(msghdr),
+    ))
+}
+
+/// CmdSetInterface changing the interface type
+pub(crate) fn set_interface_type_request(
+    interface_index: u32,
+    iftype: Nl80211Iftype,
+    monitor_flags: Option<MonitorFlags>,
+) -> Nl80211Request {
+    let request = Nl80211Request::new("CMD_SET_INTERFACE", Nl80211Cmd::CmdSetInterface)
+        .attr(Nl80211Attr::AttrIfindex, interface_index)
+        .attr(Nl80211Attr::AttrIftype, u16::from(iftype) as u32);
+    match monitor_flags {
+        Some(flags) if iftype == Nl80211Iftype::IftypeMonitor => with_monitor_flags(request, flags),
+        _ => request,
+    }
+}
+
+/// CmdTriggerScan for the SSIDs, frequencies and flags of the request
+pub(crate) fn scan_request(interface_index: u32, request: &ScanRequest) -> Nl80211Request {
+    let mut scan = Nl80211Request::new("CMD_TRIGGER_SCAN", Nl80211Cmd::CmdTriggerScan)
+        .attr(Nl80211Attr::AttrIfindex, interface_index);
+    if !request.ssids.is_empty() {
+        scan = scan.nested(
+            Nl80211Attr::AttrScanSsids,
+            request
+                .ssids
+                .iter()
+                .enumerate()
+                .map(|(i, ssid)| (i as u16 + 1, &ssid[..])),
+        );
+    }
+    if !request.frequencies.is_empty() {
+        scan = scan.nested(
+            Nl80211Attr::AttrScanFrequencies,
+            request
+                .frequencies
+                .iter()
+                .enumerate()
+                .map(|(i, freq)| (i as u16 + 1, *freq)),
+        );
+    }
+    let flags = request.flags_as_u32();
+    if flags != 0 {
+        scan = scan.attr(Nl80211Attr::AttrScanFlags, flags);
+    }
+    scan
+}
+
+/// CmdSetPowerSave disabling power save
+pub(crate) fn powersave_off_request(interface_index: u32) -> Nl80211Request {
+    Nl80211Request::new("CMD_SET_POWER_SAVE", Nl80211Cmd::CmdSetPowerSave)
+        .attr(Nl80211Attr::AttrIfindex, interface_index)
+        .attr(Nl80211Attr::AttrPsState, 0u32)
+}
+
+/// CmdSetChannel for a primary frequency
+pub(crate) fn frequency_request(
+    interface_index: u32,
+    frequency: u32,
+    chan_width: Nl80211ChanWidth,
+    chan_type: Nl80211ChannelType,
+) -> Nl80211Request {
+    Nl80211Request::new("CMD_SET_CHANNEL", Nl80211Cmd::CmdSetChannel)
+        .attr(Nl80211Attr::AttrIfindex, interface_index)
+        .attr(Nl80211Attr::AttrWiphyFreq, frequency)
+        .attr(
+            Nl80211Attr::AttrChannelWidth,
+            u32::from(u16::from(chan_width)),
+        )
+        .attr(
+            Nl80211Attr::AttrWiphyChannelType,
+            u32::from(u16::from(chan_type)),
+        )
+        .attr(Nl80211Attr::AttrCenterFreq1, frequency)
+}
+
+/// CmdSetChannel for a `ChannelSpec`
+pub(crate) fn channel_spec_request(interface_index: u32, spec: &ChannelSpec) -> Nl80211Request {
+    Nl80211Request::new("CMD_SET_CHANNEL", Nl80211Cmd::CmdSetChannel)
+        .attr(Nl80211Attr::AttrIfindex, interface_index)
+        .attr(Nl80211Attr::AttrWiphyFreq, spec.frequency)
+        // The kernel prefers the legacy channel type over the width when both are present,
+        // so it is only sent for the channels it can describe.
+        .attr_opt(
+            Nl80211Attr::AttrWiphyChannelType,
+            spec.channel_type()
+                .map(|chan_type| u32::from(u16::from(chan_type))),
+        )
+        .attr(
+            Nl80211Attr::AttrChannelWidth,
+            u32::from(u16::from(spec.width)),
+        )
+        .attr(Nl80211Attr::AttrCenterFreq1, spec.center_freq1)
+        .attr_opt(Nl80211Attr::AttrCenterFreq2, spec.center_freq2)
+}
+
+/// CmdNewInterface for a new virtual interface on the wiphy
+pub(crate) fn create_interface_request(
+    wiphy: u32,
+    name: &str,
+    iftype: Nl80211Iftype,
+    options: &InterfaceOptions,
+) -> Nl80211Request {
+    let request = Nl80211Request::new("CMD_NEW_INTERFACE", Nl80211Cmd::CmdNewInterface)
+        .attr(Nl80211Attr::AttrWiphy, wiphy)
+        .attr(Nl80211Attr::AttrIfname, name)
+        .attr(Nl80211Attr::AttrIftype, u16::from(iftype) as u32)
+        .attr_opt(Nl80211Attr::Attr4addr, options.four_address.map(u8::from))
+        .attr_opt(
+            Nl80211Attr::AttrMac,
+            options.mac.as_ref().map(|mac| &mac[..]),
+        );
+    if iftype == Nl80211Iftype::IftypeMonitor && !options.monitor_flags.is_empty() {
+        with_monitor_flags(request, options.monitor_flags)
+    } else {
+        request
+    }
+}
+
+/// The interface index from the reply to CmdNewInterface
+pub(crate) fn new_interface_reply(
+    reply: Option<Genlmsghdr<Nl80211Cmd, Nl80211Attr>>,
+) -> Result<u32, Nl80211Error> {
+    reply
+        .and_then(|p| {
+            p.get_attr_handle()
+                .get_attr_payload_as::<u32>(Nl80211Attr::AttrIfindex)
+                .ok()
+        })
+        .ok_or(Nl80211Error::Parse(
+            "CMD_NEW_INTERFACE reply did not contain an interface index".to_string(),
+        ))
+}
+
+/// CmdDelInterface
+pub(crate) fn delete_interface_request(interface_index: u32) -> Nl80211Request {
+    Nl80211Request::new("CMD_DEL_INTERFACE", Nl80211Cmd::CmdDelInterface)
+        .attr(Nl80211Attr::AttrIfindex, interface_index)
+}
+
+/// The id of the scan multicast group
+pub(crate) fn scan_group(mcast_groups: &HashMap<String, u32>) -> Result<u32, Nl80211Error> {
+    mcast_groups
+        .get(McastGroup::Scan.name())
+        .copied()
+        .ok_or(Nl80211Error::NotFound("Multicast group scan".to_string()))
+}
+
+/// Whether a scan group notification ends the interface's scan, and how.
+pub(crate) fn scan_outcome(
+    family_id: u16,
+    interface_index: u32,
+    msg: &Nlmsghdr<u16, Genlmsghdr<Nl80211Cmd, Nl80211Attr>>,
+) -> Option<Result<(), Nl80211Error>> {
+    if msg.nl_type != family_id {
+        return None;
+    }
+    let p = msg.nl_payload.get_payload()?;
+    let ifindex: Option<u32> = p
+        .get_attr_handle()
+        .get_attr_payload_as(Nl80211Attr::AttrIfindex)
+        .ok();
+    if ifindex != Some(interface_index) {
+        return None;
+    }
+    match p.cmd {
+        Nl80211Cmd::CmdNewScanResults => Some(Ok(())),
+        Nl80211Cmd::CmdScanAborted => Some(Err(Nl80211Error::ScanAborted)),
+        _ => None,
+    }
+}
+
+/// CmdGetScan dump of the interface's scan results
+pub(crate) fn scan_results_request(interface_index: u32) -> Nl80211Request {
+    Nl80211Request::new("CMD_GET_SCAN", Nl80211Cmd::CmdGetScan)
+        .attr(Nl80211Attr::AttrIfindex, interface_index)
+}
+
+/// The BSSs of a CmdGetScan dump
+pub(crate) fn scan_results_reply(
+    replies: Vec<Genlmsghdr<Nl80211Cmd, Nl80211Attr>>,
+) -> Result<Vec<Bss>, Nl80211Error> {
+    let mut results: Vec<Bss> = Vec::new();
+
+    for p in replies {
+        if p.cmd != Nl80211Cmd::CmdNewScanResults {
+            continue;
+        }
+        if let Some(bss) = decode_bss(&p)? {
+            results.push(bss);
+        }
+    }
+    Ok(results)
+}
+
+/// CmdGetStation for one station of the interface, or a dump of all of them
+pub(crate) fn station_request(interface_index: u32, mac: Option<&[u8; 6]>) -> Nl80211Request {
+    Nl80211Request::new("CMD_GET_STATION", Nl80211Cmd::CmdGetStation)
+        .attr(Nl80211Attr::AttrIfindex, interface_index)
+        .attr_opt(Nl80211Attr::AttrMac, mac.map(|mac| &mac[..]))
+}
+
+/// The station of a CmdGetStation reply
+pub(crate) fn station_reply(
+    reply: Option<Genlmsghdr<Nl80211Cmd, Nl80211Attr>>,
+) -> Result<StationInfo, Nl80211Error> {
+    match reply {
+        Some(p) if p.cmd == Nl80211Cmd::CmdNewStation => decode_station(&p),
+        _ => Err(Nl80211Error::NotFound("Station".to_string())),
+    }
+}
+
+/// The stations of a CmdGetStation dump
+pub(crate) fn stations_reply(
+    replies: Vec<Genlmsghdr<Nl80211Cmd, Nl80211Attr>>,
+) -> Result<Vec<StationInfo>, Nl80211Error> {
+    let mut stations: Vec<StationInfo> = Vec::new();
+
+    for p in replies {
+        if p.cmd != Nl80211Cmd::CmdNewStation {
+            continue;
+        }
+        stations.push(decode_station(&p)?);
+    }
+    Ok(stations)
+}
+
+/// CmdGetSurvey dump of the interface's phy
+pub(crate) fn survey_request(interface_index: u32) -> Nl80211Request {
+    Nl80211Request::new("CMD_GET_SURVEY", Nl80211Cmd::CmdGetSurvey)
+        .attr(Nl80211Attr::AttrIfindex, interface_index)
+}
+
+/// The channels of a CmdGetSurvey dump
+pub(crate) fn survey_reply(
+    replies: Vec<Genlmsghdr<Nl80211Cmd, Nl80211Attr>>,
+) -> Result<Vec<SurveyInfo>, Nl80211Error> {
+    let mut surveys: Vec<SurveyInfo> = Vec::new();
+
+    for p in replies {
+        if p.cmd != Nl80211Cmd::CmdNewSurveyResults {
+            continue;
+        }
+        if let Some(survey) = decode_survey(&p)? {
+            surveys.push(survey);
+        }
+    }
+    Ok(surveys)
+}
+
+/// CmdGetReg for the wiphy's domain, or the global one
+pub(crate) fn regdomain_request(wiphy: Option<u32>) -> Nl80211Request {
+    Nl80211Request::new("CMD_GET_REG", Nl80211Cmd::CmdGetReg)
+        .attr_opt(Nl80211Attr::AttrWiphy, wiphy)
+}
+
+/// The domain of a CmdGetReg reply
+pub(crate) fn regdomain_reply(
+    reply: Option<Genlmsghdr<Nl80211Cmd, Nl80211Attr>>,
+) -> Result<RegDomain, Nl80211Error> {
+    match reply {
+        Some(p) if p.cmd == Nl80211Cmd::CmdGetReg => decode_regdomain(&p),
+        _ => Err(Nl80211Error::NotFound("Regulatory domain".to_string())),
+    }
+}
+
+/// The domains of a CmdGetReg dump
+pub(crate) fn regdomains_reply(
+    replies: Vec<Genlmsghdr<Nl80211Cmd, Nl80211Attr>>,
+) -> Result<Vec<RegDomain>, Nl80211Error> {
+    let mut regdomains: Vec<RegDomain> = Vec::new();
+
+    for p in replies {
+        if p.cmd != Nl80211Cmd::CmdGetReg {
+            continue;
+        }
+        regdomains.push(decode_regdomain(&p)?);
+    }
+    Ok(regdomains)
+}
+
+/// CmdReqSetReg for an ISO 3166-1 alpha2 country ("00" for world)
+pub(crate) fn set_regdomain_request(alpha2: &str) -> Result<Nl80211Request, Nl80211Error> {
+    if alpha2.len() != 2 || !alpha2.is_ascii() {
+        return Err(Nl80211Error::InvalidArgument(format!(
+            "Invalid alpha2 country code: {}",
+            alpha2
+        )));
+    }
+    Ok(
+        Nl80211Request::new("CMD_REQ_SET_REG", Nl80211Cmd::CmdReqSetReg)
+            .attr(Nl80211Attr::AttrRegAlpha2, alpha2.to_ascii_uppercase()),
+    )
+}
+
+/// Add the nested `AttrMntrFlags` attribute, one empty flag attribute per flag set.
+fn with_monitor_flags(request: Nl80211Request, flags: MonitorFlags) -> Nl80211Request {
+    request.nested(
+        Nl80211Attr::AttrMntrFlags,
+        flags.flags().into_iter().map(|flag| (flag, Buffer::new())),
+    )
+}
+
+/// Ask the kernel for extended ACK messages on errors, best effort as older kernels don't support it.
+fn enable_ext_ack(sock: &NlSocketHandle) {
+    const SOL_NETLINK: libc::c_int = 270;
+    const NETLINK_EXT_ACK: libc::c_int = 11;
+    let enable: libc::c_int = 1;
+    unsafe {
+        libc::setsockopt(
+            sock.as_raw_fd(),
+            SOL_NETLINK,
+            NETLINK_EXT_ACK,
+            &enable as *const libc::c_int as *const libc::c_void,
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        );
+    }
+}
+
+impl<T: AsRawFd> AsRawFd for NtSocket<T> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.transport.as_raw_fd()
     }
 }
 
 impl From<NtSocket> for NlSocketHandle {
     /// Returns the underlying generic netlink socket
     fn from(sock: NtSocket) -> Self {
-        sock.sock
+        sock.transport.into()
     }
 }
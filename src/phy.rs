@@ -1,5 +1,11 @@
-use super::attr::{Nl80211ChanWidth, Nl80211Iftype};
-use super::channels::BandList;
+use super::attr::{Nl80211ChanWidth, Nl80211ExtFeatureIndex, Nl80211FeatureFlags, Nl80211Iftype};
+use super::channels::{
+    supported_channel_specs, validate_channel_spec, BandList, ChannelError, ChannelSpec, WiFiBand,
+};
+use super::cmd::Nl80211Cmd;
+use super::combination::{check_combinations, ConcurrencyRequest, InterfaceCombination};
+
+use std::fs;
 
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct WirelessPhy {
@@ -12,7 +18,188 @@ pub struct WirelessPhy {
     pub driver: Option<String>,                // Driver information
     pub has_netlink: Option<bool>,             // If Interface has netlink
     pub active_monitor: Option<bool>,          // If Interface has netlink
-    pub frequency: Frequency,          // If Interface has netlink
+    pub frequency: Frequency,                  // If Interface has netlink
+    pub supported_commands: Option<Vec<Nl80211Cmd>>, // AttrSupportedCommands
+    pub cipher_suites: Option<Vec<u32>>,       // AttrCipherSuites, see cipher_suite_name
+    pub akm_suites: Option<Vec<u32>>,          // AttrAkmSuites, see akm_suite_name
+    pub max_scan_ssids: Option<u8>,            // AttrMaxNumScanSsids
+    pub max_scan_ie_len: Option<u16>,          // AttrMaxScanIeLen
+    pub max_sched_scan_ssids: Option<u8>,      // AttrMaxNumSchedScanSsids
+    pub max_sched_scan_ie_len: Option<u16>,    // AttrMaxSchedScanIeLen
+    pub max_match_sets: Option<u8>,            // AttrMaxMatchSets
+    pub max_remain_on_channel_duration: Option<u32>, // AttrMaxRemainOnChannelDuration (ms)
+    pub antenna_avail_tx: Option<u32>,         // AttrWiphyAntennaAvailTx, antenna bitmask
+    pub antenna_avail_rx: Option<u32>,         // AttrWiphyAntennaAvailRx, antenna bitmask
+    pub antenna_tx: Option<u32>,               // AttrWiphyAntennaTx, configured bitmask
+    pub antenna_rx: Option<u32>,               // AttrWiphyAntennaRx, configured bitmask
+    pub retry_short: Option<u8>,               // AttrWiphyRetryShort
+    pub retry_long: Option<u8>,                // AttrWiphyRetryLong
+    pub frag_threshold: Option<u32>,           // AttrWiphyFragThreshold, u32::MAX if disabled
+    pub rts_threshold: Option<u32>,            // AttrWiphyRtsThreshold, u32::MAX if disabled
+    pub coverage_class: Option<u8>,            // AttrWiphyCoverageClass
+    pub feature_flags: Option<FeatureFlags>,   // AttrFeatureFlags
+    pub ext_features: Option<ExtFeatures>,     // AttrExtFeatures
+    pub software_iftypes: Option<Vec<Nl80211Iftype>>, // AttrSoftwareIftypes, no combination limits
+    pub num_interface_combinations: Option<u32>, // Number of AttrInterfaceCombinations entries
+    pub interface_combinations: Option<Vec<InterfaceCombination>>, // AttrInterfaceCombinations
+}
+
+impl WirelessPhy {
+    /// Check the channel against the phy's frequency list before handing it to the kernel, for an
+    /// interface of the given type.
+    pub fn validate_channel_spec(
+        &self,
+        spec: &ChannelSpec,
+        iftype: Nl80211Iftype,
+    ) -> Result<(), ChannelError> {
+        validate_channel_spec(
+            spec,
+            self.frequency_list.as_deref().unwrap_or_default(),
+            iftype,
+        )
+    }
+
+    /// Every usable primary channel and width combination of the phy for an interface of the given type.
+    pub fn supported_channel_specs(&self, iftype: Nl80211Iftype) -> Vec<ChannelSpec> {
+        supported_channel_specs(self.frequency_list.as_deref().unwrap_or_default(), iftype)
+    }
+
+    /// Whether the interfaces can run concurrently, checked against the interface combinations
+    /// before creating or bringing up an interface instead of waiting for EBUSY.
+    ///
+    /// Software interface types (usually monitor) don't count, see `software_iftypes`.
+    pub fn can_run(&self, request: &ConcurrencyRequest) -> bool {
+        check_combinations(
+            self.interface_combinations.as_deref().unwrap_or_default(),
+            self.software_iftypes.as_deref().unwrap_or_default(),
+            request,
+        )
+    }
+
+    /// The newest Wi-Fi generation the bands support, "Wi-Fi 4" (HT) up to "Wi-Fi 7" (EHT).
+    /// "Wi-Fi 6E" is HE on the 6 GHz band.
+    pub fn wifi_generation(&self) -> Option<&'static str> {
+        let bands = self.frequency_list.as_deref()?;
+        if bands.iter().any(|band| band.supports_eht()) {
+            Some("Wi-Fi 7")
+        } else if bands
+            .iter()
+            .any(|band| band.band == WiFiBand::Band6GHz && band.supports_he())
+        {
+            Some("Wi-Fi 6E")
+        } else if bands.iter().any(|band| band.supports_he()) {
+            Some("Wi-Fi 6")
+        } else if bands.iter().any(|band| band.vht.is_some()) {
+            Some("Wi-Fi 5")
+        } else if bands.iter().any(|band| band.ht.is_some()) {
+            Some("Wi-Fi 4")
+        } else {
+            None
+        }
+    }
+}
+
+/// The `Nl80211FeatureFlags` of a phy, one bit per flag
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FeatureFlags(u32);
+
+impl FeatureFlags {
+    pub fn from_bits(bits: u32) -> FeatureFlags {
+        FeatureFlags(bits)
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+
+    pub fn contains(&self, flag: Nl80211FeatureFlags) -> bool {
+        self.0 & u32::from(flag) != 0
+    }
+
+    /// The flags in the set, unknown bits as `UnrecognizedConst`.
+    pub fn flags(&self) -> Vec<Nl80211FeatureFlags> {
+        (0..32)
+            .map(|bit| 1u32 << bit)
+            .filter(|bit| self.0 & bit != 0)
+            .map(Nl80211FeatureFlags::from)
+            .collect()
+    }
+}
+
+/// The `Nl80211ExtFeatureIndex` bitmap of a phy, bit n of byte n / 8 for index n
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct ExtFeatures(Vec<u8>);
+
+impl ExtFeatures {
+    pub fn from_bytes(bytes: &[u8]) -> ExtFeatures {
+        ExtFeatures(bytes.to_vec())
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn contains(&self, feature: Nl80211ExtFeatureIndex) -> bool {
+        let index = u16::from(feature) as usize;
+        self.0
+            .get(index / 8)
+            .is_some_and(|byte| byte & (1 << (index % 8)) != 0)
+    }
+
+    /// The features in the bitmap, unknown indexes as `UnrecognizedConst`.
+    pub fn features(&self) -> Vec<Nl80211ExtFeatureIndex> {
+        (0..self.0.len() * 8)
+            .filter(|index| self.0[index / 8] & (1 << (index % 8)) != 0)
+            .map(|index| Nl80211ExtFeatureIndex::from(index as u16))
+            .collect()
+    }
+}
+
+/// The name of an IEEE 802.11 cipher suite selector, as in `AttrCipherSuites`.
+pub fn cipher_suite_name(suite: u32) -> &'static str {
+    match suite {
+        0x000f_ac01 => "WEP-40",
+        0x000f_ac02 => "TKIP",
+        0x000f_ac04 => "CCMP-128",
+        0x000f_ac05 => "WEP-104",
+        0x000f_ac06 => "BIP-CMAC-128",
+        0x000f_ac08 => "GCMP-128",
+        0x000f_ac09 => "GCMP-256",
+        0x000f_ac0a => "CCMP-256",
+        0x000f_ac0b => "BIP-GMAC-128",
+        0x000f_ac0c => "BIP-GMAC-256",
+        0x000f_ac0d => "BIP-CMAC-256",
+        0x0014_7201 => "SMS4",
+        _ => "Unknown",
+    }
+}
+
+/// The name of an IEEE 802.11 AKM suite selector, as in `AttrAkmSuites`.
+pub fn akm_suite_name(suite: u32) -> &'static str {
+    match suite {
+        0x000f_ac01 => "802.1X",
+        0x000f_ac02 => "PSK",
+        0x000f_ac03 => "FT/802.1X",
+        0x000f_ac04 => "FT/PSK",
+        0x000f_ac05 => "802.1X/SHA-256",
+        0x000f_ac06 => "PSK/SHA-256",
+        0x000f_ac07 => "TDLS",
+        0x000f_ac08 => "SAE",
+        0x000f_ac09 => "FT/SAE",
+        0x000f_ac0b => "802.1X/Suite-B",
+        0x000f_ac0c => "802.1X/Suite-B-192",
+        0x000f_ac0d => "FT/802.1X/SHA-384",
+        0x000f_ac0e => "FILS/SHA-256",
+        0x000f_ac0f => "FILS/SHA-384",
+        0x000f_ac10 => "FT/FILS/SHA-256",
+        0x000f_ac11 => "FT/FILS/SHA-384",
+        0x000f_ac12 => "OWE",
+        0x000f_ac13 => "FT/PSK/SHA-384",
+        0x000f_ac14 => "PSK/SHA-384",
+        0x000f_ac18 => "SAE-EXT-KEY",
+        0x000f_ac19 => "FT/SAE-EXT-KEY",
+        _ => "Unknown",
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -48,10 +235,20 @@ impl Frequency {
     }
 }
 
-pub fn iftypes_to_string_list(iftypes: &Vec<Nl80211Iftype>) -> String {
+pub fn iftypes_to_string_list(iftypes: &[Nl80211Iftype]) -> String {
     iftypes
         .iter()
         .map(|iftype| iftype.string())
         .collect::<Vec<&str>>()
         .join(", ")
 }
+
+/// The driver bound to the wiphy, from its sysfs device link.
+pub(crate) fn read_driver(phy_name: &str) -> Option<String> {
+    let link_path =
+        fs::read_link(format!("/sys/class/ieee80211/{}/device/driver", phy_name)).ok()?;
+    link_path
+        .file_name()
+        .and_then(|driver_name| driver_name.to_str())
+        .map(|driver_name| driver_name.to_string())
+}
@@ -0,0 +1,161 @@
+use crate::attr::{Nl80211DfsRegions, Nl80211RegRuleFlags};
+
+/// `NL80211_RRF_NO_160MHZ`, doesn't fit in the u16 backed `Nl80211RegRuleFlags`
+pub const RRF_NO_160MHZ: u32 = 1 << 16;
+
+/// A regulatory domain as reported by `CMD_GET_REG`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegDomain {
+    pub alpha2: String,                // AttrRegAlpha2 ("00" is the world domain)
+    pub dfs_region: Nl80211DfsRegions, // AttrDfsRegion
+    pub wiphy: Option<u32>,            // AttrWiphy, set for self-managed domains
+    pub self_managed: bool,            // AttrWiphySelfManagedReg
+    pub rules: Vec<RegRule>,           // AttrRegRules
+}
+
+impl Default for RegDomain {
+    fn default() -> Self {
+        RegDomain {
+            alpha2: String::new(),
+            dfs_region: Nl80211DfsRegions::DfsUnset,
+            wiphy: None,
+            self_managed: false,
+            rules: Vec::new(),
+        }
+    }
+}
+
+impl RegDomain {
+    /// The rule covering the given frequency (MHz), if any.
+    pub fn rule_for_frequency(&self, frequency: u32) -> Option<&RegRule> {
+        let khz = frequency * 1000;
+        self.rules
+            .iter()
+            .find(|rule| rule.start_freq_khz <= khz && khz <= rule.end_freq_khz)
+    }
+
+    pub fn pretty_print(&self) -> String {
+        let mut output = format!("country {}: {:?}", self.alpha2, self.dfs_region);
+        if let Some(wiphy) = self.wiphy {
+            output += &format!(
+                " (phy#{}{})",
+                wiphy,
+                if self.self_managed {
+                    ", self-managed"
+                } else {
+                    ""
+                }
+            );
+        }
+        output.push('\n');
+        for rule in &self.rules {
+            output += &format!("\t{}\n", rule.pretty_print());
+        }
+        output
+    }
+}
+
+/// A single rule of a regulatory domain
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RegRule {
+    pub start_freq_khz: u32,       // AttrFreqRangeStart
+    pub end_freq_khz: u32,         // AttrFreqRangeEnd
+    pub max_bandwidth_khz: u32,    // AttrFreqRangeMaxBw
+    pub max_antenna_gain: u32,     // AttrPowerRuleMaxAntGain (mBi)
+    pub max_eirp: u32,             // AttrPowerRuleMaxEirp (mBm)
+    pub dfs_cac_time: Option<u32>, // AttrDfsCacTime (ms)
+    pub flags: u32,                // AttrRegRuleFlags
+}
+
+impl RegRule {
+    pub fn has_flag(&self, flag: Nl80211RegRuleFlags) -> bool {
+        self.flags & u32::from(u16::from(flag)) != 0
+    }
+
+    pub fn no_ofdm(&self) -> bool {
+        self.has_flag(Nl80211RegRuleFlags::RrfNoOfdm)
+    }
+
+    pub fn no_cck(&self) -> bool {
+        self.has_flag(Nl80211RegRuleFlags::RrfNoCck)
+    }
+
+    pub fn no_indoor(&self) -> bool {
+        self.has_flag(Nl80211RegRuleFlags::RrfNoIndoor)
+    }
+
+    pub fn no_outdoor(&self) -> bool {
+        self.has_flag(Nl80211RegRuleFlags::RrfNoOutdoor)
+    }
+
+    pub fn dfs(&self) -> bool {
+        self.has_flag(Nl80211RegRuleFlags::RrfDfs)
+    }
+
+    pub fn no_ir(&self) -> bool {
+        self.has_flag(Nl80211RegRuleFlags::RrfNoIr)
+    }
+
+    pub fn auto_bw(&self) -> bool {
+        self.has_flag(Nl80211RegRuleFlags::RrfAutoBw)
+    }
+
+    pub fn no_80mhz(&self) -> bool {
+        self.has_flag(Nl80211RegRuleFlags::RrfNo80mhz)
+    }
+
+    pub fn no_160mhz(&self) -> bool {
+        self.flags & RRF_NO_160MHZ != 0
+    }
+
+    /// Names of the flags set on this rule, in `iw reg get` style.
+    pub fn flag_names(&self) -> Vec<&'static str> {
+        let names = [
+            (Nl80211RegRuleFlags::RrfNoOfdm, "NO-OFDM"),
+            (Nl80211RegRuleFlags::RrfNoCck, "NO-CCK"),
+            (Nl80211RegRuleFlags::RrfNoIndoor, "NO-INDOOR"),
+            (Nl80211RegRuleFlags::RrfNoOutdoor, "NO-OUTDOOR"),
+            (Nl80211RegRuleFlags::RrfDfs, "DFS"),
+            (Nl80211RegRuleFlags::RrfPtpOnly, "PTP-ONLY"),
+            (Nl80211RegRuleFlags::RrfPtmpOnly, "PTMP-ONLY"),
+            (Nl80211RegRuleFlags::RrfNoIr, "NO-IR"),
+            (Nl80211RegRuleFlags::RrfAutoBw, "AUTO-BW"),
+            (Nl80211RegRuleFlags::RrfIrConcurrent, "IR-CONCURRENT"),
+            (Nl80211RegRuleFlags::RrfNoHt40minus, "NO-HT40MINUS"),
+            (Nl80211RegRuleFlags::RrfNoHt40plus, "NO-HT40PLUS"),
+            (Nl80211RegRuleFlags::RrfNo80mhz, "NO-80MHZ"),
+        ];
+        let mut flags: Vec<&'static str> = names
+            .iter()
+            .filter(|(flag, _)| self.has_flag(*flag))
+            .map(|(_, name)| *name)
+            .collect();
+        if self.no_160mhz() {
+            flags.push("NO-160MHZ");
+        }
+        flags
+    }
+
+    pub fn pretty_print(&self) -> String {
+        let mut output = format!(
+            "({} - {} @ {}), ({}, {})",
+            self.start_freq_khz / 1000,
+            self.end_freq_khz / 1000,
+            self.max_bandwidth_khz / 1000,
+            if self.max_antenna_gain == 0 {
+                "N/A".to_string()
+            } else {
+                format!("{}", self.max_antenna_gain / 100)
+            },
+            self.max_eirp / 100
+        );
+        if let Some(cac) = self.dfs_cac_time {
+            output += &format!(", ({} ms)", cac);
+        }
+        let flags = self.flag_names();
+        if !flags.is_empty() {
+            output += &format!(", {}", flags.join(", "));
+        }
+        output
+    }
+}
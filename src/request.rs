@@ -0,0 +1,89 @@
+use crate::attr::Nl80211Attr;
+use crate::cmd::Nl80211Cmd;
+use crate::error::Nl80211Error;
+use neli::consts::genl::NlAttrType;
+use neli::genl::Nlattr;
+use neli::types::{Buffer, GenlBuffer};
+use neli::{Size, ToBytes};
+
+/// A nl80211 command and its attributes, sent with `NtSocket::execute_ack`, `execute_get` or
+/// `execute_dump`.
+///
+/// Attributes that fail to serialize don't interrupt the chain, the first error is returned when
+/// the request is executed.
+#[derive(Debug)]
+pub struct Nl80211Request {
+    pub(crate) name: &'static str,
+    pub(crate) cmd: Nl80211Cmd,
+    attrs: GenlBuffer<Nl80211Attr, Buffer>,
+    error: Option<Nl80211Error>,
+}
+
+impl Nl80211Request {
+    /// `name` is how the command is reported in errors, e.g. "CMD_SET_INTERFACE".
+    pub fn new(name: &'static str, cmd: Nl80211Cmd) -> Self {
+        Nl80211Request {
+            name,
+            cmd,
+            attrs: GenlBuffer::new(),
+            error: None,
+        }
+    }
+
+    /// Add an attribute. Integers, strings and byte slices are sent as they are.
+    pub fn attr<P: Size + ToBytes>(mut self, attr: Nl80211Attr, payload: P) -> Self {
+        match Nlattr::new(false, false, attr, payload) {
+            Ok(attr) => self.attrs.push(attr),
+            Err(err) => self.fail(Nl80211Error::ser(self.name, err)),
+        }
+        self
+    }
+
+    /// Add an attribute only when there is a value for it.
+    pub fn attr_opt<P: Size + ToBytes>(self, attr: Nl80211Attr, payload: Option<P>) -> Self {
+        match payload {
+            Some(payload) => self.attr(attr, payload),
+            None => self,
+        }
+    }
+
+    /// Add a flag attribute, its presence is the value.
+    pub fn flag(self, attr: Nl80211Attr) -> Self {
+        self.attr(attr, Buffer::new())
+    }
+
+    /// Add a nested attribute. Lists like `AttrScanSsids` number their entries from 1, sets of
+    /// flags like `AttrMntrFlags` use the flag as the type and an empty payload.
+    pub fn nested<T, P, I>(mut self, attr: Nl80211Attr, entries: I) -> Self
+    where
+        T: NlAttrType,
+        P: Size + ToBytes,
+        I: IntoIterator<Item = (T, P)>,
+    {
+        let nested = Nlattr::new(false, false, attr, Buffer::new()).and_then(|mut nested| {
+            for (ty, payload) in entries {
+                nested.add_nested_attribute(&Nlattr::new(false, false, ty, payload)?)?;
+            }
+            Ok(nested)
+        });
+        match nested {
+            Ok(nested) => self.attrs.push(nested),
+            Err(err) => self.fail(Nl80211Error::ser(self.name, err)),
+        }
+        self
+    }
+
+    /// Reject the request, for arguments that can only be checked while it's being built.
+    pub(crate) fn fail(&mut self, err: Nl80211Error) {
+        if self.error.is_none() {
+            self.error = Some(err);
+        }
+    }
+
+    pub(crate) fn into_attrs(self) -> Result<GenlBuffer<Nl80211Attr, Buffer>, Nl80211Error> {
+        match self.error {
+            Some(err) => Err(err),
+            None => Ok(self.attrs),
+        }
+    }
+}
@@ -1,253 +1,594 @@
+use std::cell::Cell;
+use std::collections::{HashMap, VecDeque};
 use std::fmt;
+use std::io::Cursor;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::Path;
+use std::time::Duration;
 
 use crate::attr::*;
+use crate::capture::PcapWriter;
+use crate::decode::decode_string;
+use crate::error::Nl80211Error;
+use crate::transaction::{payloads, Transactor, MAX_RESYNCS};
+use crate::transport::{NetlinkTransport, Transport};
 
 extern crate rand;
 
 use neli::consts::rtnl::{Arphrd, IffFlags, Ifla, RtAddrFamily, Rtm};
-use neli::consts::{nl::NlmF, nl::NlmFFlags, socket::NlFamily};
+use neli::consts::{nl::NlmF, nl::NlmFFlags, nl::Nlmsg, socket::NlFamily};
 use neli::nl::{NlPayload, Nlmsghdr};
 use neli::rtnl::{Ifinfomsg, Rtattr};
 use neli::socket::NlSocketHandle;
 use neli::types::RtBuffer;
+use neli::ToBytes;
 use rand::Rng;
 
-pub struct RtSocket {
-    pub(crate) sock: NlSocketHandle,
+pub struct RtSocket<T = NetlinkTransport> {
+    pub(crate) transport: T,
+    pub(crate) transactor: Transactor,
 }
 
-impl fmt::Debug for RtSocket {
+impl<T: fmt::Debug> fmt::Debug for RtSocket<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("NtSocket")
-            .field("sock", &format_args!("<NlSocketHandle>"))
+        f.debug_struct("RtSocket")
+            .field("transport", &self.transport)
+            .field("transactor", &self.transactor)
             .finish()
     }
 }
 
 impl RtSocket {
-    pub fn connect() -> Result<Self, String> {
-        let sock =
-            NlSocketHandle::connect(NlFamily::Route, None, &[]).map_err(|e| e.to_string())?;
-        sock.nonblock().map_err(|e| e.to_string())?;
-        Ok(Self { sock })
+    pub fn connect() -> Result<Self, Nl80211Error> {
+        Ok(RtSocket::from_transport(NetlinkTransport::connect(
+            NlFamily::Route,
+        )?))
+    }
+}
+
+impl<T: Transport> RtSocket<T> {
+    /// Send the requests over another transport, e.g. a `MockTransport` or a `Recorder`.
+    pub fn from_transport(transport: T) -> Self {
+        let transactor = Transactor::new(transport.pid(), libc::NETLINK_ROUTE as u16);
+        RtSocket {
+            transport,
+            transactor,
+        }
+    }
+
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    pub fn transport_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    /// How long a request waits for its reply, None waits forever.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.transactor.timeout()
+    }
+
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.transactor.set_timeout(timeout);
+    }
+
+    pub fn capture(&self) -> Option<&PcapWriter> {
+        self.transactor.capture()
+    }
+
+    /// Write every request and reply to a pcap file, None stops capturing.
+    pub fn set_capture(&mut self, capture: Option<PcapWriter>) {
+        self.transactor.set_capture(capture);
     }
 
-    pub fn get_interface_status(&mut self, interface: u32) -> Result<Operstate, String> {
+    /// Send a rtnetlink request and collect the replies to it, until the ack.
+    pub(crate) fn transact(
+        &mut self,
+        command: &'static str,
+        request: Nlmsghdr<Rtm, Ifinfomsg>,
+    ) -> Result<Vec<Ifinfomsg>, Nl80211Error> {
+        let replies = self.transactor.transact(
+            command,
+            &mut self.transport,
+            request,
+            Nl80211Error::nlmsgerr::<Rtm, Ifinfomsg>,
+        )?;
+        Ok(payloads(replies))
+    }
+
+    pub fn get_interface_status(&mut self, interface: u32) -> Result<Operstate, Nl80211Error> {
+        let replies = self.transact("RTM_GETLINK", getlink_request(interface))?;
+        match replies.first() {
+            Some(msg) => parse_operstate(msg),
+            None => Ok(Operstate::Unknown),
+        }
+    }
+
+    pub fn set_interface_mac_random(&mut self, interface_index: u32) -> Result<(), Nl80211Error> {
+        self.transact(
+            "RTM_NEWLINK",
+            set_mac_request(interface_index, &generate_valid_mac()),
+        )?;
+        Ok(())
+    }
+
+    pub fn set_interface_mac(
+        &mut self,
+        interface_index: u32,
+        mac: &[u8; 6],
+    ) -> Result<(), Nl80211Error> {
+        self.transact("RTM_NEWLINK", set_mac_request(interface_index, mac))?;
+        Ok(())
+    }
+
+    pub fn set_interface_up(&mut self, interface_index: u32) -> Result<(), Nl80211Error> {
+        self.transact("RTM_NEWLINK", set_up_request(interface_index, true))?;
+        Ok(())
+    }
+
+    pub fn set_interface_down(&mut self, interface_index: u32) -> Result<(), Nl80211Error> {
+        self.transact("RTM_NEWLINK", set_up_request(interface_index, false))?;
+        Ok(())
+    }
+}
+
+impl<T: AsRawFd> AsRawFd for RtSocket<T> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.transport.as_raw_fd()
+    }
+}
+
+/// RTM_GETLINK for a single interface
+pub(crate) fn getlink_request(interface_index: u32) -> Nlmsghdr<Rtm, Ifinfomsg> {
+    Nlmsghdr::new(
+        None,
+        Rtm::Getlink,
+        NlmFFlags::new(&[NlmF::Request, NlmF::Ack]),
+        None,
+        None,
+        NlPayload::Payload(Ifinfomsg::new(
+            RtAddrFamily::Packet,
+            Arphrd::Netrom,
+            interface_index.try_into().unwrap(),
+            IffFlags::empty(),
+            IffFlags::empty(),
+            RtBuffer::new(),
+        )),
+    )
+}
+
+/// RTM_NEWLINK setting the hardware address
+pub(crate) fn set_mac_request(interface_index: u32, mac: &[u8; 6]) -> Nlmsghdr<Rtm, Ifinfomsg> {
+    let mut rtattr = RtBuffer::new();
+    rtattr.push(Rtattr::new(None, Ifla::Address, &mac[..]).unwrap());
+    Nlmsghdr::new(
+        None,
+        Rtm::Newlink,
+        NlmFFlags::new(&[NlmF::Request, NlmF::Ack]),
+        None,
+        None,
+        NlPayload::Payload(Ifinfomsg::new(
+            RtAddrFamily::Unspecified,
+            Arphrd::None,
+            interface_index.try_into().unwrap(),
+            IffFlags::empty(),
+            IffFlags::empty(),
+            rtattr,
+        )),
+    )
+}
+
+/// RTM_NEWLINK setting or clearing IFF_UP
+pub(crate) fn set_up_request(interface_index: u32, up: bool) -> Nlmsghdr<Rtm, Ifinfomsg> {
+    let index = interface_index.try_into().unwrap();
+    let ifinfomsg = if up {
+        Ifinfomsg::up(
+            RtAddrFamily::Unspecified,
+            Arphrd::None,
+            index,
+            RtBuffer::new(),
+        )
+    } else {
+        Ifinfomsg::down(
+            RtAddrFamily::Unspecified,
+            Arphrd::None,
+            index,
+            RtBuffer::new(),
+        )
+    };
+    Nlmsghdr::new(
+        None,
+        Rtm::Newlink,
+        NlmFFlags::new(&[NlmF::Request, NlmF::Ack]),
+        None,
+        None,
+        NlPayload::Payload(ifinfomsg),
+    )
+}
+
+pub(crate) fn parse_operstate(msg: &Ifinfomsg) -> Result<Operstate, Nl80211Error> {
+    let handle = msg.rtattrs.get_attr_handle();
+    let operstate = handle
+        .get_attr_payload_as_with_len::<Vec<u8>>(Ifla::Operstate)
+        .map_err(|_| Nl80211Error::Parse("RTM_GETLINK missing IFLA_OPERSTATE".to_string()))?;
+    operstate
+        .first()
+        .map(|state| Operstate::from_u8(*state))
+        .ok_or(Nl80211Error::Parse(
+            "RTM_GETLINK empty IFLA_OPERSTATE".to_string(),
+        ))
+}
+
+/// The state of a link as reported by RTM_NEWLINK
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkInfo {
+    pub index: u32,                   // ifi_index
+    pub name: Option<String>,         // IFLA_IFNAME
+    pub mac: Option<[u8; 6]>,         // IFLA_ADDRESS, only for ethernet-like addresses
+    pub operstate: Option<Operstate>, // IFLA_OPERSTATE
+    pub carrier: Option<bool>,        // IFLA_CARRIER
+    pub flags: u32,                   // ifi_flags, IFF_*
+    pub wireless: bool,               // Has a /sys/class/net/<name>/phy80211 link
+}
+
+impl LinkInfo {
+    fn from_ifinfomsg(msg: &Ifinfomsg) -> LinkInfo {
+        let handle = msg.rtattrs.get_attr_handle();
+        let name = handle
+            .get_attribute(Ifla::Ifname)
+            .and_then(|attr| decode_string(attr.rta_payload.as_ref()).ok());
+        let wireless = name
+            .as_ref()
+            .map(|name| {
+                Path::new("/sys/class/net")
+                    .join(name)
+                    .join("phy80211")
+                    .exists()
+            })
+            .unwrap_or(false);
+        LinkInfo {
+            index: msg.ifi_index as u32,
+            name,
+            mac: handle
+                .get_attr_payload_as_with_len::<Vec<u8>>(Ifla::Address)
+                .ok()
+                .and_then(|mac| mac.try_into().ok()),
+            operstate: handle
+                .get_attr_payload_as::<u8>(Ifla::Operstate)
+                .ok()
+                .map(Operstate::from_u8),
+            carrier: handle
+                .get_attr_payload_as::<u8>(Ifla::Carrier)
+                .ok()
+                .map(|carrier| carrier != 0),
+            flags: iff_bits(&msg.ifi_flags),
+            wireless,
+        }
+    }
+
+    /// IFF_UP, the interface is administratively up
+    pub fn is_up(&self) -> bool {
+        self.flags & libc::IFF_UP as u32 != 0
+    }
+
+    /// IFF_RUNNING, the interface is operationally up
+    pub fn is_running(&self) -> bool {
+        self.flags & libc::IFF_RUNNING as u32 != 0
+    }
+}
+
+/// A change to a link, as seen by a `LinkMonitor`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkEvent {
+    /// A new link appeared
+    Created(LinkInfo),
+    /// The link was removed, with the last known state
+    Deleted(LinkInfo),
+    Renamed {
+        index: u32,
+        old_name: Option<String>,
+        new_name: Option<String>,
+    },
+    OperstateChanged {
+        index: u32,
+        old: Option<Operstate>,
+        new: Option<Operstate>,
+    },
+    CarrierChanged {
+        index: u32,
+        carrier: Option<bool>,
+    },
+    /// The IFF_* flags changed (e.g. up/down, promiscuous)
+    FlagsChanged {
+        index: u32,
+        old: u32,
+        new: u32,
+    },
+    MacChanged {
+        index: u32,
+        old: Option<[u8; 6]>,
+        new: Option<[u8; 6]>,
+    },
+}
+
+impl LinkEvent {
+    /// Index of the link the event is about
+    pub fn index(&self) -> u32 {
+        match self {
+            LinkEvent::Created(link) | LinkEvent::Deleted(link) => link.index,
+            LinkEvent::Renamed { index, .. }
+            | LinkEvent::OperstateChanged { index, .. }
+            | LinkEvent::CarrierChanged { index, .. }
+            | LinkEvent::FlagsChanged { index, .. }
+            | LinkEvent::MacChanged { index, .. } => *index,
+        }
+    }
+}
+
+/// A route socket subscribed to RTNLGRP_LINK. It keeps the last known state of every link and
+/// turns the kernel's RTM_NEWLINK / RTM_DELLINK notifications into `LinkEvent`s.
+pub struct LinkMonitor {
+    sock: NlSocketHandle,
+    links: HashMap<u32, LinkInfo>,
+    pending: VecDeque<LinkEvent>,
+    wireless_only: bool,
+    nonblocking: Cell<bool>,
+}
+
+impl fmt::Debug for LinkMonitor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LinkMonitor")
+            .field("sock", &format_args!("<NlSocketHandle>"))
+            .field("links", &self.links)
+            .field("pending", &self.pending)
+            .field("wireless_only", &self.wireless_only)
+            .field("nonblocking", &self.nonblocking.get())
+            .finish()
+    }
+}
+
+impl LinkMonitor {
+    /// Join the link group and load the current links, so only changes after this call are
+    /// reported. With `wireless_only` set, events for non-wireless links are dropped.
+    pub fn connect(wireless_only: bool) -> Result<Self, Nl80211Error> {
+        let sock = NlSocketHandle::connect(NlFamily::Route, None, &[])
+            .map_err(|err| Nl80211Error::io("connect", err))?;
+        sock.add_mcast_membership(&[libc::RTNLGRP_LINK])
+            .map_err(|err| Nl80211Error::io("add_mcast_membership", err))?;
+
+        let mut monitor = LinkMonitor {
+            sock,
+            links: HashMap::new(),
+            pending: VecDeque::new(),
+            wireless_only,
+            nonblocking: Cell::new(false),
+        };
+        monitor.dump_links()?;
+        Ok(monitor)
+    }
+
+    /// Dump every link into the cache without generating events. Notifications that arrive
+    /// during the dump are applied the same way.
+    fn dump_links(&mut self) -> Result<(), Nl80211Error> {
         let nlmsg = Nlmsghdr::new(
             None,
             Rtm::Getlink,
-            NlmFFlags::new(&[NlmF::Request, NlmF::Ack]),
+            NlmFFlags::new(&[NlmF::Request, NlmF::Dump]),
             None,
             None,
             NlPayload::Payload(Ifinfomsg::new(
-                RtAddrFamily::Packet,
-                Arphrd::Netrom,
-                interface.try_into().unwrap(),
+                RtAddrFamily::Unspecified,
+                Arphrd::None,
+                0,
                 IffFlags::empty(),
                 IffFlags::empty(),
                 RtBuffer::new(),
             )),
         );
-        self.sock.send(nlmsg).unwrap();
-
-        let iter = self.sock.iter::<Rtm, Ifinfomsg>(false);
-        for msg in iter {
-            match msg {
-                Ok(p) => match p.get_payload() {
-                    Ok(p) => {
-                        let handle = p.rtattrs.get_attr_handle();
-                        if let Ok(operstate) =
-                            handle.get_attr_payload_as_with_len::<Vec<u8>>(Ifla::Operstate)
-                        {
-                            return Ok(Operstate::from_u8(operstate[0]));
-                        } else {
-                            return Err("Ethernet address not found".to_string());
-                        }
-                    }
-                    Err(p) => {
-                        if format!("{}", p) != "This packet does not have a payload" {
-                            return Err(format!("(GET_INTERFACE_STATUS 1) {}", p));
-                        }
+        self.sock
+            .send(nlmsg)
+            .map_err(|err| Nl80211Error::ser("RTM_GETLINK", err))?;
+
+        loop {
+            let msg = self
+                .sock
+                .recv::<Rtm, Ifinfomsg>()
+                .map_err(|err| Nl80211Error::netlink("RTM_GETLINK", err))?;
+            let Some(msg) = msg else {
+                return Ok(());
+            };
+            if u16::from(msg.nl_type) == u16::from(Nlmsg::Done) {
+                return Ok(());
+            }
+            if let NlPayload::Payload(payload) = &msg.nl_payload {
+                let link = LinkInfo::from_ifinfomsg(payload);
+                match msg.nl_type {
+                    Rtm::Newlink if link.wireless || !self.wireless_only => {
+                        self.links.insert(link.index, link);
                     }
-                },
-                Err(p) => {
-                    if format!("{}", p) != "This packet does not have a payload" && format!("{}", p) != "Netlink failure due to error: Wrapped IO error: No buffer space available (os error 105)" {
-                        return Err(format!("(GET_INTERFACE_STATUS 2) {}", p));
+                    Rtm::Dellink => {
+                        self.links.remove(&link.index);
                     }
+                    _ => (),
                 }
             }
         }
-        Ok(Operstate::Unknown)
     }
 
-    pub fn set_interface_mac_random(&mut self, interface_index: u32) -> Result<(), String> {
-        let mut rtattr: RtBuffer<Ifla, neli::types::Buffer> = RtBuffer::new();
-        let mac = generate_valid_mac();
-        rtattr.push(Rtattr::new(None, Ifla::Address, &mac[..]).unwrap());
+    /// The last known state of the monitored links, keyed by index.
+    pub fn links(&self) -> &HashMap<u32, LinkInfo> {
+        &self.links
+    }
 
-        self.sock
-            .send(Nlmsghdr::new(
-                None,
-                Rtm::Newlink,
-                NlmFFlags::new(&[NlmF::Request, NlmF::Ack]),
-                None,
-                None,
-                NlPayload::Payload(Ifinfomsg::new(
-                    RtAddrFamily::Unspecified,
-                    Arphrd::None,
-                    interface_index.try_into().unwrap(),
-                    IffFlags::empty(),
-                    IffFlags::empty(),
-                    rtattr,
-                )),
-            ))
-            .unwrap();
-
-        let iter = self.sock.iter::<Rtm, Ifinfomsg>(false);
-        for msg in iter {
-            match msg {
-                Ok(p) => match p.get_payload() {
-                    Ok(_p) => {}
-                    Err(p) => {
-                        if format!("{}", p) != "This packet does not have a payload" {
-                            return Err(format!("{}", p));
-                        }
-                    }
-                },
-                Err(p) => {
-                    if format!("{}", p) != "This packet does not have a payload" {
-                        return Err(format!("{}", p));
-                    }
-                }
-            }
+    /// Switch the socket between blocking (the default) and non-blocking reads.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> Result<(), Nl80211Error> {
+        if nonblocking {
+            self.sock.nonblock()
+        } else {
+            self.sock.block()
         }
-
+        .map_err(|err| Nl80211Error::io("fcntl", err))?;
+        self.nonblocking.set(nonblocking);
         Ok(())
     }
 
-    pub fn set_interface_mac(&mut self, interface_index: u32, mac: &[u8; 6]) -> Result<(), String> {
-        let mut rtattr = RtBuffer::new();
-        rtattr.push(Rtattr::new(None, Ifla::Address, &mac[..]).unwrap());
-
-        self.sock
-            .send(Nlmsghdr::new(
-                None,
-                Rtm::Newlink,
-                NlmFFlags::new(&[NlmF::Request, NlmF::Ack]),
-                None,
-                None,
-                NlPayload::Payload(Ifinfomsg::new(
-                    RtAddrFamily::Unspecified,
-                    Arphrd::None,
-                    interface_index.try_into().unwrap(),
-                    IffFlags::empty(),
-                    IffFlags::empty(),
-                    rtattr,
-                )),
-            ))
-            .unwrap();
-
-        let iter = self.sock.iter::<Rtm, Ifinfomsg>(false);
-        for msg in iter {
-            match msg {
-                Ok(p) => match p.get_payload() {
-                    Ok(_p) => {}
-                    Err(p) => {
-                        if format!("{}", p) != "This packet does not have a payload" {
-                            return Err(format!("{}", p));
-                        }
-                    }
-                },
-                Err(p) => {
-                    if format!("{}", p) != "This packet does not have a payload" {
-                        return Err(format!("{}", p));
+    /// Wait for the next event, or return None if the socket is non-blocking and nothing is pending.
+    /// If the receive buffer overflowed, the links are dumped again and the changes that were
+    /// missed are reported as events.
+    pub fn next_event(&mut self) -> Result<Option<LinkEvent>, Nl80211Error> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Ok(Some(event));
+            }
+            let msg = match self.sock.recv::<Rtm, Ifinfomsg>() {
+                Ok(msg) => msg,
+                Err(err) => {
+                    let err = Nl80211Error::netlink("recv", err);
+                    if err.errno() != Some(libc::ENOBUFS) {
+                        return Err(err);
                     }
+                    self.resync()?;
+                    continue;
+                }
+            };
+            let Some(msg) = msg else {
+                return Ok(None);
+            };
+            if let NlPayload::Payload(payload) = &msg.nl_payload {
+                match msg.nl_type {
+                    Rtm::Newlink => self.link_changed(LinkInfo::from_ifinfomsg(payload)),
+                    Rtm::Dellink => self.link_removed(payload.ifi_index as u32),
+                    _ => (),
                 }
             }
         }
-
-        Ok(())
     }
 
-    pub fn set_interface_up(&mut self, interface_index: u32) -> Result<(), String> {
-        self.sock
-            .send(Nlmsghdr::new(
-                None,
-                Rtm::Newlink,
-                NlmFFlags::new(&[NlmF::Request, NlmF::Ack]),
-                None,
-                None,
-                NlPayload::Payload(Ifinfomsg::up(
-                    RtAddrFamily::Unspecified,
-                    Arphrd::None,
-                    interface_index.try_into().unwrap(),
-                    RtBuffer::new(),
-                )),
-            ))
-            .unwrap();
-
-        let iter = self.sock.iter::<Rtm, Ifinfomsg>(false);
-        for msg in iter {
-            match msg {
-                Ok(p) => match p.get_payload() {
-                    Ok(_p) => {}
-                    Err(p) => {
-                        if format!("{}", p) != "This packet does not have a payload" {
-                            return Err(format!("{}", p));
-                        }
-                    }
-                },
-                Err(p) => {
-                    if format!("{}", p) != "This packet does not have a payload" {
-                        return Err(format!("{}", p));
-                    }
+    /// Notifications overflowed the receive buffer, so dump the links again and queue the
+    /// difference to the last known state as events.
+    fn resync(&mut self) -> Result<(), Nl80211Error> {
+        // The dump has to block, otherwise it ends at the first empty read.
+        let nonblocking = self.nonblocking.get();
+        if nonblocking {
+            self.set_nonblocking(false)?;
+        }
+        let known = std::mem::take(&mut self.links);
+        let mut resyncs = 0;
+        let result = loop {
+            match self.dump_links() {
+                Err(err) if err.errno() == Some(libc::ENOBUFS) && resyncs < MAX_RESYNCS => {
+                    resyncs += 1;
+                    self.links.clear();
                 }
+                result => break result,
             }
+        };
+        let current = std::mem::replace(&mut self.links, known);
+        if nonblocking {
+            self.set_nonblocking(true)?;
         }
+        result?;
 
+        let removed: Vec<u32> = self
+            .links
+            .keys()
+            .filter(|index| !current.contains_key(index))
+            .copied()
+            .collect();
+        for index in removed {
+            self.link_removed(index);
+        }
+        for link in current.into_values() {
+            self.link_changed(link);
+        }
         Ok(())
     }
 
-    pub fn set_interface_down(&mut self, interface_index: u32) -> Result<(), String> {
-        let nlmsg = Nlmsghdr::new(
-            None,
-            Rtm::Newlink,
-            NlmFFlags::new(&[NlmF::Request, NlmF::Ack]),
-            None,
-            None,
-            NlPayload::Payload(Ifinfomsg::down(
-                RtAddrFamily::Unspecified,
-                Arphrd::None,
-                interface_index.try_into().unwrap(),
-                RtBuffer::new(),
-            )),
-        );
-        self.sock.send(nlmsg).unwrap();
-
-        let iter = self.sock.iter::<Rtm, Ifinfomsg>(false);
-        for msg in iter {
-            match msg {
-                Ok(p) => match p.get_payload() {
-                    Ok(_p) => {}
-                    Err(p) => {
-                        if format!("{}", p) != "This packet does not have a payload" {
-                            return Err(format!("{}", p));
-                        }
-                    }
-                },
-                Err(p) => {
-                    if format!("{}", p) != "This packet does not have a payload" {
-                        return Err(format!("{}", p));
-                    }
-                }
+    fn link_changed(&mut self, link: LinkInfo) {
+        let Some(old) = self.links.get(&link.index) else {
+            if link.wireless || !self.wireless_only {
+                self.links.insert(link.index, link.clone());
+                self.pending.push_back(LinkEvent::Created(link));
             }
+            return;
+        };
+
+        let index = link.index;
+        if old.name != link.name {
+            self.pending.push_back(LinkEvent::Renamed {
+                index,
+                old_name: old.name.clone(),
+                new_name: link.name.clone(),
+            });
+        }
+        if old.operstate != link.operstate {
+            self.pending.push_back(LinkEvent::OperstateChanged {
+                index,
+                old: old.operstate.clone(),
+                new: link.operstate.clone(),
+            });
         }
+        if old.carrier != link.carrier {
+            self.pending.push_back(LinkEvent::CarrierChanged {
+                index,
+                carrier: link.carrier,
+            });
+        }
+        if old.flags != link.flags {
+            self.pending.push_back(LinkEvent::FlagsChanged {
+                index,
+                old: old.flags,
+                new: link.flags,
+            });
+        }
+        if old.mac != link.mac {
+            self.pending.push_back(LinkEvent::MacChanged {
+                index,
+                old: old.mac,
+                new: link.mac,
+            });
+        }
+        // Once a link is known to be wireless it stays that way, the sysfs lookup can race a rename.
+        let wireless = link.wireless || old.wireless;
+        self.links.insert(index, LinkInfo { wireless, ..link });
+    }
 
-        Ok(())
+    fn link_removed(&mut self, index: u32) {
+        if let Some(link) = self.links.remove(&index) {
+            self.pending.push_back(LinkEvent::Deleted(link));
+        }
+    }
+}
+
+impl Iterator for LinkMonitor {
+    type Item = Result<LinkEvent, Nl80211Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event().transpose()
+    }
+}
+
+impl AsRawFd for LinkMonitor {
+    fn as_raw_fd(&self) -> RawFd {
+        self.sock.as_raw_fd()
+    }
+}
+
+/// neli doesn't expose the raw bitmask of a flag set, so serialize it back.
+fn iff_bits(flags: &IffFlags) -> u32 {
+    let mut buffer = Cursor::new(Vec::new());
+    if flags.to_bytes(&mut buffer).is_err() {
+        return 0;
     }
+    buffer
+        .into_inner()
+        .get(..4)
+        .map(|bits| u32::from_ne_bytes([bits[0], bits[1], bits[2], bits[3]]))
+        .unwrap_or(0)
 }
 
-fn generate_valid_mac() -> [u8; 6] {
+pub(crate) fn generate_valid_mac() -> [u8; 6] {
     let mut rng = rand::thread_rng();
 
     loop {
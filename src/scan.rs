@@ -0,0 +1,93 @@
+use crate::attr::{Nl80211BssStatus, Nl80211ScanFlags};
+use crate::channels::chan_from_frequency;
+use crate::ie::{parse_information_elements, InformationElement};
+
+/// Parameters for a `CMD_TRIGGER_SCAN` request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanRequest {
+    pub ssids: Vec<Vec<u8>>, // SSIDs to probe for, an empty SSID is the wildcard
+    pub frequencies: Vec<u32>, // Frequencies to scan (MHz), empty scans every enabled channel
+    pub flags: Vec<Nl80211ScanFlags>, // Scan flags
+}
+
+impl Default for ScanRequest {
+    /// An active scan with the wildcard SSID on every channel.
+    fn default() -> Self {
+        ScanRequest {
+            ssids: vec![Vec::new()],
+            frequencies: Vec::new(),
+            flags: Vec::new(),
+        }
+    }
+}
+
+impl ScanRequest {
+    /// A passive scan (no probe requests sent) on every channel.
+    pub fn passive() -> Self {
+        ScanRequest {
+            ssids: Vec::new(),
+            frequencies: Vec::new(),
+            flags: Vec::new(),
+        }
+    }
+
+    /// Combines the requested flags into the u32 bitmask used by `AttrScanFlags`.
+    pub fn flags_as_u32(&self) -> u32 {
+        self.flags
+            .iter()
+            .fold(0, |acc, flag| acc | u32::from(u16::from(*flag)))
+    }
+}
+
+/// A BSS reported by `CMD_GET_SCAN`
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Bss {
+    pub bssid: [u8; 6],                        // BssBssid
+    pub frequency: u32,                        // BssFrequency (MHz)
+    pub channel: u32,                          // Derived from frequency
+    pub tsf: Option<u64>,                      // BssTsf
+    pub beacon_interval: Option<u16>,          // BssBeaconInterval (TUs)
+    pub capability: Option<u16>,               // BssCapability
+    pub signal_mbm: Option<i32>,               // BssSignalMbm (mBm, dBm * 100)
+    pub signal_unspec: Option<u8>,             // BssSignalUnspec (0 - 100)
+    pub seen_ms_ago: Option<u32>,              // BssSeenMsAgo
+    pub status: Option<Nl80211BssStatus>,      // BssStatus
+    pub information_elements: Option<Vec<u8>>, // BssInformationElements
+    pub beacon_ies: Option<Vec<u8>>,           // BssBeaconIes
+}
+
+impl Bss {
+    /// Signal strength in dBm, if the driver reports it in mBm.
+    pub fn signal_dbm(&self) -> Option<i32> {
+        self.signal_mbm.map(|mbm| mbm / 100)
+    }
+
+    /// Decoded elements, preferring the most recent frame (probe response) over the beacon.
+    pub fn ies(&self) -> Vec<InformationElement> {
+        self.information_elements
+            .as_ref()
+            .or(self.beacon_ies.as_ref())
+            .map(|ies| parse_information_elements(ies))
+            .unwrap_or_default()
+    }
+
+    pub fn ssid(&self) -> Option<Vec<u8>> {
+        self.ies().into_iter().find_map(|ie| match ie {
+            InformationElement::Ssid(ssid) => Some(ssid),
+            _ => None,
+        })
+    }
+
+    pub fn bssid_as_string(&self) -> String {
+        self.bssid
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<Vec<String>>()
+            .join(":")
+    }
+
+    pub(crate) fn set_frequency(&mut self, frequency: u32) {
+        self.frequency = frequency;
+        self.channel = chan_from_frequency(frequency);
+    }
+}
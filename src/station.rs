@@ -0,0 +1,197 @@
+use crate::attr::{Nl80211ChanWidth, Nl80211StaFlags};
+
+/// A station (peer) as reported by `CMD_GET_STATION`
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct StationInfo {
+    pub mac: [u8; 6],                       // AttrMac
+    pub interface_index: Option<u32>,       // AttrIfindex
+    pub inactive_time: Option<u32>,         // StaInfoInactiveTime (ms)
+    pub rx_bytes: Option<u64>,              // StaInfoRxBytes64 / StaInfoRxBytes
+    pub tx_bytes: Option<u64>,              // StaInfoTxBytes64 / StaInfoTxBytes
+    pub rx_packets: Option<u32>,            // StaInfoRxPackets
+    pub tx_packets: Option<u32>,            // StaInfoTxPackets
+    pub tx_retries: Option<u32>,            // StaInfoTxRetries
+    pub tx_failed: Option<u32>,             // StaInfoTxFailed
+    pub rx_drop_misc: Option<u64>,          // StaInfoRxDropMisc
+    pub beacon_loss: Option<u32>,           // StaInfoBeaconLoss
+    pub beacon_rx: Option<u64>,             // StaInfoBeaconRx
+    pub signal: Option<i8>,                 // StaInfoSignal (dBm)
+    pub signal_avg: Option<i8>,             // StaInfoSignalAvg (dBm)
+    pub beacon_signal_avg: Option<i8>,      // StaInfoBeaconSignalAvg (dBm)
+    pub chain_signal: Vec<i8>,              // StaInfoChainSignal (dBm per chain)
+    pub chain_signal_avg: Vec<i8>,          // StaInfoChainSignalAvg (dBm per chain)
+    pub tx_bitrate: Option<RateInfo>,       // StaInfoTxBitrate
+    pub rx_bitrate: Option<RateInfo>,       // StaInfoRxBitrate
+    pub expected_throughput: Option<u32>,   // StaInfoExpectedThroughput (kbps)
+    pub connected_time: Option<u32>,        // StaInfoConnectedTime (s)
+    pub flags: Option<StationFlags>,        // StaInfoStaFlags
+    pub bss_param: Option<StationBssParam>, // StaInfoBssParam
+    pub tid_stats: Vec<TidStats>,           // StaInfoTidStats
+}
+
+impl StationInfo {
+    pub fn new(mac: [u8; 6]) -> StationInfo {
+        StationInfo {
+            mac,
+            ..Default::default()
+        }
+    }
+
+    pub fn mac_as_string(&self) -> String {
+        self.mac
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<Vec<String>>()
+            .join(":")
+    }
+}
+
+/// Bitrate information decoded from a nested `Nl80211RateInfo` attribute
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateInfo {
+    pub bitrate: Option<u32>, // RateInfoBitrate32 / RateInfoBitrate (100 kbps)
+    pub mcs: Option<u8>,      // RateInfoMcs (HT)
+    pub vht_mcs: Option<u8>,  // RateInfoVhtMcs
+    pub vht_nss: Option<u8>,  // RateInfoVhtNss
+    pub he_mcs: Option<u8>,   // RateInfoHeMcs
+    pub he_nss: Option<u8>,   // RateInfoHeNss
+    pub he_gi: Option<u8>,    // RateInfoHeGi (0: 0.8us, 1: 1.6us, 2: 3.2us)
+    pub he_dcm: Option<u8>,   // RateInfoHeDcm
+    pub eht_mcs: Option<u8>,  // RateInfoEhtMcs
+    pub eht_nss: Option<u8>,  // RateInfoEhtNss
+    pub eht_gi: Option<u8>,   // RateInfoEhtGi
+    pub short_gi: bool,       // RateInfoShortGi (HT/VHT 0.4us guard interval)
+    pub width: Nl80211ChanWidth, // RateInfo*Width flags
+}
+
+impl Default for RateInfo {
+    fn default() -> Self {
+        RateInfo {
+            bitrate: None,
+            mcs: None,
+            vht_mcs: None,
+            vht_nss: None,
+            he_mcs: None,
+            he_nss: None,
+            he_gi: None,
+            he_dcm: None,
+            eht_mcs: None,
+            eht_nss: None,
+            eht_gi: None,
+            short_gi: false,
+            width: Nl80211ChanWidth::ChanWidth20Noht,
+        }
+    }
+}
+
+impl RateInfo {
+    /// Bitrate in Mbps
+    pub fn bitrate_mbps(&self) -> Option<f32> {
+        self.bitrate.map(|rate| rate as f32 / 10.0)
+    }
+
+    /// True if no HT/VHT/HE/EHT MCS was reported
+    pub fn is_legacy(&self) -> bool {
+        self.mcs.is_none()
+            && self.vht_mcs.is_none()
+            && self.he_mcs.is_none()
+            && self.eht_mcs.is_none()
+    }
+
+    /// Number of spatial streams, HT MCS indexes encode it as groups of eight
+    pub fn nss(&self) -> Option<u8> {
+        self.eht_nss
+            .or(self.he_nss)
+            .or(self.vht_nss)
+            .or(self.mcs.map(|mcs| mcs / 8 + 1))
+    }
+
+    pub fn width_mhz(&self) -> u32 {
+        match self.width {
+            Nl80211ChanWidth::ChanWidth5 => 5,
+            Nl80211ChanWidth::ChanWidth10 => 10,
+            Nl80211ChanWidth::ChanWidth40 => 40,
+            Nl80211ChanWidth::ChanWidth80 => 80,
+            Nl80211ChanWidth::ChanWidth80p80 | Nl80211ChanWidth::ChanWidth160 => 160,
+            Nl80211ChanWidth::ChanWidth320 => 320,
+            _ => 20,
+        }
+    }
+}
+
+/// `struct nl80211_sta_flag_update`, only flags present in `mask` are meaningful
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct StationFlags {
+    pub mask: u32,
+    pub set: u32,
+}
+
+impl StationFlags {
+    pub fn from_bytes(bytes: &[u8]) -> Option<StationFlags> {
+        if bytes.len() < 8 {
+            return None;
+        }
+        Some(StationFlags {
+            mask: u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            set: u32::from_ne_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]),
+        })
+    }
+
+    /// `None` if the kernel didn't report this flag
+    pub fn get(&self, flag: Nl80211StaFlags) -> Option<bool> {
+        let bit = 1u32 << u16::from(flag);
+        if self.mask & bit == 0 {
+            None
+        } else {
+            Some(self.set & bit != 0)
+        }
+    }
+
+    pub fn authorized(&self) -> Option<bool> {
+        self.get(Nl80211StaFlags::StaFlagAuthorized)
+    }
+
+    pub fn authenticated(&self) -> Option<bool> {
+        self.get(Nl80211StaFlags::StaFlagAuthenticated)
+    }
+
+    pub fn associated(&self) -> Option<bool> {
+        self.get(Nl80211StaFlags::StaFlagAssociated)
+    }
+
+    pub fn short_preamble(&self) -> Option<bool> {
+        self.get(Nl80211StaFlags::StaFlagShortPreamble)
+    }
+
+    pub fn wme(&self) -> Option<bool> {
+        self.get(Nl80211StaFlags::StaFlagWme)
+    }
+
+    pub fn mfp(&self) -> Option<bool> {
+        self.get(Nl80211StaFlags::StaFlagMfp)
+    }
+
+    pub fn tdls_peer(&self) -> Option<bool> {
+        self.get(Nl80211StaFlags::StaFlagTdlsPeer)
+    }
+}
+
+/// BSS parameters of the station's link, from `StaInfoBssParam`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct StationBssParam {
+    pub cts_protection: bool,
+    pub short_preamble: bool,
+    pub short_slot_time: bool,
+    pub dtim_period: Option<u8>,
+    pub beacon_interval: Option<u16>,
+}
+
+/// Per-TID counters from `StaInfoTidStats`, TID 16 holds non-QoS traffic
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TidStats {
+    pub tid: u16,
+    pub rx_msdu: Option<u64>,
+    pub tx_msdu: Option<u64>,
+    pub tx_msdu_retries: Option<u64>,
+    pub tx_msdu_failed: Option<u64>,
+}
@@ -0,0 +1,72 @@
+use crate::channels::{BandList, ChannelData};
+
+/// Survey data for a single frequency as reported by `CMD_GET_SURVEY`
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SurveyInfo {
+    pub frequency: u32,             // SurveyInfoFrequency (MHz)
+    pub channel: u32,               // Derived from frequency
+    pub noise: Option<i8>,          // SurveyInfoNoise (dBm)
+    pub in_use: bool,               // SurveyInfoInUse
+    pub time: Option<u64>,          // SurveyInfoTime (ms the radio was on channel)
+    pub time_busy: Option<u64>,     // SurveyInfoTimeBusy (ms the channel was sensed busy)
+    pub time_ext_busy: Option<u64>, // SurveyInfoTimeExtBusy (ms the extension channel was busy)
+    pub time_rx: Option<u64>,       // SurveyInfoTimeRx (ms spent receiving)
+    pub time_tx: Option<u64>,       // SurveyInfoTimeTx (ms spent transmitting)
+    pub time_scan: Option<u64>,     // SurveyInfoTimeScan (ms spent scanning)
+}
+
+impl SurveyInfo {
+    /// Percentage of the on-channel time the channel was busy.
+    pub fn utilization(&self) -> Option<f32> {
+        match (self.time, self.time_busy) {
+            (Some(time), Some(busy)) if time > 0 => Some(busy as f32 * 100.0 / time as f32),
+            _ => None,
+        }
+    }
+}
+
+/// A channel of the phy joined with its survey results
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelUtilization {
+    pub channel: ChannelData,
+    pub survey: SurveyInfo,
+    pub utilization: Option<f32>, // Percentage busy
+}
+
+/// Join survey results with the phy's channel list and rank them, least utilized first.
+///
+/// Channels without survey data or utilization figures are ranked last, ties are broken on the noise floor.
+pub fn rank_channels(surveys: &[SurveyInfo], bands: &[BandList]) -> Vec<ChannelUtilization> {
+    let mut ranked: Vec<ChannelUtilization> = bands
+        .iter()
+        .flat_map(|band| band.channels.iter())
+        .map(|channel| {
+            let survey = surveys
+                .iter()
+                .find(|survey| survey.frequency == channel.frequency)
+                .cloned()
+                .unwrap_or_else(|| SurveyInfo {
+                    frequency: channel.frequency,
+                    channel: channel.channel,
+                    ..Default::default()
+                });
+            ChannelUtilization {
+                channel: channel.clone(),
+                utilization: survey.utilization(),
+                survey,
+            }
+        })
+        .collect();
+
+    ranked.sort_by(|a, b| {
+        let util_a = a.utilization.unwrap_or(f32::MAX);
+        let util_b = b.utilization.unwrap_or(f32::MAX);
+        util_a.total_cmp(&util_b).then_with(|| {
+            a.survey
+                .noise
+                .unwrap_or(i8::MAX)
+                .cmp(&b.survey.noise.unwrap_or(i8::MAX))
+        })
+    });
+    ranked
+}
@@ -0,0 +1,450 @@
+use crate::capture::{describe_datagram, PcapWriter};
+use crate::cmd::Nl80211Cmd;
+use crate::error::Nl80211Error;
+use crate::transport::Transport;
+use neli::consts::nl::{NlType, Nlmsg};
+use neli::err::Nlmsgerr;
+use neli::nl::{NlPayload, Nlmsghdr};
+use neli::{FromBytes, ToBytes};
+use rand::Rng;
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, Cursor};
+use std::time::{Duration, Instant};
+
+/// How long a request waits for its reply unless the socket is given another timeout.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How many times a request is resent after the socket's receive buffer overflowed.
+pub(crate) const MAX_RESYNCS: usize = 3;
+
+/// Large enough for the biggest datagram the kernel builds for a dump.
+const RECV_BUFFER_SIZE: usize = 64 * 1024;
+
+const NLMSG_HDRLEN: usize = 16;
+const NLM_F_MULTI: u16 = libc::NLM_F_MULTI as u16;
+const NLM_F_ACK: u16 = libc::NLM_F_ACK as u16;
+const NLM_F_DUMP: u16 = (libc::NLM_F_ROOT | libc::NLM_F_MATCH) as u16;
+
+/// Sequence numbers, port id and timeout for the requests sent on one netlink socket.
+///
+/// Every request gets its own sequence number, and only messages carrying that number and our
+/// port id are taken as its replies. Late replies to requests that timed out are dropped, and so
+/// are multicast notifications unless `keep_notifications` is set.
+pub(crate) struct Transactor {
+    pid: u32,
+    protocol: u16,
+    seq: u32,
+    timeout: Option<Duration>,
+    capture: Option<PcapWriter>,
+    buffer: Vec<u8>,
+    keep_notifications: bool,
+    notifications: VecDeque<Vec<u8>>,
+}
+
+impl fmt::Debug for Transactor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Transactor")
+            .field("pid", &self.pid)
+            .field("protocol", &self.protocol)
+            .field("seq", &self.seq)
+            .field("timeout", &self.timeout)
+            .field("capture", &self.capture)
+            .field("keep_notifications", &self.keep_notifications)
+            .finish()
+    }
+}
+
+/// A request waiting for its replies.
+pub(crate) struct Transaction<T, P> {
+    command: &'static str,
+    request: Vec<u8>,
+    seq: u32,
+    acked: bool,
+    idempotent: bool,
+    resyncs: usize,
+    replies: Vec<Nlmsghdr<T, P>>,
+    kernel: fn(&'static str, &Nlmsgerr<T, P>) -> Nl80211Error,
+}
+
+impl Transactor {
+    /// `protocol` is the netlink protocol of the socket, e.g. `NETLINK_GENERIC`.
+    pub(crate) fn new(pid: u32, protocol: u16) -> Self {
+        Transactor {
+            pid,
+            protocol,
+            // Don't start where the previous owner of this port id left off.
+            seq: rand::thread_rng().gen(),
+            timeout: Some(DEFAULT_TIMEOUT),
+            capture: None,
+            buffer: vec![0; RECV_BUFFER_SIZE],
+            keep_notifications: false,
+            notifications: VecDeque::new(),
+        }
+    }
+
+    pub(crate) fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    pub(crate) fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    pub(crate) fn capture(&self) -> Option<&PcapWriter> {
+        self.capture.as_ref()
+    }
+
+    pub(crate) fn set_capture(&mut self, capture: Option<PcapWriter>) {
+        self.capture = capture;
+    }
+
+    /// Queue the notifications received while waiting for replies for `next_notification`
+    /// instead of dropping them. Turning it off drops the queued ones.
+    pub(crate) fn keep_notifications(&mut self, keep: bool) {
+        self.keep_notifications = keep;
+        if !keep {
+            self.notifications.clear();
+        }
+    }
+
+    /// Hand a datagram to the capture file and, with the `log` feature, the log.
+    #[cfg_attr(not(feature = "log"), allow(unused_variables))]
+    fn trace(&self, command: &'static str, outgoing: bool, data: &[u8]) {
+        if let Some(capture) = &self.capture {
+            // A capture that can't be written shouldn't fail the request it records
+            let _ = capture.write_packet(self.protocol, outgoing, data);
+        }
+        #[cfg(feature = "log")]
+        if log::log_enabled!(log::Level::Debug) {
+            log::debug!(
+                "{} {}: {}",
+                command,
+                if outgoing { "sent" } else { "received" },
+                describe_datagram(self.protocol, data).trim_end()
+            );
+        }
+    }
+
+    fn next_seq(&mut self) -> u32 {
+        // 0 is what notifications carry
+        self.seq = self.seq.wrapping_add(1).max(1);
+        self.seq
+    }
+
+    /// Send the request and block until all its replies arrived, or the timeout expired.
+    pub(crate) fn transact<R: Transport + ?Sized, S, Q, T, P>(
+        &mut self,
+        command: &'static str,
+        transport: &mut R,
+        request: Nlmsghdr<S, Q>,
+        kernel: fn(&'static str, &Nlmsgerr<T, P>) -> Nl80211Error,
+    ) -> Result<Vec<Nlmsghdr<T, P>>, Nl80211Error>
+    where
+        S: NlType,
+        Q: ToBytes,
+        T: NlType,
+        Nlmsghdr<T, P>: for<'a> FromBytes<'a>,
+    {
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        let mut transaction = self.start(command, transport, request, kernel)?;
+        while !self.read(transport, &mut transaction)? {
+            let timeout =
+                deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+            let readable = match timeout {
+                Some(timeout) if timeout.is_zero() => false,
+                timeout => transport
+                    .wait(timeout)
+                    .map_err(|err| Nl80211Error::io(command, err))?,
+            };
+            if !readable {
+                return Err(Nl80211Error::io(
+                    command,
+                    io::Error::from_raw_os_error(libc::ETIMEDOUT),
+                ));
+            }
+        }
+        Ok(transaction.replies)
+    }
+
+    /// Send the request, its replies are then picked up with `read`.
+    pub(crate) fn start<R: Transport + ?Sized, S, Q, T, P>(
+        &mut self,
+        command: &'static str,
+        transport: &mut R,
+        request: Nlmsghdr<S, Q>,
+        kernel: fn(&'static str, &Nlmsgerr<T, P>) -> Nl80211Error,
+    ) -> Result<Transaction<T, P>, Nl80211Error>
+    where
+        S: NlType,
+        Q: ToBytes,
+    {
+        let mut buffer = Cursor::new(Vec::new());
+        request
+            .to_bytes(&mut buffer)
+            .map_err(|err| Nl80211Error::ser(command, err))?;
+        let request = buffer.into_inner();
+        let flags = u16::from_ne_bytes([request[6], request[7]]);
+        let idempotent = is_idempotent(self.protocol, &request);
+        let mut transaction = Transaction {
+            command,
+            request,
+            seq: 0,
+            // Dumps end with NLMSG_DONE, whether or not an ack was asked for.
+            acked: flags & NLM_F_ACK != 0 || flags & NLM_F_DUMP == NLM_F_DUMP,
+            idempotent,
+            resyncs: 0,
+            replies: Vec::new(),
+            kernel,
+        };
+        self.send(transport, &mut transaction)?;
+        Ok(transaction)
+    }
+
+    /// Send (or resend) the request under a new sequence number.
+    fn send<R: Transport + ?Sized, T, P>(
+        &mut self,
+        transport: &mut R,
+        transaction: &mut Transaction<T, P>,
+    ) -> Result<(), Nl80211Error> {
+        transaction.seq = self.next_seq();
+        transaction.request[8..12].copy_from_slice(&transaction.seq.to_ne_bytes());
+        transaction.request[12..16].copy_from_slice(&self.pid.to_ne_bytes());
+        transaction.replies.clear();
+        self.trace(transaction.command, true, &transaction.request);
+        transport
+            .send(&transaction.request)
+            .map_err(|err| Nl80211Error::io(transaction.command, err))
+    }
+
+    /// Read the replies queued on the socket without blocking. Returns true once the transaction
+    /// is complete, false if more replies are still to come.
+    pub(crate) fn read<R: Transport + ?Sized, T, P>(
+        &mut self,
+        transport: &mut R,
+        transaction: &mut Transaction<T, P>,
+    ) -> Result<bool, Nl80211Error>
+    where
+        T: NlType,
+        Nlmsghdr<T, P>: for<'a> FromBytes<'a>,
+    {
+        let command = transaction.command;
+        loop {
+            let len = match transport.recv(&mut self.buffer) {
+                Ok(len) => len,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(false),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                // Notifications overflowed the receive buffer and our replies may be lost with
+                // them, so throw everything away and ask again. A request that changes something
+                // may already have been applied, the caller has to look at the state instead.
+                Err(err)
+                    if err.raw_os_error() == Some(libc::ENOBUFS)
+                        && transaction.idempotent
+                        && transaction.resyncs < MAX_RESYNCS =>
+                {
+                    transaction.resyncs += 1;
+                    self.drain(transport);
+                    self.send(transport, transaction)?;
+                    continue;
+                }
+                Err(err) => return Err(Nl80211Error::io(command, err)),
+            };
+            self.trace(command, false, &self.buffer[..len]);
+
+            let mut position = 0;
+            while position + NLMSG_HDRLEN <= len {
+                let header = &self.buffer[position..position + NLMSG_HDRLEN];
+                let msg_len = u32::from_ne_bytes(header[0..4].try_into().unwrap()) as usize;
+                let msg_type = u16::from_ne_bytes(header[4..6].try_into().unwrap());
+                let msg_flags = u16::from_ne_bytes(header[6..8].try_into().unwrap());
+                let msg_seq = u32::from_ne_bytes(header[8..12].try_into().unwrap());
+                let msg_pid = u32::from_ne_bytes(header[12..16].try_into().unwrap());
+                if msg_len < NLMSG_HDRLEN || position + msg_len > len {
+                    return Err(Nl80211Error::Parse(format!(
+                        "{} truncated netlink message",
+                        command
+                    )));
+                }
+                let packet = &self.buffer[position..position + msg_len];
+                position += (msg_len + 3) & !3;
+
+                if msg_seq == 0 && self.keep_notifications {
+                    self.notifications.push_back(packet.to_vec());
+                    continue;
+                }
+                // Notifications, and replies to requests we already gave up on
+                if msg_seq != transaction.seq || msg_pid != self.pid {
+                    continue;
+                }
+                if msg_type == u16::from(Nlmsg::Noop) {
+                    continue;
+                }
+                if msg_type == u16::from(Nlmsg::Done) {
+                    return Ok(true);
+                }
+
+                let msg = Nlmsghdr::<T, P>::from_bytes(&mut Cursor::new(packet))
+                    .map_err(|err| Nl80211Error::Parse(format!("{} {}", command, err)))?;
+                match msg.nl_payload {
+                    NlPayload::Ack(_) => return Ok(true),
+                    NlPayload::Err(ref err) => return Err((transaction.kernel)(command, err)),
+                    _ => transaction.replies.push(msg),
+                }
+                // Without an ack the single reply is all there is.
+                if !transaction.acked && msg_flags & NLM_F_MULTI == 0 {
+                    return Ok(true);
+                }
+            }
+        }
+    }
+
+    /// Wait for the next multicast notification, replies to earlier requests are dropped. Fails
+    /// with ETIMEDOUT once the deadline passed (None waits forever).
+    pub(crate) fn next_notification<R: Transport + ?Sized, T, P>(
+        &mut self,
+        command: &'static str,
+        transport: &mut R,
+        deadline: Option<Instant>,
+    ) -> Result<Nlmsghdr<T, P>, Nl80211Error>
+    where
+        T: NlType,
+        Nlmsghdr<T, P>: for<'a> FromBytes<'a>,
+    {
+        loop {
+            if let Some(msg) = self.try_notification(command, transport)? {
+                return Ok(msg);
+            }
+            let timeout =
+                deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+            let readable = match timeout {
+                Some(timeout) if timeout.is_zero() => false,
+                timeout => transport
+                    .wait(timeout)
+                    .map_err(|err| Nl80211Error::io(command, err))?,
+            };
+            if !readable {
+                return Err(Nl80211Error::io(
+                    command,
+                    io::Error::from_raw_os_error(libc::ETIMEDOUT),
+                ));
+            }
+        }
+    }
+
+    /// The next multicast notification already queued or readable without blocking, None when
+    /// the socket has nothing more for now.
+    pub(crate) fn try_notification<R: Transport + ?Sized, T, P>(
+        &mut self,
+        command: &'static str,
+        transport: &mut R,
+    ) -> Result<Option<Nlmsghdr<T, P>>, Nl80211Error>
+    where
+        T: NlType,
+        Nlmsghdr<T, P>: for<'a> FromBytes<'a>,
+    {
+        loop {
+            if let Some(packet) = self.notifications.pop_front() {
+                return Nlmsghdr::<T, P>::from_bytes(&mut Cursor::new(&packet[..]))
+                    .map(Some)
+                    .map_err(|err| Nl80211Error::Parse(format!("{} {}", command, err)));
+            }
+            let len = match transport.recv(&mut self.buffer) {
+                Ok(len) => len,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(None),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                // The notification may be among the ones lost, there's nothing to ask again for
+                Err(err) => return Err(Nl80211Error::io(command, err)),
+            };
+            self.trace(command, false, &self.buffer[..len]);
+
+            let mut position = 0;
+            while position + NLMSG_HDRLEN <= len {
+                let header = &self.buffer[position..position + NLMSG_HDRLEN];
+                let msg_len = u32::from_ne_bytes(header[0..4].try_into().unwrap()) as usize;
+                let msg_seq = u32::from_ne_bytes(header[8..12].try_into().unwrap());
+                if msg_len < NLMSG_HDRLEN || position + msg_len > len {
+                    return Err(Nl80211Error::Parse(format!(
+                        "{} truncated netlink message",
+                        command
+                    )));
+                }
+                if msg_seq == 0 {
+                    self.notifications
+                        .push_back(self.buffer[position..position + msg_len].to_vec());
+                }
+                position += (msg_len + 3) & !3;
+            }
+        }
+    }
+
+    /// Throw away everything queued on the socket.
+    fn drain<R: Transport + ?Sized>(&mut self, transport: &mut R) {
+        loop {
+            match transport.recv(&mut self.buffer) {
+                Ok(_) => (),
+                Err(err) if err.raw_os_error() == Some(libc::ENOBUFS) => (),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+                Err(_) => return,
+            }
+        }
+    }
+}
+
+impl<T, P> Transaction<T, P> {
+    /// The payloads of the replies, once `read` reported the transaction complete.
+    pub(crate) fn into_payloads(self) -> Vec<P> {
+        payloads(self.replies)
+    }
+}
+
+/// Whether sending the request twice can't change anything: dumps, rtnetlink RTM_GET* requests,
+/// the family lookup and the nl80211 GET commands.
+fn is_idempotent(protocol: u16, request: &[u8]) -> bool {
+    let msg_type = u16::from_ne_bytes([request[4], request[5]]);
+    let flags = u16::from_ne_bytes([request[6], request[7]]);
+    if flags & NLM_F_DUMP == NLM_F_DUMP {
+        return true;
+    }
+    match protocol as i32 {
+        // RTM_GETLINK, RTM_GETADDR, ... are every fourth type from RTM_BASE + 2
+        libc::NETLINK_ROUTE => msg_type >= libc::RTM_GETLINK && msg_type % 4 == 2,
+        libc::NETLINK_GENERIC => {
+            let Some(&cmd) = request.get(NLMSG_HDRLEN) else {
+                return false;
+            };
+            if msg_type == libc::GENL_ID_CTRL as u16 {
+                return cmd as i32 == libc::CTRL_CMD_GETFAMILY;
+            }
+            matches!(
+                Nl80211Cmd::from(cmd),
+                Nl80211Cmd::CmdGetWiphy
+                    | Nl80211Cmd::CmdGetInterface
+                    | Nl80211Cmd::CmdGetKey
+                    | Nl80211Cmd::CmdGetBeacon
+                    | Nl80211Cmd::CmdGetStation
+                    | Nl80211Cmd::CmdGetMpath
+                    | Nl80211Cmd::CmdGetMeshConfig
+                    | Nl80211Cmd::CmdGetReg
+                    | Nl80211Cmd::CmdGetScan
+                    | Nl80211Cmd::CmdGetSurvey
+                    | Nl80211Cmd::CmdGetPowerSave
+                    | Nl80211Cmd::CmdGetWowlan
+                    | Nl80211Cmd::CmdGetProtocolFeatures
+                    | Nl80211Cmd::CmdGetCoalesce
+                    | Nl80211Cmd::CmdGetMpp
+            )
+        }
+        _ => false,
+    }
+}
+
+pub(crate) fn payloads<T, P>(replies: Vec<Nlmsghdr<T, P>>) -> Vec<P> {
+    replies
+        .into_iter()
+        .filter_map(|reply| match reply.nl_payload {
+            NlPayload::Payload(p) => Some(p),
+            _ => None,
+        })
+        .collect()
+}
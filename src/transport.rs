@@ -0,0 +1,451 @@
+use crate::error::Nl80211Error;
+use neli::consts::nl::NlType;
+use neli::consts::socket::NlFamily;
+use neli::nl::Nlmsghdr;
+use neli::socket::NlSocketHandle;
+use neli::ToBytes;
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Cursor, Write};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::Path;
+use std::time::Duration;
+
+/// The datagram socket beneath `NtSocket` and `RtSocket`.
+///
+/// `NetlinkTransport` talks to the kernel, `MockTransport` replays canned replies and `Recorder`
+/// captures the traffic of another transport to a fixture file `MockTransport` can load.
+pub trait Transport {
+    /// The port id replies are addressed to.
+    fn pid(&self) -> u32;
+
+    /// Send one request datagram.
+    fn send(&mut self, buf: &[u8]) -> io::Result<()>;
+
+    /// Receive one datagram without blocking, `WouldBlock` when nothing is queued.
+    fn recv(&mut self, buf: &mut [u8]) -> io::Result<usize>;
+
+    /// Block until a datagram is queued, false once the timeout expired (None waits forever).
+    fn wait(&mut self, timeout: Option<Duration>) -> io::Result<bool>;
+
+    /// Start receiving the notifications of a multicast group.
+    fn join_group(&mut self, group: u32) -> io::Result<()>;
+
+    /// Stop receiving the notifications of a multicast group.
+    fn leave_group(&mut self, group: u32) -> io::Result<()>;
+}
+
+/// A netlink socket.
+pub struct NetlinkTransport {
+    pub(crate) sock: NlSocketHandle,
+    pid: u32,
+}
+
+impl fmt::Debug for NetlinkTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NetlinkTransport")
+            .field("sock", &format_args!("<NlSocketHandle>"))
+            .field("pid", &self.pid)
+            .finish()
+    }
+}
+
+impl NetlinkTransport {
+    pub fn connect(family: NlFamily) -> Result<Self, Nl80211Error> {
+        let sock = NlSocketHandle::connect(family, None, &[])
+            .map_err(|err| Nl80211Error::io("connect", err))?;
+        sock.nonblock()
+            .map_err(|err| Nl80211Error::io("connect", err))?;
+        let pid = sock.pid().map_err(|err| Nl80211Error::io("connect", err))?;
+        Ok(NetlinkTransport { sock, pid })
+    }
+}
+
+impl Transport for NetlinkTransport {
+    fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    fn send(&mut self, buf: &[u8]) -> io::Result<()> {
+        loop {
+            let sent = unsafe {
+                libc::send(
+                    self.sock.as_raw_fd(),
+                    buf.as_ptr() as *const libc::c_void,
+                    buf.len(),
+                    0,
+                )
+            };
+            if sent >= 0 {
+                return Ok(());
+            }
+            let err = io::Error::last_os_error();
+            if err.kind() != io::ErrorKind::Interrupted {
+                return Err(err);
+            }
+        }
+    }
+
+    fn recv(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match unsafe {
+            libc::recv(
+                self.sock.as_raw_fd(),
+                buf.as_mut_ptr() as *mut libc::c_void,
+                buf.len(),
+                libc::MSG_DONTWAIT,
+            )
+        } {
+            len if len >= 0 => Ok(len as usize),
+            _ => Err(io::Error::last_os_error()),
+        }
+    }
+
+    fn wait(&mut self, timeout: Option<Duration>) -> io::Result<bool> {
+        let timeout = match timeout {
+            // Round up so the last sub-millisecond doesn't turn into a busy loop.
+            Some(timeout) => timeout.as_micros().div_ceil(1000).min(i32::MAX as u128) as i32,
+            None => -1,
+        };
+        let mut pollfd = libc::pollfd {
+            fd: self.sock.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        match unsafe { libc::poll(&mut pollfd, 1, timeout) } {
+            -1 => match io::Error::last_os_error() {
+                // Let the caller work out how much time is left
+                err if err.kind() == io::ErrorKind::Interrupted => Ok(true),
+                err => Err(err),
+            },
+            0 => Ok(false),
+            _ => Ok(true),
+        }
+    }
+
+    fn join_group(&mut self, group: u32) -> io::Result<()> {
+        self.sock.add_mcast_membership(&[group])
+    }
+
+    fn leave_group(&mut self, group: u32) -> io::Result<()> {
+        self.sock.drop_mcast_membership(&[group])
+    }
+}
+
+impl AsRawFd for NetlinkTransport {
+    fn as_raw_fd(&self) -> RawFd {
+        self.sock.as_raw_fd()
+    }
+}
+
+impl From<NetlinkTransport> for NlSocketHandle {
+    fn from(transport: NetlinkTransport) -> Self {
+        transport.sock
+    }
+}
+
+/// A reply `MockTransport` sends back for a request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MockReply {
+    /// One datagram, holding one or more netlink messages
+    Datagram(Vec<u8>),
+    /// NLMSG_DONE ending a dump
+    Done,
+    /// NLMSG_ERROR with error 0
+    Ack,
+    /// NLMSG_ERROR with the (positive) errno
+    Error(i32),
+    /// The receive buffer overflowed: the next read fails with ENOBUFS
+    Overrun,
+}
+
+/// Replays canned kernel replies instead of talking to the kernel, for tests without Wi-Fi hardware.
+///
+/// Every request pops the next expected exchange. Messages in its replies with a non-zero sequence
+/// number are rewritten to the sequence number and port id of the request, so replies can be
+/// built without knowing which sequence numbers the socket will pick. Acks and errors echo the
+/// request like the kernel does.
+#[derive(Debug, Default)]
+pub struct MockTransport {
+    pid: u32,
+    exchanges: VecDeque<Vec<MockReply>>,
+    queued: VecDeque<io::Result<Vec<u8>>>,
+    requests: Vec<Vec<u8>>,
+    groups: Vec<u32>,
+}
+
+impl MockTransport {
+    pub fn new() -> Self {
+        MockTransport {
+            pid: 4242,
+            ..Default::default()
+        }
+    }
+
+    /// Load a fixture written by `Recorder`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Nl80211Error> {
+        let fixture = fs::read_to_string(path).map_err(|err| Nl80211Error::io("load", err))?;
+        MockTransport::from_fixture(&fixture)
+    }
+
+    /// Parse a fixture: `> hex` lines are requests and the `< hex` lines after them the datagrams
+    /// received in reply. Datagrams before the first request are queued right away, like
+    /// notifications that arrived before anything was sent. Lines starting with `#` are comments.
+    pub fn from_fixture(fixture: &str) -> Result<Self, Nl80211Error> {
+        let mut mock = MockTransport::new();
+        let mut replies: Option<Vec<MockReply>> = None;
+        for (number, line) in fixture.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (request, data) = if let Some(data) = line.strip_prefix('>') {
+                (true, data)
+            } else if let Some(data) = line.strip_prefix('<') {
+                (false, data)
+            } else {
+                return Err(Nl80211Error::Parse(format!(
+                    "Fixture line {} doesn't start with > or <",
+                    number + 1
+                )));
+            };
+            let data = decode_hex(data.trim()).ok_or_else(|| {
+                Nl80211Error::Parse(format!("Fixture line {} is not valid hex", number + 1))
+            })?;
+            match (request, replies.as_mut()) {
+                (true, _) => {
+                    if let Some(replies) = replies.take() {
+                        mock.exchanges.push_back(replies);
+                    }
+                    replies = Some(Vec::new());
+                }
+                (false, Some(replies)) => replies.push(MockReply::Datagram(data)),
+                (false, None) => mock.queued.push_back(Ok(data)),
+            }
+        }
+        if let Some(replies) = replies {
+            mock.exchanges.push_back(replies);
+        }
+        Ok(mock)
+    }
+
+    /// Answer the next request with `replies`.
+    pub fn expect(&mut self, replies: Vec<MockReply>) -> &mut Self {
+        self.exchanges.push_back(replies);
+        self
+    }
+
+    /// Queue a datagram that wasn't asked for, e.g. a multicast notification.
+    pub fn notify(&mut self, datagram: Vec<u8>) -> &mut Self {
+        self.queued.push_back(Ok(datagram));
+        self
+    }
+
+    /// Every request sent so far.
+    pub fn requests(&self) -> &[Vec<u8>] {
+        &self.requests
+    }
+
+    /// The multicast groups currently joined.
+    pub fn groups(&self) -> &[u32] {
+        &self.groups
+    }
+
+    /// Whether every expected exchange was used up.
+    pub fn is_done(&self) -> bool {
+        self.exchanges.is_empty()
+    }
+}
+
+impl Transport for MockTransport {
+    fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    fn send(&mut self, buf: &[u8]) -> io::Result<()> {
+        if buf.len() < NLMSG_HDRLEN {
+            return Err(io::Error::from_raw_os_error(libc::EINVAL));
+        }
+        self.requests.push(buf.to_vec());
+        let replies = self
+            .exchanges
+            .pop_front()
+            .ok_or_else(|| io::Error::other("MockTransport has no reply for the request"))?;
+        let seq = u32::from_ne_bytes(buf[8..12].try_into().unwrap());
+        for reply in replies {
+            let mut datagram = match reply {
+                MockReply::Datagram(datagram) => datagram,
+                MockReply::Done => {
+                    header(libc::NLMSG_DONE as u16, NLM_F_MULTI, &0i32.to_ne_bytes())
+                }
+                // Like the kernel, acks only echo the header and errors the whole request
+                MockReply::Ack => error_message(0, &buf[..NLMSG_HDRLEN]),
+                MockReply::Error(errno) => error_message(-errno, buf),
+                MockReply::Overrun => {
+                    let overrun = io::Error::from_raw_os_error(libc::ENOBUFS);
+                    self.queued.push_back(Err(overrun));
+                    continue;
+                }
+            };
+            patch_seq(&mut datagram, seq, self.pid);
+            self.queued.push_back(Ok(datagram));
+        }
+        Ok(())
+    }
+
+    fn recv(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let datagram = self
+            .queued
+            .pop_front()
+            .ok_or_else(|| io::Error::from(io::ErrorKind::WouldBlock))??;
+        // Truncated like a real datagram socket
+        let len = datagram.len().min(buf.len());
+        buf[..len].copy_from_slice(&datagram[..len]);
+        Ok(len)
+    }
+
+    fn wait(&mut self, _timeout: Option<Duration>) -> io::Result<bool> {
+        // Nothing more is ever going to arrive
+        Ok(!self.queued.is_empty())
+    }
+
+    fn join_group(&mut self, group: u32) -> io::Result<()> {
+        if !self.groups.contains(&group) {
+            self.groups.push(group);
+        }
+        Ok(())
+    }
+
+    fn leave_group(&mut self, group: u32) -> io::Result<()> {
+        self.groups.retain(|joined| *joined != group);
+        Ok(())
+    }
+}
+
+/// Captures the traffic of another transport to a fixture file `MockTransport::load` replays.
+pub struct Recorder<T> {
+    inner: T,
+    out: File,
+}
+
+impl<T: fmt::Debug> fmt::Debug for Recorder<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Recorder")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl<T: Transport> Recorder<T> {
+    /// Record to `path`, replacing the file.
+    pub fn create<P: AsRef<Path>>(inner: T, path: P) -> Result<Self, Nl80211Error> {
+        let out = File::create(path).map_err(|err| Nl80211Error::io("create", err))?;
+        Ok(Recorder { inner, out })
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    fn record(&mut self, direction: char, data: &[u8]) -> io::Result<()> {
+        writeln!(self.out, "{} {}", direction, encode_hex(data))
+    }
+}
+
+impl<T: Transport> Transport for Recorder<T> {
+    fn pid(&self) -> u32 {
+        self.inner.pid()
+    }
+
+    fn send(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.inner.send(buf)?;
+        self.record('>', buf)
+    }
+
+    fn recv(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.recv(buf)?;
+        self.record('<', &buf[..len])?;
+        Ok(len)
+    }
+
+    fn wait(&mut self, timeout: Option<Duration>) -> io::Result<bool> {
+        self.inner.wait(timeout)
+    }
+
+    fn join_group(&mut self, group: u32) -> io::Result<()> {
+        self.inner.join_group(group)
+    }
+
+    fn leave_group(&mut self, group: u32) -> io::Result<()> {
+        self.inner.leave_group(group)
+    }
+}
+
+impl<T: AsRawFd> AsRawFd for Recorder<T> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+/// Serialize a message into a datagram for `MockReply::Datagram`. Give it a non-zero sequence
+/// number to have it taken as a reply, zero for a notification.
+pub fn message_bytes<T: NlType, P: ToBytes>(msg: &Nlmsghdr<T, P>) -> Result<Vec<u8>, Nl80211Error> {
+    let mut buffer = Cursor::new(Vec::new());
+    msg.to_bytes(&mut buffer)
+        .map_err(|err| Nl80211Error::ser("message_bytes", err))?;
+    Ok(buffer.into_inner())
+}
+
+const NLMSG_HDRLEN: usize = 16;
+const NLM_F_MULTI: u16 = libc::NLM_F_MULTI as u16;
+
+/// A message of the given type and payload, with a placeholder sequence number.
+fn header(msg_type: u16, flags: u16, payload: &[u8]) -> Vec<u8> {
+    let mut msg = Vec::with_capacity(NLMSG_HDRLEN + payload.len());
+    msg.extend_from_slice(&((NLMSG_HDRLEN + payload.len()) as u32).to_ne_bytes());
+    msg.extend_from_slice(&msg_type.to_ne_bytes());
+    msg.extend_from_slice(&flags.to_ne_bytes());
+    msg.extend_from_slice(&1u32.to_ne_bytes());
+    msg.extend_from_slice(&0u32.to_ne_bytes());
+    msg.extend_from_slice(payload);
+    msg
+}
+
+/// NLMSG_ERROR echoing the request.
+fn error_message(error: i32, request: &[u8]) -> Vec<u8> {
+    let mut payload = error.to_ne_bytes().to_vec();
+    payload.extend_from_slice(request);
+    header(libc::NLMSG_ERROR as u16, 0, &payload)
+}
+
+/// Point every message of the datagram that isn't a notification at the request.
+fn patch_seq(datagram: &mut [u8], seq: u32, pid: u32) {
+    let mut position = 0;
+    while position + NLMSG_HDRLEN <= datagram.len() {
+        let msg_len =
+            u32::from_ne_bytes(datagram[position..position + 4].try_into().unwrap()) as usize;
+        if msg_len < NLMSG_HDRLEN {
+            return;
+        }
+        if datagram[position + 8..position + 12] != [0; 4] {
+            datagram[position + 8..position + 12].copy_from_slice(&seq.to_ne_bytes());
+            datagram[position + 12..position + 16].copy_from_slice(&pid.to_ne_bytes());
+        }
+        position += (msg_len + 3) & !3;
+    }
+}
+
+fn encode_hex(data: &[u8]) -> String {
+    data.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
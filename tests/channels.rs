@@ -0,0 +1,319 @@
+use nl80211_ng::attr::{Nl80211ChanWidth, Nl80211ChannelType, Nl80211DfsState, Nl80211Iftype};
+use nl80211_ng::band::{
+    EhtCapabilities, HeCapabilities, HtCapabilities, IftypeCapabilities, VhtCapabilities,
+};
+use nl80211_ng::channels::{
+    chan_from_frequency, supported_channel_specs, validate_channel_spec, BandList, ChannelData,
+    ChannelError, ChannelSpec, FrequencyStatus, WiFiBand,
+};
+
+fn channels(frequencies: impl Iterator<Item = u32>) -> Vec<ChannelData> {
+    frequencies
+        .map(|frequency| ChannelData {
+            frequency,
+            channel: chan_from_frequency(frequency),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Channels 36 to 64
+fn band_5ghz() -> BandList {
+    BandList {
+        band: WiFiBand::Band5GHz,
+        channels: channels((5180..=5320).step_by(20)),
+        ht: Some(HtCapabilities {
+            ht40: true,
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// Channels 1 to 61, a single 320 MHz block
+fn band_6ghz(eht320: bool) -> BandList {
+    BandList {
+        band: WiFiBand::Band6GHz,
+        channels: channels((5955..=6255).step_by(20)),
+        iftype_data: vec![IftypeCapabilities {
+            iftypes: vec![Nl80211Iftype::IftypeStation, Nl80211Iftype::IftypeAp],
+            he: Some(HeCapabilities {
+                he40_80_5ghz: true,
+                he160: true,
+                ..Default::default()
+            }),
+            eht: Some(EhtCapabilities {
+                eht320,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }],
+        ..Default::default()
+    }
+}
+
+fn widths(specs: &[ChannelSpec]) -> Vec<Nl80211ChanWidth> {
+    let mut widths: Vec<Nl80211ChanWidth> = specs.iter().map(|spec| spec.width).collect();
+    widths.sort();
+    widths.dedup();
+    widths
+}
+
+#[test]
+fn ht_only_phys_stop_at_40mhz() {
+    let bands = [band_5ghz()];
+    let specs = supported_channel_specs(&bands, Nl80211Iftype::IftypeMonitor);
+    assert_eq!(
+        widths(&specs),
+        vec![
+            Nl80211ChanWidth::ChanWidth20Noht,
+            Nl80211ChanWidth::ChanWidth20,
+            Nl80211ChanWidth::ChanWidth40,
+        ]
+    );
+
+    let mut legacy = band_5ghz();
+    legacy.ht = None;
+    let specs = supported_channel_specs(&[legacy], Nl80211Iftype::IftypeMonitor);
+    assert_eq!(widths(&specs), vec![Nl80211ChanWidth::ChanWidth20Noht]);
+}
+
+#[test]
+fn vht_phys_without_eht_stop_at_160mhz() {
+    let mut band = band_5ghz();
+    band.vht = Some(VhtCapabilities::default());
+    let specs = supported_channel_specs(&[band.clone()], Nl80211Iftype::IftypeMonitor);
+    assert!(widths(&specs).contains(&Nl80211ChanWidth::ChanWidth80));
+    assert!(!widths(&specs).contains(&Nl80211ChanWidth::ChanWidth160));
+
+    band.vht = Some(VhtCapabilities {
+        vht160: true,
+        ..Default::default()
+    });
+    let specs = supported_channel_specs(&[band], Nl80211Iftype::IftypeMonitor);
+    assert!(widths(&specs).contains(&Nl80211ChanWidth::ChanWidth160));
+    assert!(!widths(&specs).contains(&Nl80211ChanWidth::ChanWidth320));
+
+    let specs = supported_channel_specs(&[band_6ghz(false)], Nl80211Iftype::IftypeMonitor);
+    assert!(widths(&specs).contains(&Nl80211ChanWidth::ChanWidth160));
+    assert!(!widths(&specs).contains(&Nl80211ChanWidth::ChanWidth320));
+    let specs = supported_channel_specs(&[band_6ghz(true)], Nl80211Iftype::IftypeMonitor);
+    assert!(widths(&specs).contains(&Nl80211ChanWidth::ChanWidth320));
+}
+
+#[test]
+fn monitor_interfaces_can_listen_on_dfs_channels() {
+    let mut band = band_5ghz();
+    // Channel 52 needs radar detection, 56 is passive only and 64 disabled
+    band.channels[4].radar = true;
+    band.channels[4].dfs_state = Some(Nl80211DfsState::DfsUsable);
+    band.channels[5].no_ir = true;
+    band.channels[7].status = FrequencyStatus::Disabled;
+    let bands = [band];
+
+    for frequency in [5260, 5280] {
+        let spec = ChannelSpec::no_ht(frequency);
+        assert_eq!(
+            validate_channel_spec(&spec, &bands, Nl80211Iftype::IftypeMonitor),
+            Ok(())
+        );
+    }
+    assert_eq!(
+        validate_channel_spec(&ChannelSpec::no_ht(5260), &bands, Nl80211Iftype::IftypeAp),
+        Err(ChannelError::Radar(5260))
+    );
+    assert_eq!(
+        validate_channel_spec(&ChannelSpec::no_ht(5280), &bands, Nl80211Iftype::IftypeAp),
+        Err(ChannelError::NoIr(5280))
+    );
+    assert_eq!(
+        validate_channel_spec(
+            &ChannelSpec::no_ht(5320),
+            &bands,
+            Nl80211Iftype::IftypeMonitor
+        ),
+        Err(ChannelError::Disabled(5320))
+    );
+}
+
+#[test]
+fn cleared_radar_channels_can_beacon() {
+    let mut band = band_5ghz();
+    band.channels[4].radar = true;
+    band.channels[4].dfs_state = Some(Nl80211DfsState::DfsAvailable);
+    let bands = [band];
+
+    for iftype in [
+        Nl80211Iftype::IftypeAp,
+        Nl80211Iftype::IftypeAdhoc,
+        Nl80211Iftype::IftypeMeshPoint,
+    ] {
+        assert_eq!(
+            validate_channel_spec(&ChannelSpec::no_ht(5260), &bands, iftype),
+            Ok(())
+        );
+    }
+}
+
+#[test]
+fn narrower_width_flags_apply_to_320mhz() {
+    let mut band = band_6ghz(true);
+    band.channels[9].no_160mhz = true;
+    let bands = [band];
+
+    let spec = ChannelSpec::new(5955, Nl80211ChanWidth::ChanWidth320).unwrap();
+    assert_eq!(
+        validate_channel_spec(&spec, &bands, Nl80211Iftype::IftypeMonitor),
+        Err(ChannelError::WidthNotAllowed {
+            frequency: 6135,
+            width: Nl80211ChanWidth::ChanWidth320,
+        })
+    );
+    let specs = supported_channel_specs(&bands, Nl80211Iftype::IftypeMonitor);
+    assert!(!widths(&specs).contains(&Nl80211ChanWidth::ChanWidth320));
+}
+
+#[test]
+fn center_frequencies_follow_the_channel_plan() {
+    use Nl80211ChanWidth::*;
+    // (band, channel, width, primary, center)
+    let cases = [
+        (WiFiBand::Band2GHz, 1, ChanWidth20, 2412, 2412),
+        (WiFiBand::Band2GHz, 1, ChanWidth40, 2412, 2422),
+        (WiFiBand::Band2GHz, 7, ChanWidth40, 2442, 2452),
+        (WiFiBand::Band2GHz, 8, ChanWidth40, 2447, 2437),
+        (WiFiBand::Band2GHz, 13, ChanWidth40, 2472, 2462),
+        (WiFiBand::Band2GHz, 14, ChanWidth20, 2484, 2484),
+        (WiFiBand::Band5GHz, 36, ChanWidth40, 5180, 5190),
+        (WiFiBand::Band5GHz, 36, ChanWidth80, 5180, 5210),
+        (WiFiBand::Band5GHz, 36, ChanWidth160, 5180, 5250),
+        (WiFiBand::Band5GHz, 64, ChanWidth160, 5320, 5250),
+        (WiFiBand::Band5GHz, 149, ChanWidth40, 5745, 5755),
+        (WiFiBand::Band5GHz, 149, ChanWidth80, 5745, 5775),
+        (WiFiBand::Band5GHz, 149, ChanWidth160, 5745, 5815),
+        (WiFiBand::Band6GHz, 1, ChanWidth160, 5955, 6025),
+        (WiFiBand::Band6GHz, 37, ChanWidth160, 6135, 6185),
+        (WiFiBand::Band6GHz, 1, ChanWidth320, 5955, 6105),
+        (WiFiBand::Band6GHz, 61, ChanWidth320, 6255, 6105),
+        (WiFiBand::Band6GHz, 65, ChanWidth320, 6275, 6425),
+    ];
+    for (band, channel, width, frequency, center_freq1) in cases {
+        let spec = ChannelSpec::from_channel(channel, band, width).unwrap();
+        assert_eq!(spec, ChannelSpec::new(frequency, width).unwrap());
+        assert_eq!(
+            (spec.frequency, spec.center_freq1, spec.center_freq2),
+            (frequency, center_freq1, None),
+            "channel {} {:?}",
+            channel,
+            width
+        );
+    }
+}
+
+#[test]
+fn ht40_picks_the_secondary_channel() {
+    // (primary, plus, center, channel type)
+    let cases = [
+        (
+            2412,
+            true,
+            Some(2422),
+            Some(Nl80211ChannelType::ChanHt40plus),
+        ),
+        (2412, false, None, None),
+        (
+            2437,
+            true,
+            Some(2447),
+            Some(Nl80211ChannelType::ChanHt40plus),
+        ),
+        (
+            2437,
+            false,
+            Some(2427),
+            Some(Nl80211ChannelType::ChanHt40minus),
+        ),
+        (2472, true, None, None),
+        (
+            2472,
+            false,
+            Some(2462),
+            Some(Nl80211ChannelType::ChanHt40minus),
+        ),
+        (2484, true, None, None),
+        (2484, false, None, None),
+        (
+            5180,
+            true,
+            Some(5190),
+            Some(Nl80211ChannelType::ChanHt40plus),
+        ),
+        (5180, false, None, None),
+        (
+            5200,
+            false,
+            Some(5190),
+            Some(Nl80211ChannelType::ChanHt40minus),
+        ),
+        (5200, true, None, None),
+        (
+            5955,
+            true,
+            Some(5965),
+            Some(Nl80211ChannelType::ChanHt40plus),
+        ),
+    ];
+    for (frequency, plus, center_freq1, channel_type) in cases {
+        let spec = ChannelSpec::ht40(frequency, plus).ok();
+        assert_eq!(
+            spec.map(|spec| spec.center_freq1),
+            center_freq1,
+            "{} MHz HT40{}",
+            frequency,
+            if plus { "+" } else { "-" }
+        );
+        assert_eq!(spec.and_then(|spec| spec.channel_type()), channel_type);
+    }
+}
+
+#[test]
+fn invalid_channels_are_rejected() {
+    use Nl80211ChanWidth::*;
+    // Channel 14 is 20 MHz only
+    assert!(ChannelSpec::new(2484, ChanWidth40).is_err());
+    assert!(ChannelSpec::from_channel(14, WiFiBand::Band2GHz, ChanWidth40).is_err());
+    assert!(ChannelSpec::from_channel(15, WiFiBand::Band2GHz, ChanWidth20).is_err());
+    // 5 GHz channel 144 has no 160 MHz channel
+    assert!(ChannelSpec::from_channel(144, WiFiBand::Band5GHz, ChanWidth160).is_err());
+    // 6 GHz primary channels are 1, 5, 9 ... and the last 320 MHz block doesn't fit
+    assert!(ChannelSpec::from_channel(3, WiFiBand::Band6GHz, ChanWidth40).is_err());
+    assert!(ChannelSpec::from_channel(221, WiFiBand::Band6GHz, ChanWidth320).is_err());
+    assert!(ChannelSpec::new(5180, ChanWidth80p80).is_err());
+    assert!(ChannelSpec::new(2400, ChanWidth20).is_err());
+}
+
+#[test]
+fn vht80p80_segments_must_be_apart() {
+    let spec = ChannelSpec::vht80p80(5180, 5775).unwrap();
+    assert_eq!(spec.width, Nl80211ChanWidth::ChanWidth80p80);
+    assert_eq!((spec.center_freq1, spec.center_freq2), (5210, Some(5775)));
+    assert_eq!(spec.channel_type(), None);
+    assert!(ChannelSpec::vht80p80(5180, 5530).is_ok());
+
+    // (primary, second segment): adjacent, overlapping, not an 80 MHz center, another band
+    for (frequency, segment2) in [
+        (5180, 5290),
+        (5260, 5210),
+        (5745, 5855),
+        (5180, 5210),
+        (5180, 5250),
+        (5180, 6025),
+    ] {
+        assert!(
+            ChannelSpec::vht80p80(frequency, segment2).is_err(),
+            "{} + {} MHz",
+            frequency,
+            segment2
+        );
+    }
+}
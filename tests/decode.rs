@@ -0,0 +1,1151 @@
+use nl80211_ng::attr::{
+    Nl80211Attr, Nl80211BandAttr, Nl80211BandIftypeAttr, Nl80211Bandc, Nl80211BitrateAttr,
+    Nl80211Bss, Nl80211ChanWidth, Nl80211DfsRegions, Nl80211DfsState, Nl80211ExtFeatureIndex,
+    Nl80211FeatureFlags, Nl80211FrequencyAttr, Nl80211IfCombinationAttrs, Nl80211IfaceLimitAttrs,
+    Nl80211Iftype, Nl80211RateInfo, Nl80211StaInfo, Nl80211TidStats,
+};
+use nl80211_ng::channels::{
+    chan_from_frequency, pretty_print_band_lists, BandList, ChannelData, FrequencyStatus, WiFiBand,
+};
+use nl80211_ng::cmd::Nl80211Cmd;
+use nl80211_ng::combination::{ConcurrencyRequest, InterfaceCombination, InterfaceLimit};
+use nl80211_ng::decode::{
+    decode_bss, decode_interface, decode_messages, decode_regdomain, decode_station, decode_survey,
+    decode_wiphy,
+};
+use nl80211_ng::phy::{akm_suite_name, cipher_suite_name};
+use nl80211_ng::survey::{rank_channels, SurveyInfo};
+use nl80211_ng::{Interface, Nl80211Error, Nl80211Event};
+use proptest::prelude::*;
+
+const FAMILY_ID: u16 = 0x1c;
+const NLA_F_NESTED: u16 = 1 << 15;
+
+fn nlattr(ty: u16, payload: &[u8]) -> Vec<u8> {
+    let mut attr = ((4 + payload.len()) as u16).to_ne_bytes().to_vec();
+    attr.extend_from_slice(&ty.to_ne_bytes());
+    attr.extend_from_slice(payload);
+    attr.resize((attr.len() + 3) & !3, 0);
+    attr
+}
+
+fn nested(ty: u16, children: &[Vec<u8>]) -> Vec<u8> {
+    nlattr(ty | NLA_F_NESTED, &children.concat())
+}
+
+/// A nl80211 message around already serialized attributes.
+fn message(cmd: Nl80211Cmd, attrs: &[u8]) -> Vec<u8> {
+    let mut msg = ((16 + 4 + attrs.len()) as u32).to_ne_bytes().to_vec();
+    msg.extend_from_slice(&FAMILY_ID.to_ne_bytes());
+    msg.extend_from_slice(&(libc::NLM_F_MULTI as u16).to_ne_bytes());
+    msg.extend_from_slice(&1u32.to_ne_bytes());
+    msg.extend_from_slice(&0u32.to_ne_bytes());
+    msg.extend_from_slice(&[u8::from(cmd), 1, 0, 0]);
+    msg.extend_from_slice(attrs);
+    msg
+}
+
+fn attr_type(attr: Nl80211Attr) -> u16 {
+    u16::from(attr)
+}
+
+/// Run every decoder over the message, whatever it contains.
+fn decode_all(bytes: &[u8]) {
+    let Ok(messages) = decode_messages(bytes) else {
+        return;
+    };
+    let _ = decode_wiphy(&messages);
+    for msg in &messages {
+        let _ = decode_interface(msg);
+        let _ = decode_bss(msg);
+        let _ = decode_survey(msg);
+        let _ = decode_station(msg);
+        let _ = decode_regdomain(msg);
+        let _ = Nl80211Event::from_genl(msg);
+    }
+}
+
+/// Attributes the decoders look into, with payloads that are often nested attributes themselves.
+fn interesting_attr() -> impl Strategy<Value = Vec<u8>> {
+    let types = prop::sample::select(vec![
+        Nl80211Attr::AttrWiphy,
+        Nl80211Attr::AttrWiphyName,
+        Nl80211Attr::AttrIfindex,
+        Nl80211Attr::AttrIfname,
+        Nl80211Attr::AttrIftype,
+        Nl80211Attr::AttrMac,
+        Nl80211Attr::AttrWiphyFreq,
+        Nl80211Attr::AttrSupportedIftypes,
+        Nl80211Attr::AttrWiphyBands,
+        Nl80211Attr::AttrMntrFlags,
+        Nl80211Attr::AttrBss,
+        Nl80211Attr::AttrSurveyInfo,
+        Nl80211Attr::AttrStaInfo,
+        Nl80211Attr::AttrRegRules,
+        Nl80211Attr::AttrRegAlpha2,
+        Nl80211Attr::AttrFrame,
+    ]);
+    let leaf = (0u16..32, prop::collection::vec(any::<u8>(), 0..12))
+        .prop_map(|(ty, payload)| nlattr(ty, &payload));
+    let tree = leaf.prop_recursive(3, 32, 4, |inner| {
+        (0u16..32, prop::collection::vec(inner, 0..4))
+            .prop_map(|(ty, children)| nested(ty, &children))
+    });
+    (types, prop::collection::vec(tree, 0..4)).prop_map(|(ty, children)| {
+        // Sometimes nested, sometimes a raw payload of the same bytes
+        nlattr(attr_type(ty), &children.concat())
+    })
+}
+
+fn frequency_entry(index: u16, frequency: u32) -> Vec<u8> {
+    nested(
+        index,
+        &[nlattr(
+            u16::from(Nl80211FrequencyAttr::FrequencyAttrFreq),
+            &frequency.to_ne_bytes(),
+        )],
+    )
+}
+
+fn band_2ghz(frequencies: &[u32]) -> Vec<u8> {
+    let entries: Vec<Vec<u8>> = frequencies
+        .iter()
+        .enumerate()
+        .map(|(i, frequency)| frequency_entry(i as u16, *frequency))
+        .collect();
+    band(Nl80211Bandc::Band2ghz, &entries)
+}
+
+fn band(band: Nl80211Bandc, entries: &[Vec<u8>]) -> Vec<u8> {
+    nested(
+        attr_type(Nl80211Attr::AttrWiphyBands),
+        &[nested(
+            u16::from(band),
+            &[nested(u16::from(Nl80211BandAttr::BandAttrFreqs), entries)],
+        )],
+    )
+}
+
+fn name_attr(ty: Nl80211Attr, name: &str) -> Vec<u8> {
+    let mut name = name.as_bytes().to_vec();
+    name.push(0);
+    nlattr(attr_type(ty), &name)
+}
+
+proptest! {
+    #[test]
+    fn arbitrary_datagrams_dont_panic(bytes in prop::collection::vec(any::<u8>(), 0..512)) {
+        decode_all(&bytes);
+    }
+
+    #[test]
+    fn arbitrary_attributes_dont_panic(
+        cmd in prop::sample::select(vec![
+            Nl80211Cmd::CmdNewWiphy,
+            Nl80211Cmd::CmdNewInterface,
+            Nl80211Cmd::CmdNewScanResults,
+            Nl80211Cmd::CmdNewSurveyResults,
+            Nl80211Cmd::CmdNewStation,
+            Nl80211Cmd::CmdGetReg,
+            Nl80211Cmd::CmdRegChange,
+            Nl80211Cmd::CmdConnect,
+        ]),
+        attrs in prop::collection::vec(interesting_attr(), 0..6),
+    ) {
+        decode_all(&message(cmd, &attrs.concat()));
+    }
+
+    #[test]
+    fn truncated_messages_are_errors(cut in 1usize..64) {
+        let attrs = [
+            nlattr(attr_type(Nl80211Attr::AttrWiphy), &0u32.to_ne_bytes()),
+            name_attr(Nl80211Attr::AttrWiphyName, "phy0"),
+            band_2ghz(&[2412, 2417, 2422]),
+        ]
+        .concat();
+        let msg = message(Nl80211Cmd::CmdNewWiphy, &attrs);
+        let cut = cut.min(msg.len() - 16);
+        prop_assert!(decode_messages(&msg[..msg.len() - cut]).is_err());
+    }
+
+    #[test]
+    fn interfaces_round_trip(
+        index in 1u32..100_000,
+        wiphy in 0u32..16,
+        name in "[a-z][a-z0-9]{0,14}",
+        mac in any::<[u8; 6]>(),
+        iftype in prop::sample::select(vec![
+            Nl80211Iftype::IftypeStation,
+            Nl80211Iftype::IftypeAp,
+            Nl80211Iftype::IftypeMonitor,
+            Nl80211Iftype::IftypeMeshPoint,
+        ]),
+        frequency in prop::option::of(0u32..80_000),
+    ) {
+        let mut attrs = vec![
+            nlattr(attr_type(Nl80211Attr::AttrIfindex), &index.to_ne_bytes()),
+            name_attr(Nl80211Attr::AttrIfname, &name),
+            nlattr(attr_type(Nl80211Attr::AttrWiphy), &wiphy.to_ne_bytes()),
+            nlattr(attr_type(Nl80211Attr::AttrIftype), &(u16::from(iftype) as u32).to_ne_bytes()),
+            nlattr(attr_type(Nl80211Attr::AttrMac), &mac),
+        ];
+        if let Some(frequency) = frequency {
+            attrs.push(nlattr(attr_type(Nl80211Attr::AttrWiphyFreq), &frequency.to_ne_bytes()));
+        }
+        let messages = decode_messages(&message(Nl80211Cmd::CmdNewInterface, &attrs.concat())).unwrap();
+        prop_assert_eq!(messages.len(), 1);
+
+        let interface = decode_interface(&messages[0]).unwrap();
+        prop_assert_eq!(interface.index, Some(index));
+        prop_assert_eq!(interface.phy_name, wiphy);
+        prop_assert_eq!(interface.name_as_string(), name);
+        prop_assert_eq!(interface.mac, Some(mac.to_vec()));
+        prop_assert_eq!(interface.current_iftype, Some(iftype));
+        prop_assert_eq!(interface.frequency.frequency, frequency);
+        prop_assert_eq!(interface.frequency.channel, frequency.map(chan_from_frequency));
+    }
+
+    #[test]
+    fn split_wiphy_dumps_merge_their_bands(
+        chunks in prop::collection::vec(prop::collection::vec(2412u32..2484, 0..8), 1..6),
+    ) {
+        let datagram: Vec<u8> = chunks
+            .iter()
+            .flat_map(|frequencies| {
+                let attrs = [
+                    nlattr(attr_type(Nl80211Attr::AttrWiphy), &3u32.to_ne_bytes()),
+                    name_attr(Nl80211Attr::AttrWiphyName, "phy3"),
+                    band_2ghz(frequencies),
+                ];
+                message(Nl80211Cmd::CmdNewWiphy, &attrs.concat())
+            })
+            .collect();
+        let messages = decode_messages(&datagram).unwrap();
+        prop_assert_eq!(messages.len(), chunks.len());
+
+        let phy = decode_wiphy(&messages).unwrap();
+        prop_assert_eq!(phy.phy, 3);
+        prop_assert_eq!(phy.phy_name.as_deref(), Some("phy3"));
+        let bands = phy.frequency_list.unwrap();
+        prop_assert_eq!(bands.len(), 1);
+        prop_assert_eq!(&bands[0].band, &WiFiBand::Band2GHz);
+        let frequencies: Vec<u32> = bands[0].channels.iter().map(|channel| channel.frequency).collect();
+        prop_assert_eq!(frequencies, chunks.concat());
+    }
+}
+
+#[test]
+fn kernel_errors_are_returned() {
+    let mut msg = 36u32.to_ne_bytes().to_vec();
+    msg.extend_from_slice(&(libc::NLMSG_ERROR as u16).to_ne_bytes());
+    msg.extend_from_slice(&[0; 10]);
+    msg.extend_from_slice(&(-libc::ENODEV).to_ne_bytes());
+    msg.extend_from_slice(&[0; 16]);
+
+    let err = decode_messages(&msg).unwrap_err();
+    assert!(err.is_no_device());
+}
+
+#[test]
+fn wiphys_need_a_name() {
+    let attrs = nlattr(attr_type(Nl80211Attr::AttrWiphy), &0u32.to_ne_bytes());
+    let messages = decode_messages(&message(Nl80211Cmd::CmdNewWiphy, &attrs)).unwrap();
+    assert!(decode_wiphy(&messages).is_err());
+}
+
+#[test]
+fn channel_flags_are_decoded() {
+    let flag = |attr: Nl80211FrequencyAttr| nlattr(u16::from(attr), &[]);
+    let value =
+        |attr: Nl80211FrequencyAttr, value: u32| nlattr(u16::from(attr), &value.to_ne_bytes());
+    let entries = [
+        nested(0, &[value(Nl80211FrequencyAttr::FrequencyAttrFreq, 5180)]),
+        nested(
+            1,
+            &[
+                value(Nl80211FrequencyAttr::FrequencyAttrFreq, 5260),
+                flag(Nl80211FrequencyAttr::FrequencyAttrNoIr),
+                flag(Nl80211FrequencyAttr::FrequencyAttrRadar),
+                value(Nl80211FrequencyAttr::FrequencyAttrDfsState, 1),
+                value(Nl80211FrequencyAttr::FrequencyAttrDfsTime, 4000),
+                flag(Nl80211FrequencyAttr::FrequencyAttrNoHt40Minus),
+                value(Nl80211FrequencyAttr::FrequencyAttrDfsCacTime, 60000),
+                flag(Nl80211FrequencyAttr::FrequencyAttrIndoorOnly),
+                flag(Nl80211FrequencyAttr::FrequencyAttrNo10mhz),
+            ],
+        ),
+        nested(
+            2,
+            &[
+                value(Nl80211FrequencyAttr::FrequencyAttrFreq, 5745),
+                flag(Nl80211FrequencyAttr::FrequencyAttrNoIr),
+            ],
+        ),
+        nested(
+            3,
+            &[
+                value(Nl80211FrequencyAttr::FrequencyAttrFreq, 5865),
+                flag(Nl80211FrequencyAttr::FrequencyAttrDisabled),
+                flag(Nl80211FrequencyAttr::FrequencyAttrNoIr),
+            ],
+        ),
+    ];
+    let attrs = [
+        nlattr(attr_type(Nl80211Attr::AttrWiphy), &0u32.to_ne_bytes()),
+        name_attr(Nl80211Attr::AttrWiphyName, "phy0"),
+        band(Nl80211Bandc::Band5ghz, &entries),
+    ];
+    let messages = decode_messages(&message(Nl80211Cmd::CmdNewWiphy, &attrs.concat())).unwrap();
+    let bands = decode_wiphy(&messages).unwrap().frequency_list.unwrap();
+    let channels = &bands[0].channels;
+
+    assert_eq!(channels[0].status, FrequencyStatus::Enabled);
+    assert_eq!(channels[0].dfs_state, None);
+
+    let radar = &channels[1];
+    assert_eq!(radar.status, FrequencyStatus::Radar);
+    assert!(radar.no_ir && radar.radar && radar.indoor_only && radar.no_10mhz);
+    assert!(radar.no_ht40_minus && !radar.no_ht40_plus && !radar.no_20mhz);
+    assert_eq!(radar.dfs_state, Some(Nl80211DfsState::DfsUnavailable));
+    assert_eq!(radar.dfs_time, Some(4000));
+    assert_eq!(radar.dfs_cac_time, Some(60000));
+
+    assert_eq!(channels[2].status, FrequencyStatus::PassiveOnly);
+    assert_eq!(channels[3].status, FrequencyStatus::Disabled);
+
+    let printed = pretty_print_band_lists(&bands, 4);
+    assert!(printed.contains("[5180 (36)]"));
+    assert!(printed.contains("[5260 (52) PR!I]"));
+    assert!(printed.contains("[5745 (149) P]"));
+    assert!(!printed.contains("5865"));
+    assert!(printed.contains("P: passive only"));
+}
+
+#[test]
+fn band_capabilities_are_decoded() {
+    let rate = |index: u16, rate: u32, short_preamble: bool| {
+        let mut attrs = vec![nlattr(
+            u16::from(Nl80211BitrateAttr::BitrateAttrRate),
+            &rate.to_ne_bytes(),
+        )];
+        if short_preamble {
+            attrs.push(nlattr(
+                u16::from(Nl80211BitrateAttr::BitrateAttr2ghzShortpreamble),
+                &[],
+            ));
+        }
+        nested(index, &attrs)
+    };
+    let band_attr = |attr: Nl80211BandAttr, payload: &[u8]| nlattr(u16::from(attr), payload);
+    let mut ht_mcs_set = [0u8; 16];
+    ht_mcs_set[..2].copy_from_slice(&[0xff, 0xff]);
+    let mut vht_mcs_set = Vec::new();
+    for word in [0xfffau16, 0, 0xfffa, 0] {
+        vht_mcs_set.extend_from_slice(&word.to_le_bytes());
+    }
+    let band = nested(
+        attr_type(Nl80211Attr::AttrWiphyBands),
+        &[nested(
+            u16::from(Nl80211Bandc::Band5ghz),
+            &[
+                nested(
+                    u16::from(Nl80211BandAttr::BandAttrFreqs),
+                    &[frequency_entry(0, 5180)],
+                ),
+                nested(
+                    u16::from(Nl80211BandAttr::BandAttrRates),
+                    &[rate(0, 60, false), rate(1, 540, false)],
+                ),
+                band_attr(Nl80211BandAttr::BandAttrHtMcsSet, &ht_mcs_set),
+                band_attr(Nl80211BandAttr::BandAttrHtCapa, &0x19efu16.to_ne_bytes()),
+                band_attr(Nl80211BandAttr::BandAttrHtAmpduFactor, &[3]),
+                band_attr(Nl80211BandAttr::BandAttrHtAmpduDensity, &[5]),
+                band_attr(Nl80211BandAttr::BandAttrVhtMcsSet, &vht_mcs_set),
+                band_attr(
+                    Nl80211BandAttr::BandAttrVhtCapa,
+                    &0x039071f6u32.to_ne_bytes(),
+                ),
+            ],
+        )],
+    );
+    let attrs = [
+        nlattr(attr_type(Nl80211Attr::AttrWiphy), &0u32.to_ne_bytes()),
+        name_attr(Nl80211Attr::AttrWiphyName, "phy0"),
+        band,
+    ];
+    let messages = decode_messages(&message(Nl80211Cmd::CmdNewWiphy, &attrs.concat())).unwrap();
+    let bands = decode_wiphy(&messages).unwrap().frequency_list.unwrap();
+    let band = &bands[0];
+
+    assert_eq!(band.channels.len(), 1);
+    let rates: Vec<f32> = band.bitrates.iter().map(|rate| rate.mbps()).collect();
+    assert_eq!(rates, vec![6.0, 54.0]);
+
+    let ht = band.ht.as_ref().unwrap();
+    assert!(ht.ldpc && ht.ht40 && ht.sgi20 && ht.sgi40 && ht.tx_stbc && !ht.greenfield);
+    assert_eq!(ht.rx_stbc, 1);
+    assert_eq!(ht.max_amsdu_len, 7935);
+    assert_eq!(ht.max_ampdu_len(), 65535);
+    assert_eq!(ht.min_mpdu_spacing_ns(), 4000);
+    assert_eq!(ht.mcs_indexes(), (0..16).collect::<Vec<u8>>());
+    assert_eq!(ht.max_rate_mbps().map(|rate| rate.round()), Some(300.0));
+
+    let vht = band.vht.as_ref().unwrap();
+    assert_eq!(vht.max_mpdu_len, 11454);
+    assert!(vht.vht160 && !vht.vht80p80);
+    assert!(vht.rx_ldpc && vht.sgi80 && vht.sgi160 && vht.tx_stbc);
+    assert!(!vht.su_beamformer && vht.su_beamformee && !vht.mu_beamformer && vht.mu_beamformee);
+    assert_eq!(vht.beamformee_sts, 4);
+    assert_eq!(vht.max_ampdu_len(), 1048575);
+    assert_eq!(vht.rx_mcs[..3], [Some(9), Some(9), None]);
+    assert_eq!(vht.rx_streams(), 2);
+    assert_eq!(vht.max_rate_mbps().map(|rate| rate.round()), Some(1733.0));
+    assert!(vht.summary().contains("2ss MCS 0-9"));
+}
+
+/// A 6 GHz band with HE, and optionally EHT, capabilities for stations.
+fn he_6ghz_wiphy(he_widths: u8, eht_mcs_set: Option<&[u8]>) -> Vec<u8> {
+    let iftype_attr =
+        |attr: Nl80211BandIftypeAttr, payload: &[u8]| nlattr(u16::from(attr), payload);
+    let mut he_mcs_set = Vec::new();
+    for word in [0xfffau16, 0xfffa, 0xfffa, 0xfffa] {
+        he_mcs_set.extend_from_slice(&word.to_le_bytes());
+    }
+    let mut data = vec![
+        nested(
+            u16::from(Nl80211BandIftypeAttr::BandIftypeAttrIftypes),
+            &[nlattr(u16::from(Nl80211Iftype::IftypeStation), &[])],
+        ),
+        iftype_attr(
+            Nl80211BandIftypeAttr::BandIftypeAttrHeCapMac,
+            &[0x02, 0, 0, 0, 0, 0],
+        ),
+        iftype_attr(
+            Nl80211BandIftypeAttr::BandIftypeAttrHeCapPhy,
+            &[he_widths, 0x20, 0, 0, 0x01, 0, 0x80, 0, 0, 0, 0],
+        ),
+        iftype_attr(
+            Nl80211BandIftypeAttr::BandIftypeAttrHeCapMcsSet,
+            &he_mcs_set,
+        ),
+        iftype_attr(
+            Nl80211BandIftypeAttr::BandIftypeAttrHeCapPpe,
+            &[0x39, 0x1c, 0xc7, 0x71, 0x1c, 0x07],
+        ),
+        iftype_attr(
+            Nl80211BandIftypeAttr::BandIftypeAttrHe6ghzCapa,
+            &0x06b8u16.to_ne_bytes(),
+        ),
+    ];
+    if let Some(eht_mcs_set) = eht_mcs_set {
+        data.extend([
+            iftype_attr(Nl80211BandIftypeAttr::BandIftypeAttrEhtCapMac, &[0x02, 0]),
+            iftype_attr(
+                Nl80211BandIftypeAttr::BandIftypeAttrEhtCapPhy,
+                &[0x42, 0, 0, 0, 0, 0, 0, 0, 0],
+            ),
+            iftype_attr(
+                Nl80211BandIftypeAttr::BandIftypeAttrEhtCapMcsSet,
+                eht_mcs_set,
+            ),
+        ]);
+    }
+    let band = nested(
+        attr_type(Nl80211Attr::AttrWiphyBands),
+        &[nested(
+            u16::from(Nl80211Bandc::Band6ghz),
+            &[
+                nested(
+                    u16::from(Nl80211BandAttr::BandAttrFreqs),
+                    &[frequency_entry(0, 5955)],
+                ),
+                nested(
+                    u16::from(Nl80211BandAttr::BandAttrIftypeData),
+                    &[nested(0, &data)],
+                ),
+            ],
+        )],
+    );
+    let attrs = [
+        nlattr(attr_type(Nl80211Attr::AttrWiphy), &0u32.to_ne_bytes()),
+        name_attr(Nl80211Attr::AttrWiphyName, "phy0"),
+        band,
+    ];
+    message(Nl80211Cmd::CmdNewWiphy, &attrs.concat())
+}
+
+#[test]
+fn he_and_eht_capabilities_are_decoded() {
+    let messages = decode_messages(&he_6ghz_wiphy(0x0c, Some(&[0x22; 9]))).unwrap();
+    let phy = decode_wiphy(&messages).unwrap();
+    assert_eq!(phy.wifi_generation(), Some("Wi-Fi 7"));
+    let band = &phy.frequency_list.as_ref().unwrap()[0];
+    assert!(band.iftype_capabilities(Nl80211Iftype::IftypeAp).is_none());
+    let data = band
+        .iftype_capabilities(Nl80211Iftype::IftypeStation)
+        .unwrap();
+
+    let he = data.he.as_ref().unwrap();
+    assert!(he.he40_80_5ghz && he.he160 && !he.he80p80 && !he.he40_2ghz);
+    assert!(he.ldpc && he.su_beamformee && !he.su_beamformer && he.twt_requester);
+    assert_eq!(he.mcs_80.rx[..3], [Some(11), Some(11), None]);
+    assert_eq!(he.mcs_160.as_ref().map(|mcs| mcs.rx_streams()), Some(2));
+    assert_eq!(he.mcs_80p80, None);
+    assert_eq!(he.ppe_thresholds.len(), 6);
+    assert_eq!(he.max_rate_mbps().map(|rate| rate.round()), Some(2402.0));
+
+    let he_6ghz = data.he_6ghz.as_ref().unwrap();
+    assert_eq!(he_6ghz.max_ampdu_exp, 7);
+    assert_eq!(he_6ghz.max_mpdu_len, 11454);
+    assert_eq!(he_6ghz.sm_power_save, 3);
+
+    let eht = data.eht.as_ref().unwrap();
+    assert!(eht.eht320 && eht.su_beamformee && eht.om_control);
+    assert_eq!(
+        eht.mcs_320.as_ref().and_then(|mcs| mcs.rx_max_mcs()),
+        Some(13)
+    );
+    assert_eq!(eht.mcs_80.as_ref().map(|mcs| mcs.mcs_12_13), Some((2, 2)));
+    assert!(eht.ppe_thresholds.is_empty());
+
+    let messages = decode_messages(&he_6ghz_wiphy(0x0c, None)).unwrap();
+    assert_eq!(
+        decode_wiphy(&messages).unwrap().wifi_generation(),
+        Some("Wi-Fi 6E")
+    );
+}
+
+#[test]
+fn twenty_mhz_only_eht_mcs_map_is_decoded() {
+    let messages = decode_messages(&he_6ghz_wiphy(0, Some(&[0x22, 0x22, 0x11, 0]))).unwrap();
+    let phy = decode_wiphy(&messages).unwrap();
+    let band = &phy.frequency_list.as_ref().unwrap()[0];
+    let data = band
+        .iftype_capabilities(Nl80211Iftype::IftypeStation)
+        .unwrap();
+    let he = data.he.as_ref().unwrap();
+    assert!(!he.he40_80_5ghz && !he.he160 && he.mcs_160.is_none());
+
+    let eht = data.eht.as_ref().unwrap();
+    assert_eq!(eht.mcs_80, None);
+    let mcs_20 = eht.mcs_20.as_ref().unwrap();
+    assert_eq!(mcs_20.mcs_0_7, (2, 2));
+    assert_eq!(mcs_20.mcs_10_11, (1, 1));
+    assert_eq!(mcs_20.mcs_12_13, (0, 0));
+    assert_eq!(mcs_20.rx_max_mcs(), Some(11));
+    assert!(eht.summary().contains("2ss MCS 0-11"));
+}
+
+#[test]
+fn wiphy_capabilities_are_decoded() {
+    let u8_attr = |attr: Nl80211Attr, value: u8| nlattr(attr_type(attr), &[value]);
+    let u16_attr = |attr: Nl80211Attr, value: u16| nlattr(attr_type(attr), &value.to_ne_bytes());
+    let u32_attr = |attr: Nl80211Attr, value: u32| nlattr(attr_type(attr), &value.to_ne_bytes());
+    let suites = |attr: Nl80211Attr, suites: &[u32]| {
+        let bytes: Vec<u8> = suites
+            .iter()
+            .flat_map(|suite| suite.to_ne_bytes())
+            .collect();
+        nlattr(attr_type(attr), &bytes)
+    };
+    let commands: Vec<Vec<u8>> = [Nl80211Cmd::CmdGetWiphy, Nl80211Cmd::CmdTriggerScan]
+        .iter()
+        .enumerate()
+        .map(|(i, cmd)| nlattr(i as u16 + 1, &(u8::from(*cmd) as u32).to_ne_bytes()))
+        .collect();
+    let mut ext_features = [0u8; 9];
+    ext_features[0] = 1 << 1; // ExtFeatureRrm
+    ext_features[8] = 1 << 3; // ExtFeatureDfsConcurrent
+    let combination = nested(1, &[]);
+
+    let attrs = [
+        u32_attr(Nl80211Attr::AttrWiphy, 1),
+        name_attr(Nl80211Attr::AttrWiphyName, "phy1"),
+        u8_attr(Nl80211Attr::AttrWiphyRetryShort, 7),
+        u8_attr(Nl80211Attr::AttrWiphyRetryLong, 4),
+        u32_attr(Nl80211Attr::AttrWiphyFragThreshold, u32::MAX),
+        u32_attr(Nl80211Attr::AttrWiphyRtsThreshold, 2347),
+        u8_attr(Nl80211Attr::AttrWiphyCoverageClass, 0),
+        u8_attr(Nl80211Attr::AttrMaxNumScanSsids, 20),
+        u16_attr(Nl80211Attr::AttrMaxScanIeLen, 422),
+        u8_attr(Nl80211Attr::AttrMaxNumSchedScanSsids, 20),
+        u16_attr(Nl80211Attr::AttrMaxSchedScanIeLen, 422),
+        u8_attr(Nl80211Attr::AttrMaxMatchSets, 11),
+        suites(
+            Nl80211Attr::AttrCipherSuites,
+            &[0x000f_ac04, 0x000f_ac09, 0x1234_5678],
+        ),
+        suites(Nl80211Attr::AttrAkmSuites, &[0x000f_ac02, 0x000f_ac08]),
+        u32_attr(Nl80211Attr::AttrWiphyAntennaAvailTx, 3),
+        u32_attr(Nl80211Attr::AttrWiphyAntennaAvailRx, 3),
+        u32_attr(Nl80211Attr::AttrWiphyAntennaTx, 1),
+        u32_attr(Nl80211Attr::AttrWiphyAntennaRx, 3),
+        nested(attr_type(Nl80211Attr::AttrSupportedCommands), &commands),
+        u32_attr(Nl80211Attr::AttrMaxRemainOnChannelDuration, 5000),
+        nested(
+            attr_type(Nl80211Attr::AttrSoftwareIftypes),
+            &[nlattr(u16::from(Nl80211Iftype::IftypeMonitor), &[])],
+        ),
+        nested(
+            attr_type(Nl80211Attr::AttrInterfaceCombinations),
+            &[combination.clone(), combination],
+        ),
+        u32_attr(
+            Nl80211Attr::AttrFeatureFlags,
+            (1 << 17) | (1 << 29) | (1 << 13),
+        ),
+        nlattr(attr_type(Nl80211Attr::AttrExtFeatures), &ext_features),
+    ];
+    let messages = decode_messages(&message(Nl80211Cmd::CmdNewWiphy, &attrs.concat())).unwrap();
+    let phy = decode_wiphy(&messages).unwrap();
+
+    assert_eq!((phy.retry_short, phy.retry_long), (Some(7), Some(4)));
+    assert_eq!(
+        (phy.frag_threshold, phy.rts_threshold),
+        (Some(u32::MAX), Some(2347))
+    );
+    assert_eq!(phy.coverage_class, Some(0));
+    assert_eq!(
+        (phy.max_scan_ssids, phy.max_scan_ie_len),
+        (Some(20), Some(422))
+    );
+    assert_eq!(
+        (phy.max_sched_scan_ssids, phy.max_sched_scan_ie_len),
+        (Some(20), Some(422))
+    );
+    assert_eq!(phy.max_match_sets, Some(11));
+    assert_eq!(phy.max_remain_on_channel_duration, Some(5000));
+    assert_eq!(
+        (phy.antenna_avail_tx, phy.antenna_avail_rx),
+        (Some(3), Some(3))
+    );
+    assert_eq!((phy.antenna_tx, phy.antenna_rx), (Some(1), Some(3)));
+
+    let ciphers: Vec<&str> = phy
+        .cipher_suites
+        .unwrap()
+        .into_iter()
+        .map(cipher_suite_name)
+        .collect();
+    assert_eq!(ciphers, vec!["CCMP-128", "GCMP-256", "Unknown"]);
+    let akms: Vec<&str> = phy
+        .akm_suites
+        .unwrap()
+        .into_iter()
+        .map(akm_suite_name)
+        .collect();
+    assert_eq!(akms, vec!["PSK", "SAE"]);
+    assert_eq!(
+        phy.supported_commands,
+        Some(vec![Nl80211Cmd::CmdGetWiphy, Nl80211Cmd::CmdTriggerScan])
+    );
+    assert_eq!(
+        phy.software_iftypes,
+        Some(vec![Nl80211Iftype::IftypeMonitor])
+    );
+    assert_eq!(phy.num_interface_combinations, Some(2));
+
+    let feature_flags = phy.feature_flags.unwrap();
+    assert!(feature_flags.contains(Nl80211FeatureFlags::FeatureActiveMonitor));
+    assert!(feature_flags.contains(Nl80211FeatureFlags::FeatureScanRandomMacAddr));
+    assert!(!feature_flags.contains(Nl80211FeatureFlags::FeatureSae));
+    assert_eq!(feature_flags.flags().len(), 3);
+    assert_eq!(phy.active_monitor, Some(true));
+
+    let ext_features = phy.ext_features.unwrap();
+    assert!(ext_features.contains(Nl80211ExtFeatureIndex::ExtFeatureRrm));
+    assert!(!ext_features.contains(Nl80211ExtFeatureIndex::ExtFeatureVhtIbss));
+    assert!(!ext_features.contains(Nl80211ExtFeatureIndex::ExtFeatureSppAmsduSupport));
+    assert_eq!(
+        ext_features.features(),
+        vec![
+            Nl80211ExtFeatureIndex::ExtFeatureRrm,
+            Nl80211ExtFeatureIndex::ExtFeatureDfsConcurrent
+        ]
+    );
+}
+
+#[test]
+fn malformed_capabilities_are_skipped() {
+    let commands = [
+        nlattr(1, &(u8::from(Nl80211Cmd::CmdGetWiphy) as u32).to_ne_bytes()),
+        nlattr(2, &[0x01]),
+    ];
+    let attrs = [
+        nlattr(attr_type(Nl80211Attr::AttrWiphy), &1u32.to_ne_bytes()),
+        name_attr(Nl80211Attr::AttrWiphyName, "phy1"),
+        nlattr(attr_type(Nl80211Attr::AttrMaxNumScanSsids), &[]),
+        nested(attr_type(Nl80211Attr::AttrSupportedCommands), &commands),
+        nlattr(attr_type(Nl80211Attr::AttrFeatureFlags), &[0x00, 0x00]),
+        nlattr(attr_type(Nl80211Attr::AttrWiphyRetryShort), &[7]),
+    ];
+    let messages = decode_messages(&message(Nl80211Cmd::CmdNewWiphy, &attrs.concat())).unwrap();
+    let phy = decode_wiphy(&messages).unwrap();
+
+    assert_eq!(phy.phy_name.as_deref(), Some("phy1"));
+    assert_eq!(phy.max_scan_ssids, None);
+    assert_eq!(phy.supported_commands, None);
+    assert_eq!(phy.feature_flags, None);
+    assert_eq!(phy.active_monitor, None);
+    assert_eq!(phy.retry_short, Some(7));
+}
+
+/// A phy like iwlwifi's: one station, one AP or P2P interface and a P2P device on up to two
+/// channels, monitor in software.
+fn combination_wiphy() -> Vec<u8> {
+    let limit = |index: u16, max: u32, iftypes: &[Nl80211Iftype]| {
+        let flags: Vec<Vec<u8>> = iftypes
+            .iter()
+            .map(|iftype| nlattr(u16::from(*iftype), &[]))
+            .collect();
+        nested(
+            index,
+            &[
+                nlattr(
+                    u16::from(Nl80211IfaceLimitAttrs::IfaceLimitMax),
+                    &max.to_ne_bytes(),
+                ),
+                nested(u16::from(Nl80211IfaceLimitAttrs::IfaceLimitTypes), &flags),
+            ],
+        )
+    };
+    let combination_attr =
+        |attr: Nl80211IfCombinationAttrs, value: u32| nlattr(u16::from(attr), &value.to_ne_bytes());
+    let combination = nested(
+        1,
+        &[
+            nested(
+                u16::from(Nl80211IfCombinationAttrs::IfaceCombLimits),
+                &[
+                    limit(1, 1, &[Nl80211Iftype::IftypeStation]),
+                    limit(
+                        2,
+                        1,
+                        &[
+                            Nl80211Iftype::IftypeAp,
+                            Nl80211Iftype::IftypeP2pClient,
+                            Nl80211Iftype::IftypeP2pGo,
+                        ],
+                    ),
+                    limit(3, 1, &[Nl80211Iftype::IftypeP2pDevice]),
+                ],
+            ),
+            combination_attr(Nl80211IfCombinationAttrs::IfaceCombMaxnum, 3),
+            nlattr(
+                u16::from(Nl80211IfCombinationAttrs::IfaceCombStaApBiMatch),
+                &[],
+            ),
+            combination_attr(Nl80211IfCombinationAttrs::IfaceCombNumChannels, 2),
+        ],
+    );
+    let attrs = [
+        nlattr(attr_type(Nl80211Attr::AttrWiphy), &0u32.to_ne_bytes()),
+        name_attr(Nl80211Attr::AttrWiphyName, "phy0"),
+        nested(
+            attr_type(Nl80211Attr::AttrInterfaceCombinations),
+            &[combination],
+        ),
+        nested(
+            attr_type(Nl80211Attr::AttrSoftwareIftypes),
+            &[nlattr(u16::from(Nl80211Iftype::IftypeMonitor), &[])],
+        ),
+    ];
+    message(Nl80211Cmd::CmdNewWiphy, &attrs.concat())
+}
+
+#[test]
+fn interface_combinations_are_checked() {
+    let messages = decode_messages(&combination_wiphy()).unwrap();
+    let mut phy = decode_wiphy(&messages).unwrap();
+
+    let combinations = phy.interface_combinations.as_ref().unwrap();
+    assert_eq!(combinations.len(), 1);
+    let combination = &combinations[0];
+    assert_eq!(combination.limits.len(), 3);
+    assert_eq!(combination.limits[1].max, 1);
+    assert_eq!(combination.limits[1].iftypes.len(), 3);
+    assert_eq!(
+        (combination.max_interfaces, combination.num_channels),
+        (3, 2)
+    );
+    assert!(combination.beacon_int_infra_match);
+    assert_eq!(combination.beacon_int_min_gcd, None);
+    assert!(!combination.supports_radar_detect(Nl80211ChanWidth::ChanWidth80));
+
+    use Nl80211Iftype::*;
+    // Monitor is a software interface type
+    assert!(phy.can_run(&ConcurrencyRequest::new(&[IftypeStation, IftypeMonitor], 2)));
+    assert!(phy.can_run(&ConcurrencyRequest::new(
+        &[IftypeStation, IftypeAp, IftypeP2pDevice],
+        2
+    )));
+    assert!(!phy.can_run(&ConcurrencyRequest::new(&[IftypeStation, IftypeStation], 1)));
+    assert!(!phy.can_run(&ConcurrencyRequest::new(
+        &[IftypeStation, IftypeAp, IftypeP2pGo],
+        1
+    )));
+    assert!(!phy.can_run(&ConcurrencyRequest::new(&[IftypeStation, IftypeAp], 3)));
+    assert!(!phy.can_run(&ConcurrencyRequest::new(
+        &[IftypeStation, IftypeMeshPoint],
+        1
+    )));
+    assert!(!phy.can_run(
+        &ConcurrencyRequest::new(&[IftypeStation, IftypeAp], 1)
+            .with_radar_detect(Nl80211ChanWidth::ChanWidth80)
+    ));
+
+    let mut station = Interface::new(0);
+    station.current_iftype = Some(IftypeStation);
+    station.frequency.frequency = Some(2412);
+    let running = ConcurrencyRequest::from_interfaces(&[station]);
+    assert_eq!(running.num_channels, 1);
+    assert!(phy.can_run(&running.clone().with_interface(IftypeAp)));
+    assert!(!phy.can_run(&running.with_interface(IftypeStation)));
+
+    // A single interface needs no combination
+    phy.interface_combinations = None;
+    phy.software_iftypes = None;
+    assert!(phy.can_run(&ConcurrencyRequest::new(&[IftypeMonitor], 1)));
+    assert!(!phy.can_run(&ConcurrencyRequest::new(&[IftypeStation, IftypeMonitor], 1)));
+}
+
+#[test]
+fn radar_detection_is_checked_per_width_and_region() {
+    use Nl80211Iftype::*;
+    let mut combination = InterfaceCombination {
+        limits: vec![InterfaceLimit {
+            max: 1,
+            iftypes: vec![IftypeAp],
+        }],
+        max_interfaces: 1,
+        num_channels: 1,
+        radar_detect_widths: (1 << u16::from(Nl80211ChanWidth::ChanWidth20))
+            | (1 << u16::from(Nl80211ChanWidth::ChanWidth80)),
+        ..Default::default()
+    };
+    assert!(combination.supports_radar_detect(Nl80211ChanWidth::ChanWidth80));
+    assert!(!combination.supports_radar_detect(Nl80211ChanWidth::ChanWidth160));
+    // Widths past the bitmap aren't supported instead of overflowing the shift
+    assert!(!combination.supports_radar_detect(Nl80211ChanWidth::UnrecognizedConst(40)));
+    assert!(combination.supports_radar_detect_region(Nl80211DfsRegions::DfsEtsi));
+
+    let ap = ConcurrencyRequest::new(&[IftypeAp], 1);
+    let etsi = ap
+        .clone()
+        .with_radar_detect(Nl80211ChanWidth::ChanWidth80)
+        .with_radar_detect_region(Nl80211DfsRegions::DfsEtsi);
+    assert!(combination.allows(&etsi, &[]));
+    assert!(!combination.allows(
+        &ap.clone()
+            .with_radar_detect(Nl80211ChanWidth::ChanWidth160)
+            .with_radar_detect_region(Nl80211DfsRegions::DfsEtsi),
+        &[]
+    ));
+
+    combination.radar_detect_regions = 1 << u16::from(Nl80211DfsRegions::DfsFcc);
+    assert!(!combination.supports_radar_detect_region(Nl80211DfsRegions::DfsEtsi));
+    assert!(!combination.supports_radar_detect_region(Nl80211DfsRegions::UnrecognizedConst(40)));
+    assert!(!combination.allows(&etsi, &[]));
+    assert!(combination.allows(
+        &ap.clone()
+            .with_radar_detect(Nl80211ChanWidth::ChanWidth80)
+            .with_radar_detect_region(Nl80211DfsRegions::DfsFcc),
+        &[]
+    ));
+    // Without a region only the width is checked, and without radar detection neither
+    assert!(combination.allows(
+        &ap.clone().with_radar_detect(Nl80211ChanWidth::ChanWidth20),
+        &[]
+    ));
+    assert!(combination.allows(
+        &ap.with_radar_detect_region(Nl80211DfsRegions::DfsEtsi),
+        &[]
+    ));
+}
+
+#[test]
+fn bssids_must_be_six_bytes() {
+    let scan_result = |bssid: &[u8]| {
+        let bss = nested(
+            attr_type(Nl80211Attr::AttrBss),
+            &[
+                nlattr(u16::from(Nl80211Bss::BssBssid), bssid),
+                nlattr(u16::from(Nl80211Bss::BssFrequency), &2437u32.to_ne_bytes()),
+            ],
+        );
+        decode_messages(&message(Nl80211Cmd::CmdNewScanResults, &bss)).unwrap()
+    };
+
+    let messages = scan_result(&[2, 0, 0, 0, 0, 1]);
+    let bss = decode_bss(&messages[0]).unwrap().unwrap();
+    assert_eq!(bss.bssid, [2, 0, 0, 0, 0, 1]);
+    assert_eq!((bss.frequency, bss.channel), (2437, 6));
+
+    for bssid in [&[2, 0, 0, 0, 0][..], &[2, 0, 0, 0, 0, 1, 0, 0]] {
+        let messages = scan_result(bssid);
+        assert!(matches!(
+            decode_bss(&messages[0]),
+            Err(Nl80211Error::Parse(_))
+        ));
+    }
+}
+
+/// A CMD_NEW_STATION message for 02:00:00:00:00:01 on interface 3
+fn station_message(sta_info: &[Vec<u8>]) -> Vec<u8> {
+    let attrs = [
+        nlattr(attr_type(Nl80211Attr::AttrIfindex), &3u32.to_ne_bytes()),
+        nlattr(attr_type(Nl80211Attr::AttrMac), &[2, 0, 0, 0, 0, 1]),
+        nested(attr_type(Nl80211Attr::AttrStaInfo), sta_info),
+    ];
+    message(Nl80211Cmd::CmdNewStation, &attrs.concat())
+}
+
+fn sta_attr(attr: Nl80211StaInfo, payload: &[u8]) -> Vec<u8> {
+    nlattr(u16::from(attr), payload)
+}
+
+fn rate_attr(attr: Nl80211RateInfo, payload: &[u8]) -> Vec<u8> {
+    nlattr(u16::from(attr), payload)
+}
+
+#[test]
+fn station_byte_counters_prefer_64_bits() {
+    // (attributes, rx_bytes, tx_bytes)
+    let cases = [
+        (
+            vec![
+                sta_attr(Nl80211StaInfo::StaInfoRxBytes, &1000u32.to_ne_bytes()),
+                sta_attr(Nl80211StaInfo::StaInfoTxBytes, &2000u32.to_ne_bytes()),
+            ],
+            Some(1000),
+            Some(2000),
+        ),
+        (
+            vec![
+                sta_attr(Nl80211StaInfo::StaInfoRxBytes, &5u32.to_ne_bytes()),
+                sta_attr(
+                    Nl80211StaInfo::StaInfoRxBytes64,
+                    &5_000_000_000u64.to_ne_bytes(),
+                ),
+                sta_attr(
+                    Nl80211StaInfo::StaInfoTxBytes64,
+                    &6_000_000_000u64.to_ne_bytes(),
+                ),
+                sta_attr(Nl80211StaInfo::StaInfoTxBytes, &7u32.to_ne_bytes()),
+            ],
+            Some(5_000_000_000),
+            Some(6_000_000_000),
+        ),
+        (vec![], None, None),
+    ];
+    for (sta_info, rx_bytes, tx_bytes) in cases {
+        let messages = decode_messages(&station_message(&sta_info)).unwrap();
+        let station = decode_station(&messages[0]).unwrap();
+        assert_eq!(station.mac, [2, 0, 0, 0, 0, 1]);
+        assert_eq!(station.interface_index, Some(3));
+        assert_eq!((station.rx_bytes, station.tx_bytes), (rx_bytes, tx_bytes));
+    }
+}
+
+#[test]
+fn station_bitrates_are_decoded() {
+    let sta_info = [
+        nested(
+            u16::from(Nl80211StaInfo::StaInfoTxBitrate),
+            &[
+                rate_attr(Nl80211RateInfo::RateInfoBitrate32, &8667u32.to_ne_bytes()),
+                rate_attr(Nl80211RateInfo::RateInfoBitrate, &65535u16.to_ne_bytes()),
+                rate_attr(Nl80211RateInfo::RateInfoVhtMcs, &[9]),
+                rate_attr(Nl80211RateInfo::RateInfoVhtNss, &[2]),
+                rate_attr(Nl80211RateInfo::RateInfo80MhzWidth, &[]),
+                rate_attr(Nl80211RateInfo::RateInfoShortGi, &[]),
+            ],
+        ),
+        nested(
+            u16::from(Nl80211StaInfo::StaInfoRxBitrate),
+            &[
+                rate_attr(Nl80211RateInfo::RateInfoBitrate, &650u16.to_ne_bytes()),
+                rate_attr(Nl80211RateInfo::RateInfoMcs, &[7]),
+            ],
+        ),
+    ];
+    let messages = decode_messages(&station_message(&sta_info)).unwrap();
+    let station = decode_station(&messages[0]).unwrap();
+
+    let tx = station.tx_bitrate.unwrap();
+    assert_eq!(tx.bitrate, Some(8667));
+    assert_eq!((tx.vht_mcs, tx.vht_nss), (Some(9), Some(2)));
+    assert_eq!(tx.width, Nl80211ChanWidth::ChanWidth80);
+    assert!(tx.short_gi && !tx.is_legacy());
+
+    // An MCS rate without width flag is a 20 MHz HT rate
+    let rx = station.rx_bitrate.unwrap();
+    assert_eq!((rx.bitrate, rx.mcs), (Some(650), Some(7)));
+    assert_eq!(rx.width, Nl80211ChanWidth::ChanWidth20);
+    assert!(!rx.short_gi);
+
+    // Every width flag, and legacy rates staying 20 MHz without HT
+    for (flag, width) in [
+        (None, Nl80211ChanWidth::ChanWidth20Noht),
+        (
+            Some(Nl80211RateInfo::RateInfo5MhzWidth),
+            Nl80211ChanWidth::ChanWidth5,
+        ),
+        (
+            Some(Nl80211RateInfo::RateInfo10MhzWidth),
+            Nl80211ChanWidth::ChanWidth10,
+        ),
+        (
+            Some(Nl80211RateInfo::RateInfo40MhzWidth),
+            Nl80211ChanWidth::ChanWidth40,
+        ),
+        (
+            Some(Nl80211RateInfo::RateInfo80p80MhzWidth),
+            Nl80211ChanWidth::ChanWidth80p80,
+        ),
+        (
+            Some(Nl80211RateInfo::RateInfo160MhzWidth),
+            Nl80211ChanWidth::ChanWidth160,
+        ),
+        (
+            Some(Nl80211RateInfo::RateInfo320MhzWidth),
+            Nl80211ChanWidth::ChanWidth320,
+        ),
+    ] {
+        let mut rate = vec![rate_attr(
+            Nl80211RateInfo::RateInfoBitrate,
+            &540u16.to_ne_bytes(),
+        )];
+        rate.extend(flag.map(|flag| rate_attr(flag, &[])));
+        let sta_info = [nested(u16::from(Nl80211StaInfo::StaInfoRxBitrate), &rate)];
+        let messages = decode_messages(&station_message(&sta_info)).unwrap();
+        let rx = decode_station(&messages[0]).unwrap().rx_bitrate.unwrap();
+        assert_eq!(rx.bitrate, Some(540));
+        assert_eq!(rx.width, width, "{:?}", flag);
+    }
+}
+
+#[test]
+fn station_tid_stats_are_indexed_from_one() {
+    let tid = |index: u16, stats: &[(Nl80211TidStats, u64)]| {
+        let attrs: Vec<Vec<u8>> = stats
+            .iter()
+            .map(|(attr, value)| nlattr(u16::from(*attr), &value.to_ne_bytes()))
+            .collect();
+        nested(index, &attrs)
+    };
+    let sta_info = [nested(
+        u16::from(Nl80211StaInfo::StaInfoTidStats),
+        &[
+            tid(
+                1,
+                &[
+                    (Nl80211TidStats::TidStatsRxMsdu, 10),
+                    (Nl80211TidStats::TidStatsTxMsdu, 20),
+                ],
+            ),
+            tid(
+                7,
+                &[
+                    (Nl80211TidStats::TidStatsTxMsduRetries, 3),
+                    (Nl80211TidStats::TidStatsTxMsduFailed, 1),
+                ],
+            ),
+            // Non-QoS traffic
+            tid(17, &[(Nl80211TidStats::TidStatsRxMsdu, 5)]),
+        ],
+    )];
+    let messages = decode_messages(&station_message(&sta_info)).unwrap();
+    let station = decode_station(&messages[0]).unwrap();
+
+    let tids: Vec<u16> = station.tid_stats.iter().map(|stats| stats.tid).collect();
+    assert_eq!(tids, [0, 6, 16]);
+    let tid0 = &station.tid_stats[0];
+    assert_eq!((tid0.rx_msdu, tid0.tx_msdu), (Some(10), Some(20)));
+    assert_eq!(tid0.tx_msdu_retries, None);
+    let tid6 = &station.tid_stats[1];
+    assert_eq!(
+        (tid6.tx_msdu_retries, tid6.tx_msdu_failed),
+        (Some(3), Some(1))
+    );
+    assert_eq!(station.tid_stats[2].rx_msdu, Some(5));
+}
+
+fn survey(
+    frequency: u32,
+    time: Option<u64>,
+    time_busy: Option<u64>,
+    noise: Option<i8>,
+) -> SurveyInfo {
+    SurveyInfo {
+        frequency,
+        channel: chan_from_frequency(frequency),
+        noise,
+        time,
+        time_busy,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn survey_utilization() {
+    assert_eq!(
+        survey(2412, Some(200), Some(50), None).utilization(),
+        Some(25.0)
+    );
+    assert_eq!(
+        survey(2412, Some(200), Some(0), None).utilization(),
+        Some(0.0)
+    );
+    assert_eq!(survey(2412, Some(0), Some(0), None).utilization(), None);
+    assert_eq!(survey(2412, Some(200), None, None).utilization(), None);
+    assert_eq!(survey(2412, None, Some(50), None).utilization(), None);
+}
+
+#[test]
+fn channels_are_ranked_by_utilization_then_noise() {
+    let band = |band: WiFiBand, frequencies: &[u32]| BandList {
+        band,
+        channels: frequencies
+            .iter()
+            .map(|&frequency| ChannelData {
+                frequency,
+                channel: chan_from_frequency(frequency),
+                ..Default::default()
+            })
+            .collect(),
+        ..Default::default()
+    };
+    let bands = [
+        band(WiFiBand::Band2GHz, &[2412, 2437, 2462]),
+        band(WiFiBand::Band5GHz, &[5180, 5200, 5220]),
+    ];
+    let surveys = [
+        survey(2412, Some(100), Some(50), Some(-90)),
+        survey(2437, Some(100), Some(20), Some(-85)),
+        survey(2462, Some(200), Some(40), Some(-95)),
+        // On channel but without busy time, and without noise floor either
+        survey(5180, Some(100), None, Some(-100)),
+        survey(5220, Some(100), None, None),
+        // Not a channel of the phy
+        survey(5500, Some(100), Some(0), Some(-100)),
+    ];
+
+    let ranked = rank_channels(&surveys, &bands);
+    let frequencies: Vec<u32> = ranked.iter().map(|entry| entry.channel.frequency).collect();
+    assert_eq!(frequencies, [2462, 2437, 2412, 5180, 5200, 5220]);
+    assert_eq!(ranked[0].utilization, Some(20.0));
+    assert_eq!(ranked[2].utilization, Some(50.0));
+    assert!(ranked[3..].iter().all(|entry| entry.utilization.is_none()));
+
+    // Channels without survey data keep their channel but have no figures
+    assert_eq!(ranked[4].survey.frequency, 5200);
+    assert_eq!(ranked[4].survey.channel, 40);
+    assert_eq!(
+        (ranked[4].survey.time, ranked[4].survey.noise),
+        (None, None)
+    );
+
+    assert_eq!(rank_channels(&[], &bands).len(), 6);
+    assert!(rank_channels(&surveys, &[]).is_empty());
+}
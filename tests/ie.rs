@@ -0,0 +1,313 @@
+use nl80211_ng::ie::{
+    parse_information_elements, AkmSuite, CipherSuite, CountryTriplet, InformationElement, Rsn,
+    SupportedRate, Tim, Wps, OUI_IEEE80211, OUI_MICROSOFT,
+};
+use proptest::prelude::*;
+
+/// The elements of a WPA2-PSK beacon from a 2.4 GHz 802.11n AP
+#[rustfmt::skip]
+const BEACON_IES: &[u8] = &[
+    // SSID "HomeNet"
+    0x00, 0x07, 0x48, 0x6f, 0x6d, 0x65, 0x4e, 0x65, 0x74,
+    // Supported Rates, 1 2 5.5 11 basic, 6 9 12 18
+    0x01, 0x08, 0x82, 0x84, 0x8b, 0x96, 0x0c, 0x12, 0x18, 0x24,
+    // DS Parameter Set, channel 6
+    0x03, 0x01, 0x06,
+    // TIM, DTIM period 1
+    0x05, 0x04, 0x00, 0x01, 0x00, 0x00,
+    // Country, US channels 1-11 at 30 dBm
+    0x07, 0x06, 0x55, 0x53, 0x20, 0x01, 0x0b, 0x1e,
+    // ERP
+    0x2a, 0x01, 0x00,
+    // RSN, CCMP group and pairwise, PSK
+    0x30, 0x14, 0x01, 0x00, 0x00, 0x0f, 0xac, 0x04, 0x01, 0x00, 0x00, 0x0f, 0xac, 0x04, 0x01, 0x00,
+    0x00, 0x0f, 0xac, 0x02, 0x0c, 0x00,
+    // Extended Supported Rates, 24 36 48 54
+    0x32, 0x04, 0x30, 0x48, 0x60, 0x6c,
+    // HT Capabilities, LDPC, 40 MHz, SGI 20/40, MCS 0-15
+    0x2d, 0x1a, 0x63, 0x00, 0x17, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    // WMM information element
+    0xdd, 0x07, 0x00, 0x50, 0xf2, 0x02, 0x00, 0x01, 0x80,
+    // Broadcom vendor element
+    0xdd, 0x09, 0x00, 0x10, 0x18, 0x02, 0x00, 0x00, 0x1c, 0x00, 0x00,
+];
+
+fn ssid(elements: &[InformationElement]) -> Option<&[u8]> {
+    elements.iter().find_map(|element| match element {
+        InformationElement::Ssid(ssid) => Some(&ssid[..]),
+        _ => None,
+    })
+}
+
+#[test]
+fn beacon_elements_are_decoded() {
+    let elements = parse_information_elements(BEACON_IES);
+    assert_eq!(elements.len(), 11);
+
+    assert_eq!(ssid(&elements), Some(&b"HomeNet"[..]));
+    let InformationElement::SupportedRates(rates) = &elements[1] else {
+        panic!("expected supported rates, got {:?}", elements[1]);
+    };
+    let mbps: Vec<f32> = rates.iter().map(SupportedRate::mbps).collect();
+    assert_eq!(mbps, vec![1.0, 2.0, 5.5, 11.0, 6.0, 9.0, 12.0, 18.0]);
+    assert!(rates[..4].iter().all(|rate| rate.basic));
+    assert!(rates[4..].iter().all(|rate| !rate.basic));
+    assert_eq!(elements[2], InformationElement::DsParameterSet(6));
+    assert_eq!(
+        elements[3],
+        InformationElement::Tim(Tim {
+            dtim_count: 0,
+            dtim_period: 1,
+            bitmap_control: 0,
+            partial_virtual_bitmap: vec![0],
+        })
+    );
+    let InformationElement::Country(country) = &elements[4] else {
+        panic!("expected country, got {:?}", elements[4]);
+    };
+    assert_eq!(country.country, "US");
+    assert_eq!(
+        country.triplets,
+        vec![CountryTriplet {
+            first_channel: 1,
+            num_channels: 11,
+            max_tx_power: 30,
+        }]
+    );
+    assert_eq!(
+        elements[5],
+        InformationElement::Unknown {
+            id: 42,
+            data: vec![0],
+        }
+    );
+
+    let InformationElement::Rsn(rsn) = &elements[6] else {
+        panic!("expected RSN, got {:?}", elements[6]);
+    };
+    assert_eq!(rsn.version, 1);
+    assert_eq!(rsn.group_cipher, Some(CipherSuite::Ccmp128));
+    assert_eq!(rsn.pairwise_ciphers, vec![CipherSuite::Ccmp128]);
+    assert_eq!(rsn.akm_suites, vec![AkmSuite::Psk]);
+    assert_eq!(rsn.capabilities, Some(0x000c));
+    assert!(!rsn.mfp_capable());
+    assert!(rsn.pmkids.is_empty());
+    assert_eq!(rsn.group_management_cipher, None);
+
+    let InformationElement::ExtendedSupportedRates(rates) = &elements[7] else {
+        panic!("expected extended supported rates, got {:?}", elements[7]);
+    };
+    assert_eq!(rates.len(), 4);
+    assert_eq!(rates[3].mbps(), 54.0);
+    let InformationElement::HtCapabilities(ht) = &elements[8] else {
+        panic!("expected HT capabilities, got {:?}", elements[8]);
+    };
+    assert!(ht.ldpc && ht.ht40 && ht.sgi20 && ht.sgi40);
+    assert_eq!(ht.mcs_indexes(), (0..16).collect::<Vec<u8>>());
+    assert_eq!(ht.max_amsdu_len, 3839);
+    assert_eq!(
+        elements[9],
+        InformationElement::Vendor {
+            oui: OUI_MICROSOFT,
+            data: vec![0x02, 0x00, 0x01, 0x80],
+        }
+    );
+    assert_eq!(
+        elements[10],
+        InformationElement::Vendor {
+            oui: [0x00, 0x10, 0x18],
+            data: vec![0x02, 0x00, 0x00, 0x1c, 0x00, 0x00],
+        }
+    );
+}
+
+#[test]
+fn truncated_elements_keep_the_complete_ones() {
+    // Cut inside the RSN element, everything before it survives
+    let elements = parse_information_elements(&BEACON_IES[..60]);
+    assert_eq!(elements.len(), 6);
+    assert_eq!(ssid(&elements), Some(&b"HomeNet"[..]));
+    assert!(!elements
+        .iter()
+        .any(|element| matches!(element, InformationElement::Rsn(_))));
+
+    // A lone ID without its length byte
+    assert_eq!(parse_information_elements(&[0x00]), vec![]);
+    // A length running past the end of the buffer
+    assert_eq!(
+        parse_information_elements(&[0x03, 0x01, 0x0b, 0x00, 0xff, 0x41]),
+        vec![InformationElement::DsParameterSet(11)]
+    );
+}
+
+#[test]
+fn zero_length_elements() {
+    let elements = parse_information_elements(&[
+        0x00, 0x00, 0x01, 0x00, 0x03, 0x00, 0x05, 0x00, 0xdd, 0x00, 0x30, 0x00,
+    ]);
+    assert_eq!(
+        elements,
+        vec![
+            // Hidden SSID
+            InformationElement::Ssid(vec![]),
+            InformationElement::SupportedRates(vec![]),
+            InformationElement::Unknown {
+                id: 3,
+                data: vec![]
+            },
+            InformationElement::Unknown {
+                id: 5,
+                data: vec![]
+            },
+            InformationElement::Unknown {
+                id: 221,
+                data: vec![]
+            },
+            InformationElement::Unknown {
+                id: 48,
+                data: vec![]
+            },
+        ]
+    );
+}
+
+#[test]
+fn short_and_overlong_rsn_elements() {
+    // Only the version and group cipher, the rest is optional
+    let rsn = Rsn::parse(&[0x01, 0x00, 0x00, 0x0f, 0xac, 0x02], OUI_IEEE80211).unwrap();
+    assert_eq!(rsn.group_cipher, Some(CipherSuite::Tkip));
+    assert!(rsn.pairwise_ciphers.is_empty());
+    assert_eq!(rsn.capabilities, None);
+
+    // Two pairwise ciphers announced, one present: kept as the raw element
+    let data = [
+        0x01, 0x00, 0x00, 0x0f, 0xac, 0x04, 0x02, 0x00, 0x00, 0x0f, 0xac, 0x04,
+    ];
+    assert_eq!(
+        InformationElement::parse(48, &data),
+        InformationElement::Unknown {
+            id: 48,
+            data: data.to_vec(),
+        }
+    );
+
+    // Trailing bytes after the group management cipher are ignored
+    let mut data = vec![
+        0x01, 0x00, 0x00, 0x0f, 0xac, 0x04, 0x01, 0x00, 0x00, 0x0f, 0xac, 0x04, 0x01, 0x00, 0x00,
+        0x0f, 0xac, 0x08, 0xc0, 0x00, 0x00, 0x00, 0x00, 0x0f, 0xac, 0x06,
+    ];
+    data.extend_from_slice(&[0xaa; 7]);
+    let rsn = Rsn::parse(&data, OUI_IEEE80211).unwrap();
+    assert_eq!(rsn.akm_suites, vec![AkmSuite::Sae]);
+    assert!(rsn.mfp_required() && rsn.mfp_capable());
+    assert_eq!(rsn.group_management_cipher, Some(CipherSuite::BipCmac128));
+}
+
+#[test]
+fn vendor_elements() {
+    // WPA1 with TKIP
+    let wpa = [
+        0x00, 0x50, 0xf2, 0x01, 0x01, 0x00, 0x00, 0x50, 0xf2, 0x02, 0x01, 0x00, 0x00, 0x50, 0xf2,
+        0x02, 0x01, 0x00, 0x00, 0x50, 0xf2, 0x02,
+    ];
+    let InformationElement::Wpa(wpa) = InformationElement::parse(221, &wpa) else {
+        panic!("expected WPA");
+    };
+    assert_eq!(wpa.pairwise_ciphers, vec![CipherSuite::Tkip]);
+    assert_eq!(wpa.akm_suites, vec![AkmSuite::Psk]);
+
+    // WPS with version, state and a device name whose length runs past the element
+    let wps = [
+        0x00, 0x50, 0xf2, 0x04, 0x10, 0x4a, 0x00, 0x01, 0x10, 0x10, 0x44, 0x00, 0x01, 0x02, 0x10,
+        0x11, 0x00, 0x20, 0x41, 0x50,
+    ];
+    assert_eq!(
+        InformationElement::parse(221, &wps),
+        InformationElement::Wps(Wps {
+            version: Some(0x10),
+            state: Some(2),
+            ..Default::default()
+        })
+    );
+
+    // Too short for an OUI
+    assert_eq!(
+        InformationElement::parse(221, &[0x00, 0x50]),
+        InformationElement::Unknown {
+            id: 221,
+            data: vec![0x00, 0x50],
+        }
+    );
+}
+
+#[test]
+fn vht_capabilities_element() {
+    // 7991 byte MPDUs, 160 MHz, RX LDPC, SGI80, two streams up to MCS 9
+    let data = [
+        0x35, 0x00, 0x00, 0x00, 0xfa, 0xff, 0x00, 0x00, 0xfa, 0xff, 0x00, 0x00,
+    ];
+    let InformationElement::VhtCapabilities(vht) = InformationElement::parse(191, &data) else {
+        panic!("expected VHT capabilities");
+    };
+    assert_eq!(vht.max_mpdu_len, 7991);
+    assert!(vht.vht160 && !vht.vht80p80);
+    assert!(vht.rx_ldpc && vht.sgi80 && !vht.sgi160);
+    assert_eq!(vht.rx_streams(), 2);
+    assert_eq!(vht.rx_mcs[..3], [Some(9), Some(9), None]);
+
+    // The MCS and NSS set is missing
+    assert!(matches!(
+        InformationElement::parse(191, &data[..8]),
+        InformationElement::Unknown { id: 191, .. }
+    ));
+}
+
+#[test]
+fn he_capabilities_element() {
+    // 40/80 and 160 MHz, LDPC, two streams up to MCS 11, with PPE thresholds
+    #[rustfmt::skip]
+    let data = [
+        0x23,
+        0x02, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x0c, 0x20, 0x00, 0x00, 0x01, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00,
+        0xfa, 0xff, 0xfa, 0xff, 0xfa, 0xff, 0xfa, 0xff,
+        0x39, 0x1c, 0xc7, 0x71,
+    ];
+    let InformationElement::HeCapabilities(he) = InformationElement::parse(255, &data) else {
+        panic!("expected HE capabilities");
+    };
+    assert!(he.he40_80_5ghz && he.he160 && !he.he80p80);
+    assert!(he.ldpc && he.twt_requester);
+    assert_eq!(he.mcs_80.rx[..3], [Some(11), Some(11), None]);
+    assert_eq!(he.mcs_160.as_ref().map(|mcs| mcs.rx_streams()), Some(2));
+    assert_eq!(he.ppe_thresholds, [0x39, 0x1c, 0xc7, 0x71]);
+
+    // The 160 MHz MCS and NSS set is missing
+    assert!(matches!(
+        InformationElement::parse(255, &data[..22]),
+        InformationElement::Unknown { id: 255, .. }
+    ));
+}
+
+proptest! {
+    #[test]
+    fn arbitrary_elements_dont_panic(bytes in prop::collection::vec(any::<u8>(), 0..512)) {
+        parse_information_elements(&bytes);
+    }
+
+    #[test]
+    fn every_element_body_parses(id in any::<u8>(), data in prop::collection::vec(any::<u8>(), 0..64)) {
+        let mut bytes = vec![id, data.len() as u8];
+        bytes.extend_from_slice(&data);
+        prop_assert_eq!(parse_information_elements(&bytes).len(), 1);
+    }
+
+    #[test]
+    fn truncating_the_beacon_never_adds_elements(cut in 0..BEACON_IES.len()) {
+        let all = parse_information_elements(BEACON_IES);
+        let truncated = parse_information_elements(&BEACON_IES[..cut]);
+        prop_assert!(truncated.len() < all.len());
+        prop_assert_eq!(&truncated[..], &all[..truncated.len()]);
+    }
+}
@@ -0,0 +1,476 @@
+use neli::consts::genl::{CtrlAttr, CtrlAttrMcastGrp, CtrlCmd, NlAttrType};
+use neli::consts::nl::{NlmF, NlmFFlags};
+use neli::consts::rtnl::{Arphrd, IffFlags, Ifla, RtAddrFamily, Rtm};
+use neli::genl::{Genlmsghdr, Nlattr};
+use neli::nl::{NlPayload, Nlmsghdr};
+use neli::rtnl::{Ifinfomsg, Rtattr};
+use neli::types::{Buffer, GenlBuffer, RtBuffer};
+use neli::{Size, ToBytes};
+use nl80211_ng::attr::{
+    Nl80211Attr, Nl80211BandAttr, Nl80211Bandc, Nl80211FrequencyAttr, Nl80211Iftype, Operstate,
+};
+use nl80211_ng::capture::describe_datagram;
+use nl80211_ng::channels::WiFiBand;
+use nl80211_ng::cmd::Nl80211Cmd;
+use nl80211_ng::ntsocket::NtSocket;
+use nl80211_ng::rtsocket::RtSocket;
+use nl80211_ng::transport::{message_bytes, MockReply, MockTransport, Recorder};
+use nl80211_ng::{Interface, InterfaceId, Nl80211Error, PcapWriter, ScanRequest};
+
+const FAMILY_ID: u16 = 0x1c;
+const SCAN_GROUP: u32 = 7;
+
+fn attr<T: NlAttrType, P: Size + ToBytes>(ty: T, payload: P) -> Nlattr<T, Buffer> {
+    Nlattr::new(false, false, ty, payload).unwrap()
+}
+
+fn nested<T: NlAttrType, C: NlAttrType>(
+    ty: T,
+    children: Vec<Nlattr<C, Buffer>>,
+) -> Nlattr<T, Buffer> {
+    let mut nested = Nlattr::new(false, false, ty, Buffer::new()).unwrap();
+    for child in children {
+        nested.add_nested_attribute(&child).unwrap();
+    }
+    nested
+}
+
+/// A reply to the pending request, seq 0 turns it into a notification.
+fn genl_message<C, A>(
+    msg_type: u16,
+    seq: u32,
+    multi: bool,
+    cmd: C,
+    version: u8,
+    attrs: Vec<Nlattr<A, Buffer>>,
+) -> Vec<u8>
+where
+    C: neli::consts::genl::Cmd,
+    A: NlAttrType,
+{
+    let flags = if multi { vec![NlmF::Multi] } else { vec![] };
+    let payload = Genlmsghdr::new(
+        cmd,
+        version,
+        attrs.into_iter().collect::<GenlBuffer<A, Buffer>>(),
+    );
+    message_bytes(&Nlmsghdr::new(
+        None,
+        msg_type,
+        NlmFFlags::new(&flags),
+        Some(seq),
+        None,
+        NlPayload::Payload(payload),
+    ))
+    .unwrap()
+}
+
+fn nl80211_message(
+    cmd: Nl80211Cmd,
+    seq: u32,
+    multi: bool,
+    attrs: Vec<Nlattr<Nl80211Attr, Buffer>>,
+) -> Vec<u8> {
+    genl_message(FAMILY_ID, seq, multi, cmd, 1, attrs)
+}
+
+fn family_reply() -> Vec<MockReply> {
+    vec![
+        MockReply::Datagram(genl_message(
+            libc::GENL_ID_CTRL as u16,
+            1,
+            false,
+            CtrlCmd::Newfamily,
+            2,
+            vec![
+                attr(CtrlAttr::FamilyName, "nl80211"),
+                attr(CtrlAttr::FamilyId, FAMILY_ID),
+                nested(
+                    CtrlAttr::McastGroups,
+                    vec![nested(
+                        1u16,
+                        vec![
+                            attr(CtrlAttrMcastGrp::Name, "scan"),
+                            attr(CtrlAttrMcastGrp::Id, SCAN_GROUP),
+                        ],
+                    )],
+                ),
+            ],
+        )),
+        MockReply::Ack,
+    ]
+}
+
+fn interface_message(index: u32, name: &str, iftype: Nl80211Iftype) -> Vec<u8> {
+    nl80211_message(
+        Nl80211Cmd::CmdNewInterface,
+        1,
+        true,
+        vec![
+            attr(Nl80211Attr::AttrIfindex, index),
+            attr(Nl80211Attr::AttrIfname, name),
+            attr(Nl80211Attr::AttrWiphy, 0u32),
+            attr(Nl80211Attr::AttrIftype, u16::from(iftype) as u32),
+            attr(Nl80211Attr::AttrMac, &[0x02u8, 0, 0, 0, 0, index as u8][..]),
+        ],
+    )
+}
+
+/// A mock that answers the nl80211 family lookup `NtSocket::from_transport` starts with.
+fn nl80211_mock() -> MockTransport {
+    let mut mock = MockTransport::new();
+    mock.expect(family_reply());
+    mock
+}
+
+fn nt_socket(mock: MockTransport) -> NtSocket<MockTransport> {
+    NtSocket::from_transport(mock).unwrap()
+}
+
+#[test]
+fn resolves_the_nl80211_family() {
+    let socket = nt_socket(nl80211_mock());
+    assert!(socket.transport().is_done());
+    assert_eq!(socket.transport().requests().len(), 1);
+}
+
+#[test]
+fn parses_an_interface_dump() {
+    let mut mock = nl80211_mock();
+    let mut dump = interface_message(3, "wlan0", Nl80211Iftype::IftypeStation);
+    dump.extend(interface_message(4, "mon0", Nl80211Iftype::IftypeMonitor));
+    mock.expect(vec![MockReply::Datagram(dump), MockReply::Done]);
+    let mut socket = nt_socket(mock);
+
+    let interfaces = socket.cmd_get_interfaces().unwrap();
+    assert_eq!(interfaces.len(), 2);
+    let wlan0: &Interface = &interfaces[&InterfaceId::Index(3)];
+    assert_eq!(wlan0.name_as_string(), "wlan0");
+    assert_eq!(wlan0.current_iftype, Some(Nl80211Iftype::IftypeStation));
+    assert_eq!(wlan0.mac, Some(vec![0x02, 0, 0, 0, 0, 3]));
+    let mon0 = &interfaces[&InterfaceId::Index(4)];
+    assert_eq!(mon0.current_iftype, Some(Nl80211Iftype::IftypeMonitor));
+    assert!(socket.transport().is_done());
+}
+
+#[test]
+fn dumps_are_resent_after_an_overrun() {
+    let mut mock = nl80211_mock();
+    mock.expect(vec![MockReply::Overrun]);
+    mock.expect(vec![
+        MockReply::Datagram(interface_message(3, "wlan0", Nl80211Iftype::IftypeStation)),
+        MockReply::Done,
+    ]);
+    let mut socket = nt_socket(mock);
+
+    let interfaces = socket.cmd_get_interfaces().unwrap();
+    assert_eq!(interfaces.len(), 1);
+    assert!(socket.transport().is_done());
+    assert_eq!(socket.transport().requests().len(), 3);
+}
+
+#[test]
+fn changes_are_not_resent_after_an_overrun() {
+    let mut mock = nl80211_mock();
+    mock.expect(vec![MockReply::Overrun]);
+    let mut socket = nt_socket(mock);
+
+    let err = socket.delete_interface(3).unwrap_err();
+    assert_eq!(err.errno(), Some(libc::ENOBUFS));
+    assert_eq!(err.command(), Some("CMD_DEL_INTERFACE"));
+    assert_eq!(socket.transport().requests().len(), 2);
+}
+
+#[test]
+fn merges_a_split_wiphy_dump() {
+    let channel = |frequency: u32| {
+        nested(
+            0u16,
+            vec![attr(Nl80211FrequencyAttr::FrequencyAttrFreq, frequency)],
+        )
+    };
+    let band = |band: Nl80211Bandc, frequencies: &[u32]| {
+        let mut nested_band = Nlattr::new(false, false, band, Buffer::new()).unwrap();
+        let freqs = nested(
+            Nl80211BandAttr::BandAttrFreqs,
+            frequencies
+                .iter()
+                .map(|frequency| channel(*frequency))
+                .collect(),
+        );
+        nested_band.add_nested_attribute(&freqs).unwrap();
+        nested(Nl80211Attr::AttrWiphyBands, vec![nested_band])
+    };
+    let mut iftypes = Vec::new();
+    for iftype in [Nl80211Iftype::IftypeStation, Nl80211Iftype::IftypeMonitor] {
+        iftypes.extend_from_slice(&4u16.to_ne_bytes());
+        iftypes.extend_from_slice(&u16::from(iftype).to_ne_bytes());
+    }
+
+    let mut mock = nl80211_mock();
+    mock.expect(vec![
+        MockReply::Datagram(nl80211_message(
+            Nl80211Cmd::CmdNewWiphy,
+            1,
+            true,
+            vec![
+                attr(Nl80211Attr::AttrWiphy, 0u32),
+                attr(Nl80211Attr::AttrWiphyName, "phy0"),
+                attr(Nl80211Attr::AttrSupportedIftypes, iftypes),
+            ],
+        )),
+        MockReply::Datagram(nl80211_message(
+            Nl80211Cmd::CmdNewWiphy,
+            1,
+            true,
+            vec![
+                attr(Nl80211Attr::AttrWiphy, 0u32),
+                attr(Nl80211Attr::AttrWiphyName, "phy0"),
+                band(Nl80211Bandc::Band2ghz, &[2412, 2437]),
+            ],
+        )),
+        MockReply::Datagram(nl80211_message(
+            Nl80211Cmd::CmdNewWiphy,
+            1,
+            true,
+            vec![
+                attr(Nl80211Attr::AttrWiphy, 0u32),
+                attr(Nl80211Attr::AttrWiphyName, "phy0"),
+                band(Nl80211Bandc::Band2ghz, &[2462]),
+            ],
+        )),
+        MockReply::Done,
+    ]);
+    let mut socket = nt_socket(mock);
+
+    let phy = socket.cmd_get_wiphy(0).unwrap();
+    assert_eq!(phy.phy_name.as_deref(), Some("phy0"));
+    assert_eq!(
+        phy.iftypes,
+        Some(vec![
+            Nl80211Iftype::IftypeStation,
+            Nl80211Iftype::IftypeMonitor
+        ])
+    );
+    let bands = phy.frequency_list.unwrap();
+    assert_eq!(bands.len(), 1);
+    assert_eq!(bands[0].band, WiFiBand::Band2GHz);
+    let channels: Vec<u32> = bands[0]
+        .channels
+        .iter()
+        .map(|channel| channel.channel)
+        .collect();
+    assert_eq!(channels, vec![1, 6, 11]);
+}
+
+#[test]
+fn maps_kernel_errors() {
+    let mut mock = nl80211_mock();
+    mock.expect(vec![MockReply::Error(libc::ENODEV)]);
+    let mut socket = nt_socket(mock);
+
+    let err = socket.cmd_get_interface(42).unwrap_err();
+    assert!(err.is_no_device());
+    assert!(err.is_not_found());
+    assert_eq!(err.command(), Some("CMD_GET_INTERFACE"));
+}
+
+#[test]
+fn ignores_notifications_between_replies() {
+    let mut mock = nl80211_mock();
+    mock.expect(vec![
+        MockReply::Datagram(nl80211_message(
+            Nl80211Cmd::CmdDelInterface,
+            0,
+            false,
+            vec![attr(Nl80211Attr::AttrIfindex, 9u32)],
+        )),
+        MockReply::Datagram(interface_message(3, "wlan0", Nl80211Iftype::IftypeStation)),
+        MockReply::Done,
+    ]);
+    let mut socket = nt_socket(mock);
+
+    let interfaces = socket.cmd_get_interfaces().unwrap();
+    assert_eq!(interfaces.len(), 1);
+    assert!(interfaces.contains_key(&InterfaceId::Index(3)));
+}
+
+#[test]
+fn times_out_without_a_reply() {
+    let mut mock = nl80211_mock();
+    mock.expect(vec![MockReply::Datagram(interface_message(
+        3,
+        "wlan0",
+        Nl80211Iftype::IftypeStation,
+    ))]);
+    let mut socket = nt_socket(mock);
+
+    let err = socket.cmd_get_interfaces().unwrap_err();
+    assert_eq!(err.errno(), Some(libc::ETIMEDOUT));
+}
+
+#[test]
+fn fails_requests_the_mock_has_no_reply_for() {
+    let mut socket = nt_socket(nl80211_mock());
+    let err = socket.delete_interface(3).unwrap_err();
+    assert!(matches!(
+        err,
+        Nl80211Error::Socket {
+            command: "CMD_DEL_INTERFACE",
+            ..
+        }
+    ));
+}
+
+fn scan_notification(cmd: Nl80211Cmd, index: u32) -> Vec<u8> {
+    nl80211_message(cmd, 0, false, vec![attr(Nl80211Attr::AttrIfindex, index)])
+}
+
+#[test]
+fn waits_for_the_scan_of_the_interface() {
+    let mut mock = nl80211_mock();
+    mock.expect(vec![
+        // Another interface's scan finishing before the ack must not end the wait
+        MockReply::Datagram(scan_notification(Nl80211Cmd::CmdNewScanResults, 4)),
+        MockReply::Ack,
+        MockReply::Datagram(scan_notification(Nl80211Cmd::CmdTriggerScan, 3)),
+        MockReply::Datagram(scan_notification(Nl80211Cmd::CmdNewScanResults, 3)),
+    ]);
+    mock.expect(vec![
+        MockReply::Ack,
+        MockReply::Datagram(scan_notification(Nl80211Cmd::CmdScanAborted, 3)),
+    ]);
+    let mut socket = nt_socket(mock);
+
+    socket.trigger_scan(3, ScanRequest::default()).unwrap();
+    assert!(socket.transport().groups().is_empty());
+    let err = socket.trigger_scan(3, ScanRequest::default()).unwrap_err();
+    assert_eq!(err, Nl80211Error::ScanAborted);
+    assert!(socket.transport().is_done());
+}
+
+#[test]
+fn scan_times_out_without_a_notification() {
+    let mut mock = nl80211_mock();
+    mock.expect(vec![
+        MockReply::Ack,
+        MockReply::Datagram(scan_notification(Nl80211Cmd::CmdNewScanResults, 4)),
+    ]);
+    let mut socket = nt_socket(mock);
+
+    let err = socket.trigger_scan(3, ScanRequest::default()).unwrap_err();
+    assert_eq!(err.errno(), Some(libc::ETIMEDOUT));
+    assert_eq!(err.command(), Some("CMD_TRIGGER_SCAN"));
+    assert!(socket.transport().groups().is_empty());
+}
+
+fn link_message(index: i32, operstate: u8) -> Vec<u8> {
+    let mut attrs = RtBuffer::new();
+    attrs.push(Rtattr::new(None, Ifla::Ifname, "wlan0").unwrap());
+    attrs.push(Rtattr::new(None, Ifla::Operstate, operstate).unwrap());
+    message_bytes(&Nlmsghdr::new(
+        None,
+        Rtm::Newlink,
+        NlmFFlags::empty(),
+        Some(1),
+        None,
+        NlPayload::Payload(Ifinfomsg::new(
+            RtAddrFamily::Unspecified,
+            Arphrd::Ether,
+            index,
+            IffFlags::empty(),
+            IffFlags::empty(),
+            attrs,
+        )),
+    ))
+    .unwrap()
+}
+
+#[test]
+fn reads_the_operstate() {
+    let mut mock = MockTransport::new();
+    mock.expect(vec![
+        MockReply::Datagram(link_message(3, 6)),
+        MockReply::Ack,
+    ]);
+    mock.expect(vec![MockReply::Error(libc::ENODEV)]);
+    let mut socket = RtSocket::from_transport(mock);
+
+    assert_eq!(socket.get_interface_status(3).unwrap(), Operstate::Up);
+    let err = socket.get_interface_status(9999).unwrap_err();
+    assert!(err.is_no_device());
+    assert_eq!(err.command(), Some("RTM_GETLINK"));
+}
+
+#[test]
+fn replays_recorded_fixtures() {
+    let path = std::env::temp_dir().join(format!("nl80211-ng-fixture-{}", std::process::id()));
+    let mut mock = MockTransport::new();
+    mock.expect(vec![
+        MockReply::Datagram(link_message(3, 2)),
+        MockReply::Ack,
+    ]);
+    let mut socket = RtSocket::from_transport(Recorder::create(mock, &path).unwrap());
+    assert_eq!(socket.get_interface_status(3).unwrap(), Operstate::Down);
+    drop(socket);
+
+    let mut socket = RtSocket::from_transport(MockTransport::load(&path).unwrap());
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(socket.get_interface_status(3).unwrap(), Operstate::Down);
+    assert!(socket.transport().is_done());
+}
+
+#[test]
+fn malformed_fixtures_are_errors() {
+    for fixture in ["é 0a0b", "? 0a0b", "> 0a0b\n< zz"] {
+        assert!(matches!(
+            MockTransport::from_fixture(fixture),
+            Err(Nl80211Error::Parse(_))
+        ));
+    }
+    assert!(MockTransport::from_fixture("# recorded\n\n>0a0b\n< 0c0d\n").is_ok());
+}
+
+#[test]
+fn captures_traffic_to_pcap() {
+    let path = std::env::temp_dir().join(format!("nl80211-ng-capture-{}.pcap", std::process::id()));
+    let mut mock = nl80211_mock();
+    // The family is resolved again once capturing, for Wireshark
+    mock.expect(family_reply());
+    mock.expect(vec![
+        MockReply::Datagram(interface_message(3, "wlan0", Nl80211Iftype::IftypeStation)),
+        MockReply::Done,
+    ]);
+    let mut socket = nt_socket(mock);
+    socket
+        .set_capture(Some(PcapWriter::create(&path).unwrap()))
+        .unwrap();
+    socket.cmd_get_interfaces().unwrap();
+
+    let pcap = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(u32::from_ne_bytes(pcap[20..24].try_into().unwrap()), 253);
+    let mut records = Vec::new();
+    let mut position = 24;
+    while position < pcap.len() {
+        let len =
+            u32::from_ne_bytes(pcap[position + 8..position + 12].try_into().unwrap()) as usize;
+        records.push(&pcap[position + 16..position + 16 + len]);
+        position += 16 + len;
+    }
+    // Family request, its reply and ack, then the dump request, its reply and NLMSG_DONE
+    assert_eq!(records.len(), 6);
+    let outgoing: Vec<bool> = records
+        .iter()
+        .map(|record| record[0..2] == [0, 4])
+        .collect();
+    assert_eq!(outgoing, vec![true, false, false, true, false, false]);
+    assert!(records
+        .iter()
+        .all(|record| record[14..16] == (libc::NETLINK_GENERIC as u16).to_be_bytes()));
+
+    let description = describe_datagram(libc::NETLINK_GENERIC as u16, &records[4][16..]);
+    assert!(description.contains("CmdNewInterface"));
+    assert!(description.contains("AttrIfname \"wlan0\""));
+    assert!(description.contains("AttrIfindex 3"));
+}